@@ -1,18 +1,111 @@
+use std::collections::HashSet;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-use log::info;
+use log::{debug, error, info};
 use serde::{Deserialize, Serialize};
 
+use crate::syspass::Account;
 use crate::types::{EmptyResult, OperationResult};
 
 pub const ACCOUNTS_SET_CACHE_FILENAME: &str = "accounts-set.cache";
 pub const ACCOUNTS_GET_CACHE_FILENAME: &str = "accounts-get.cache";
+pub const ACCOUNTS_EXPORT_CACHE_FILENAME: &str = "accounts-export.cache";
+pub const ACCOUNTS_APPLY_CACHE_FILENAME: &str = "accounts-apply.cache";
+pub const ACCOUNTS_AUDIT_CACHE_FILENAME: &str = "accounts-audit.cache";
+pub const ACCOUNTS_RECONCILE_CACHE_FILENAME: &str = "accounts-reconcile.cache";
+
+/// Command progress, saved into cache file
+#[derive(Serialize,Deserialize,PartialEq,Debug,Clone)]
+pub struct CommandProgress<T = Account> {
+    /// Accounts which have been processed already
+    pub processed: Vec<Account>,
+
+    /// Command results, i.e. accounts with empty permissions
+    pub results: Vec<T>,
+}
+
+impl<T> Default for CommandProgress<T> {
+    fn default() -> Self {
+        CommandProgress {
+            processed: vec![],
+            results: vec![],
+        }
+    }
+}
+
+/// Command progress shared between workers.
+///
+/// Progress is saved into cache file every N processed accounts.
+pub struct SharedProgress<T = Account> {
+    state: Mutex<SharedProgressState<T>>,
+    cache_file: PathBuf,
+    save_every: u16,
+}
+
+struct SharedProgressState<T> {
+    progress: CommandProgress<T>,
+    processed: HashSet<Account>,
+    unsaved_items: u16,
+}
+
+impl<T: Serialize + Clone> SharedProgress<T> {
+    pub fn new(progress: CommandProgress<T>, cache_file: &Path, save_every: u16) -> SharedProgress<T> {
+        let processed = progress.processed.iter().cloned().collect();
+
+        SharedProgress {
+            state: Mutex::new(SharedProgressState {
+                progress,
+                processed,
+                unsaved_items: 0,
+            }),
+            cache_file: cache_file.to_path_buf(),
+            save_every,
+        }
+    }
+
+    pub fn is_processed(&self, account: &Account) -> bool {
+        let state = self.state.lock().expect("progress lock error");
+        state.processed.contains(account)
+    }
+
+    /// Mark account as processed, `result` is added to command results
+    pub fn add_processed(&self, account: &Account, result: Option<T>) {
+        let mut state = self.state.lock().expect("progress lock error");
+
+        if state.processed.insert(account.clone()) {
+            state.progress.processed.push(account.clone());
+        }
+
+        if let Some(result) = result {
+            state.progress.results.push(result);
+        }
+
+        state.unsaved_items += 1;
+        debug!("unsaved progress items: {}", state.unsaved_items);
+
+        if state.unsaved_items >= self.save_every {
+            match save_cache_data_into_file(&state.progress, &self.cache_file) {
+                Ok(_) => {
+                    info!("progress cache has been updated");
+                    state.unsaved_items = 0;
+                },
+                Err(e) => error!("cannot update progress cache: {}", e)
+            }
+        }
+    }
+
+    pub fn get_progress(&self) -> CommandProgress<T> {
+        let state = self.state.lock().expect("progress lock error");
+        state.progress.clone()
+    }
+}
 
 pub fn save_cache_data_into_file<T: Serialize>(data: &T, filepath: &Path) -> EmptyResult {
     info!("save data into cache-file '{}'", filepath.display());
     let content = serde_json::to_string(data)?;
-    fs::write(&filepath, &content)?;
+    fs::write(filepath, &content)?;
     Ok(())
 }
 
@@ -27,10 +120,51 @@ pub fn load_cache_data_from_file<T: for<'a> Deserialize<'a>>(filepath: &Path) ->
 mod tests {
     use tempfile::NamedTempFile;
 
-    use crate::cache::{load_cache_data_from_file, save_cache_data_into_file};
+    use crate::cache::{CommandProgress, load_cache_data_from_file, save_cache_data_into_file, SharedProgress};
     use crate::syspass::Account;
     use crate::tests::account::get_sample_account;
 
+    #[test]
+    fn progress_should_be_saved_every_n_items() {
+        let file = NamedTempFile::new().unwrap();
+        let file_path = file.path();
+
+        let progress = SharedProgress::new(CommandProgress::default(), file_path, 2);
+
+        let account1 = get_sample_account();
+        let account2 = get_sample_account();
+        let account3 = get_sample_account();
+
+        progress.add_processed(&account1, None);
+        progress.add_processed(&account2, Some(account2.clone()));
+        progress.add_processed(&account3, None);
+
+        let saved: CommandProgress = load_cache_data_from_file(file_path).unwrap();
+
+        assert_eq!(vec![account1.clone(), account2.clone()], saved.processed);
+        assert_eq!(vec![account2.clone()], saved.results);
+
+        assert!(progress.is_processed(&account3));
+        assert_eq!(3, progress.get_progress().processed.len());
+    }
+
+    #[test]
+    fn processed_accounts_should_be_loaded_from_previous_progress() {
+        let file = NamedTempFile::new().unwrap();
+
+        let account = get_sample_account();
+
+        let previous_progress: CommandProgress = CommandProgress {
+            processed: vec![account.clone()],
+            results: vec![],
+        };
+
+        let progress = SharedProgress::new(previous_progress, file.path(), 10);
+
+        assert!(progress.is_processed(&account));
+        assert!(!progress.is_processed(&get_sample_account()));
+    }
+
     #[test]
     fn save_and_load_accounts() {
         let account1 = get_sample_account();
@@ -42,8 +176,8 @@ mod tests {
         let file = NamedTempFile::new().unwrap();
         let file_path = file.path();
 
-        save_cache_data_into_file(&accounts, &file_path).unwrap();
-        let results: Vec<Account> = load_cache_data_from_file(&file_path).unwrap();
+        save_cache_data_into_file(&accounts, file_path).unwrap();
+        let results: Vec<Account> = load_cache_data_from_file(file_path).unwrap();
 
         assert_eq!(accounts, results);
     }
@@ -55,8 +189,8 @@ mod tests {
         let file = NamedTempFile::new().unwrap();
         let file_path = file.path();
 
-        save_cache_data_into_file(&account, &file_path).unwrap();
-        let results: Account = load_cache_data_from_file(&file_path).unwrap();
+        save_cache_data_into_file(&account, file_path).unwrap();
+        let results: Account = load_cache_data_from_file(file_path).unwrap();
 
         assert_eq!(account, results);
     }
@@ -2,26 +2,64 @@ use std::fmt::{Display, Formatter};
 use std::fs;
 use std::path::Path;
 
+use anyhow::anyhow;
 use log::info;
-use serde::Deserialize;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use serde::de::Error;
 
-use crate::types::OperationResult;
+use crate::syspass::selectors::{AUTO_VERSION, get_ui_selectors};
+use crate::types::{EmptyResult, OperationResult};
 
 #[derive(Deserialize,PartialEq,Debug)]
 pub struct AppConfig {
     #[serde(rename(deserialize = "syspass-url"))]
     pub syspass_url: String,
 
+    /// sysPass JSON-RPC API url, i.e. `http://localhost:18080/api.php`
+    #[serde(rename(deserialize = "api-url"), default)]
+    pub api_url: Option<String>,
+
+    /// API token with permissions for account search/view, categories and clients
+    #[serde(rename(deserialize = "api-token"), default)]
+    pub api_token: Option<String>,
+
     pub webdriver: WebDriverConfig,
 
     pub auth: AuthConfig,
 
+    /// Password for encrypted xml exports, `SPT_XML_PASSWORD` env variable or prompt are used if not set
+    #[serde(rename(deserialize = "xml-password"), default)]
+    pub xml_password: Option<String>,
+
+    /// Key for xml export signature check, file with missing or invalid hash is rejected if set
+    #[serde(rename(deserialize = "xml-sign-key"), default)]
+    pub xml_sign_key: Option<String>,
+
+    /// Fail on any xml structure problem (missing id, unknown client, etc.), otherwise invalid entities are skipped
+    #[serde(rename(deserialize = "xml-strict"), default)]
+    pub xml_strict: bool,
+
     #[serde(rename(deserialize = "ignore-errors"))]
     pub ignore_errors: bool,
 
-    pub permissions: PermissionsConfig,
+    /// Fail account if user or group wasn't applied on edit page, otherwise just log warning
+    #[serde(default)]
+    pub strict: bool,
+
+    /// Default permissions, applied if no rule matches account
+    #[serde(default)]
+    pub permissions: Option<PermissionsConfig>,
+
+    /// Ordered rules, the first matching rule wins
+    #[serde(rename(deserialize = "permission-rules"), default)]
+    pub permission_rules: Vec<PermissionsRuleConfig>,
 
-    pub delays: DelaysConfig,
+    #[serde(default)]
+    pub wait: WaitConfig,
+
+    #[serde(default)]
+    pub ui: UiConfig,
 
     #[serde(rename(deserialize = "progress-cache"))]
     pub progress_cache: CommandProgressCacheConfig
@@ -31,10 +69,19 @@ impl Display for AppConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "<AppConfig>")?;
         write!(f, "syspass-url: '{}', webdriver-url: '{}', ", self.syspass_url, self.webdriver)?;
-        write!(f, "ignore-errors: {}, ", self.ignore_errors)?;
-        write!(f, "auth: {}", self.auth)?;
-        write!(f, "permissions: {}", self.permissions)?;
-        write!(f, "delays: {}", self.delays)?;
+        write!(f, "api-url: '{}', api-token: '*********', ", self.api_url.as_deref().unwrap_or(""))?;
+        write!(f, "xml-password: '{}', ", if self.xml_password.is_some() { "*********" } else { "none" })?;
+        write!(f, "xml-sign-key: '{}', xml-strict: {}, ",
+               if self.xml_sign_key.is_some() { "*********" } else { "none" }, self.xml_strict)?;
+        write!(f, "ignore-errors: {}, strict: {}, ", self.ignore_errors, self.strict)?;
+        write!(f, "auth: {}, ", self.auth)?;
+        match &self.permissions {
+            Some(permissions) => write!(f, "permissions: {}, ", permissions)?,
+            None => write!(f, "permissions: none, ")?
+        }
+        write!(f, "permission-rules: {:?}, ", self.permission_rules)?;
+        write!(f, "wait: {}, ", self.wait)?;
+        write!(f, "ui: {}, ", self.ui)?;
         write!(f, "progress-cache: {}", self.progress_cache)?;
         write!(f, "</AppConfig>")
     }
@@ -44,11 +91,24 @@ impl Display for AppConfig {
 pub struct WebDriverConfig {
     pub url: String,
     pub args: Vec<String>,
+
+    /// Max session restarts after fatal errors (page crash, etc.) per command run
+    #[serde(rename(deserialize = "max-restarts"), default = "get_default_max_restarts")]
+    pub max_restarts: u16,
+
+    /// Open fresh session every N accounts, limits browser memory usage
+    #[serde(rename(deserialize = "recycle-after"), default)]
+    pub recycle_after: Option<u16>,
+}
+
+fn get_default_max_restarts() -> u16 {
+    3
 }
 
 impl Display for WebDriverConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "<WebDriverConfig> url '{}', args: '{:?}'</WebDriverConfig>", self.url, self.args)
+        write!(f, "<WebDriverConfig> url '{}', args: '{:?}', max-restarts: {}, recycle-after: {:?}</WebDriverConfig>",
+               self.url, self.args, self.max_restarts, self.recycle_after)
     }
 }
 
@@ -98,41 +158,137 @@ impl Display for PermissionsConfig {
 pub struct EntityPermissionsConfig {
     pub view: Vec<String>,
     pub edit: Vec<String>,
+
+    /// How `view` and `edit` lists are applied to existing permissions
+    #[serde(default)]
+    pub mode: PermissionsMode,
+}
+
+#[derive(Deserialize,PartialEq,Debug,Clone,Copy,Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionsMode {
+    /// Add listed users/groups, keep existing ones
+    #[default]
+    Add,
+
+    /// Remove existing users/groups, then add listed ones
+    Replace,
+
+    /// Remove listed users/groups only
+    Remove,
+}
+
+#[derive(Deserialize,PartialEq,Debug)]
+pub struct PermissionsRuleConfig {
+    #[serde(rename(deserialize = "match"))]
+    pub matcher: AccountMatcherConfig,
+
+    pub permissions: PermissionsConfig,
+}
+
+/// Account matches if all provided conditions are met.
+/// Matcher without conditions matches any account.
+#[derive(Deserialize,PartialEq,Debug,Default)]
+#[serde(default)]
+pub struct AccountMatcherConfig {
+    pub category: Option<ValueMatcherConfig>,
+    pub client: Option<ValueMatcherConfig>,
+    pub login: Option<ValueMatcherConfig>,
+    pub name: Option<ValueMatcherConfig>,
+}
+
+#[derive(Deserialize,PartialEq,Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueMatcherConfig {
+    Exact(String),
+    Prefix(String),
+    Regex(RegexMatcher),
+}
+
+/// Regex pattern, it's compiled and validated once on config load
+#[derive(Debug,Clone)]
+pub struct RegexMatcher(Regex);
+
+impl RegexMatcher {
+    pub fn new(pattern: &str) -> OperationResult<RegexMatcher> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| anyhow!("invalid regex '{}' in permission rule: {}", pattern, e))?;
+        Ok(RegexMatcher(regex))
+    }
+
+    pub fn is_match(&self, value: &str) -> bool {
+        self.0.is_match(value)
+    }
+}
+
+impl PartialEq for RegexMatcher {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl<'de> Deserialize<'de> for RegexMatcher {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        RegexMatcher::new(&pattern).map_err(D::Error::custom)
+    }
 }
 
+/// Conditions (element is present, search results are rendered, etc.) polling
 #[derive(Deserialize,PartialEq,Debug)]
-pub struct DelaysConfig {
+pub struct WaitConfig {
 
-    /// Delay after success login into sysPass
-    #[serde(rename(deserialize = "after-login"))]
-    pub after_login: u64,
+    /// Max time to wait for condition, milliseconds
+    pub timeout: u64,
 
-    /// Delay after redirect to index page
-    #[serde(rename(deserialize = "after-redirect-to-index"))]
-    pub after_redirect_to_index: u64,
+    /// Check condition every N milliseconds
+    #[serde(rename(deserialize = "poll-interval"))]
+    pub poll_interval: u64
+}
 
-    /// Delay after redirect to edit page
-    #[serde(rename(deserialize = "after-redirect-to-edit"))]
-    pub after_redirect_to_edit: u64,
+impl Default for WaitConfig {
+    fn default() -> Self {
+        WaitConfig {
+            timeout: 10000,
+            poll_interval: 100,
+        }
+    }
+}
 
-    /// Delay after search
-    #[serde(rename(deserialize = "after-search"))]
-    pub after_search: u64,
+impl Display for WaitConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<Wait> timeout: {} (ms), poll-interval: {} (ms)</Wait>", self.timeout, self.poll_interval)
+    }
+}
 
-    /// Delay after menu open
-    #[serde(rename(deserialize = "menu-open"))]
-    pub menu_open: u64
+/// Web ui selectors profile
+#[derive(Deserialize,PartialEq,Debug)]
+pub struct UiConfig {
+    /// sysPass version for built-in selectors profile, `auto` - detect after login
+    #[serde(default = "get_default_ui_version")]
+    pub version: String,
+
+    /// Custom selectors profile (yaml), has priority over `version`
+    #[serde(rename(deserialize = "selectors-file"), default)]
+    pub selectors_file: Option<String>,
 }
 
-impl Display for DelaysConfig {
+fn get_default_ui_version() -> String {
+    AUTO_VERSION.to_string()
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        UiConfig {
+            version: get_default_ui_version(),
+            selectors_file: None,
+        }
+    }
+}
+
+impl Display for UiConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "<Delays>")?;
-        write!(f, "after-login: {} (ms)", self.after_login)?;
-        write!(f, "after-redirect-to-index: {} (ms)", self.after_redirect_to_index)?;
-        write!(f, "after-redirect-to-edit: {} (ms)", self.after_redirect_to_edit)?;
-        write!(f, "after-search: {} (ms)", self.after_search)?;
-        write!(f, "menu-open: {} (ms)", self.menu_open)?;
-        write!(f, "</Delays>")
+        write!(f, "<Ui> version: '{}', selectors-file: {:?}</Ui>", self.version, self.selectors_file)
     }
 }
 
@@ -162,19 +318,48 @@ pub fn load_config_from_file(file_path: &Path) -> OperationResult<AppConfig> {
     info!("load config from file '{}'", file_path.display());
     let content = fs::read_to_string(file_path)?;
     let config: AppConfig = serde_yaml::from_str(&content)?;
+    validate_config(&config)?;
     info!("config:");
-    info!("{:?}", config);
+    info!("{}", config);
     Ok(config)
 }
 
+fn validate_config(config: &AppConfig) -> EmptyResult {
+    if config.permissions.is_none() && config.permission_rules.is_empty() {
+        return Err(anyhow!("config error: 'permissions' or 'permission-rules' are required"))
+    }
+
+    get_ui_selectors(&config.ui).map_err(|e| anyhow!("config error: invalid ui selectors: {}", e))?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::Path;
 
     use fake::{Fake, Faker};
 
-    use crate::config::{AppConfig, AuthConfig, CommandProgressCacheConfig, DelaysConfig, EntityPermissionsConfig, load_config_from_file, PermissionsConfig, WebDriverConfig};
+    use crate::config::{AccountMatcherConfig, AppConfig, AuthConfig, CommandProgressCacheConfig, EntityPermissionsConfig, load_config_from_file, PermissionsConfig, PermissionsMode, PermissionsRuleConfig, RegexMatcher, UiConfig, validate_config, ValueMatcherConfig, WaitConfig, WebDriverConfig};
     use crate::CONFIG_FILE;
+    use crate::tests::config::get_test_config;
+
+    #[test]
+    fn secrets_should_be_masked_in_display_output() {
+        let mut config = get_test_config();
+        config.xml_password = Some("xml-pass-4f7a".to_string());
+        config.xml_sign_key = Some("sign-key-9c1e".to_string());
+        config.api_url = Some("http://localhost:18080/api.php".to_string());
+        config.api_token = Some("d3a4a8e6f1b1c9e2".to_string());
+
+        let output = config.to_string();
+
+        for secret in ["xml-pass-4f7a", "sign-key-9c1e", "d3a4a8e6f1b1c9e2", "2b34t45ynn968m"] {
+            assert!(!output.contains(secret), "secret '{}' is shown in '{}'", secret, output);
+        }
+
+        assert!(output.contains("api-url: 'http://localhost:18080/api.php'"));
+    }
 
     #[test]
     fn load_config_test() {
@@ -184,19 +369,26 @@ mod tests {
 
         let expected_config = AppConfig {
             syspass_url: "http://localhost:18080".to_string(),
+            api_url: Some("http://localhost:18080/api.php".to_string()),
+            api_token: Some("d3a4a8e6f1b1c9e2".to_string()),
 
             auth: AuthConfig {
                 login: "b2y63nu46n456".to_string(),
                 password: "2b34t45ynn968m".to_string(),
             },
             ignore_errors: true,
-            permissions: PermissionsConfig {
+            xml_password: None,
+            xml_sign_key: None,
+            xml_strict: false,
+            strict: false,
+            permissions: Some(PermissionsConfig {
                 user: EntityPermissionsConfig {
                     view: vec!["sysPass Admin".to_string()],
                     edit: vec![
                         "Mr.Editor".to_string(),
                         "sysPass Admin".to_string()
                     ],
+                    mode: PermissionsMode::Add,
                 },
                 group: EntityPermissionsConfig {
                     view: vec!["Admins".to_string()],
@@ -204,24 +396,50 @@ mod tests {
                         "Beta Group".to_string(),
                         "Demo group 1".to_string()
                     ],
+                    mode: PermissionsMode::Add,
                 },
                 owner: "Mr.Editor".to_string(),
                 main_group: "Demo group 1".to_string(),
                 private_account: false,
                 private_account_for_group: true
+            }),
+            permission_rules: vec![
+                PermissionsRuleConfig {
+                    matcher: AccountMatcherConfig {
+                        category: Some(ValueMatcherConfig::Exact("Production".to_string())),
+                        login: Some(ValueMatcherConfig::Regex(RegexMatcher::new("^db-[a-z]+$").unwrap())),
+                        ..AccountMatcherConfig::default()
+                    },
+                    permissions: PermissionsConfig {
+                        user: EntityPermissionsConfig {
+                            view: vec![],
+                            edit: vec!["sysPass Admin".to_string()],
+                            mode: PermissionsMode::Add,
+                        },
+                        group: EntityPermissionsConfig {
+                            view: vec![],
+                            edit: vec!["Admins".to_string()],
+                            mode: PermissionsMode::Replace,
+                        },
+                        owner: "sysPass Admin".to_string(),
+                        main_group: "Admins".to_string(),
+                        private_account: true,
+                        private_account_for_group: false
+                    },
+                }
+            ],
+            wait: WaitConfig {
+                timeout: 5000,
+                poll_interval: 200,
             },
-            delays: DelaysConfig {
-                after_login: 300,
-                after_redirect_to_index: 500,
-                after_redirect_to_edit: 500,
-                after_search: 500,
-                menu_open: 300,
-            },
+            ui: UiConfig::default(),
             webdriver: WebDriverConfig {
                 url: "http://localhost:9515".to_string(),
                 args: vec![
                     "--headless".to_string()
                 ],
+                max_restarts: 5,
+                recycle_after: Some(300),
             },
             progress_cache: CommandProgressCacheConfig {
                 set_accounts: 10,
@@ -232,6 +450,31 @@ mod tests {
         assert_eq!(config, expected_config);
     }
 
+    #[test]
+    fn return_error_for_invalid_regex_in_rule() {
+        let error = serde_yaml::from_str::<AccountMatcherConfig>("name: !regex '[a-z'").unwrap_err();
+
+        assert!(error.to_string().contains("invalid regex '[a-z' in permission rule"));
+        assert!(RegexMatcher::new("[a-z").is_err());
+    }
+
+    #[test]
+    fn return_error_if_no_permissions_configured() {
+        let mut config = load_config_from_file(Path::new("test-data").join(CONFIG_FILE).as_path()).unwrap();
+        config.permissions = None;
+        config.permission_rules.clear();
+
+        assert!(validate_config(&config).is_err());
+    }
+
+    #[test]
+    fn return_error_for_unsupported_ui_version() {
+        let mut config = load_config_from_file(Path::new("test-data").join(CONFIG_FILE).as_path()).unwrap();
+        config.ui.version = "2.1".to_string();
+
+        assert!(validate_config(&config).is_err());
+    }
+
     #[test]
     fn return_error_for_unknown_file() {
         let filename = Faker.fake::<String>();
@@ -0,0 +1,154 @@
+//! Decryption of data encrypted by sysPass with
+//! [defuse/php-encryption](https://github.com/defuse/php-encryption/blob/master/docs/CryptoDetails.md) v2:
+//! AES-256-CTR + HMAC-SHA256, keys are derived with HKDF-SHA256 (and PBKDF2-SHA256 for passwords).
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use anyhow::{anyhow, Context};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use crate::types::OperationResult;
+
+type HmacSha256 = Hmac<Sha256>;
+type Aes256Ctr = ctr::Ctr128BE<aes::Aes256>;
+
+const CIPHERTEXT_VERSION: [u8; 4] = [0xDE, 0xF5, 0x02, 0x00];
+const KEY_VERSION: [u8; 4] = [0xDE, 0xF0, 0x00, 0x00];
+const PASSWORD_KEY_VERSION: [u8; 4] = [0xDE, 0xF1, 0x00, 0x00];
+
+const SALT_SIZE: usize = 32;
+const IV_SIZE: usize = 16;
+const MAC_SIZE: usize = 32;
+const KEY_SIZE: usize = 32;
+
+const PBKDF2_ITERATIONS: u32 = 100000;
+
+const AUTHENTICATION_INFO: &[u8] = b"DefusePHP|V2|KeyForAuthentication";
+const ENCRYPTION_INFO: &[u8] = b"DefusePHP|V2|KeyForEncryption";
+
+pub const WRONG_PASSWORD_ERROR: &str = "wrong password or data is corrupted";
+
+/// Decrypt hex-encoded `data` with password protected key (`key` attribute of `<Data>` element in xml export)
+pub fn decrypt_with_secured_key(data: &str, secured_key: &str, password: &str) -> OperationResult<String> {
+    let key = unlock_secured_key(secured_key, password)?;
+
+    let ciphertext = hex::decode(data.trim()).context("encrypted data isn't hex-encoded")?;
+
+    let plaintext = decrypt(&ciphertext, &key)?;
+
+    Ok(String::from_utf8(plaintext)?)
+}
+
+/// Password protected key is a random key encrypted with sha256 of password
+fn unlock_secured_key(secured_key: &str, password: &str) -> OperationResult<Vec<u8>> {
+    let encrypted_key = load_checksummed_string(secured_key, &PASSWORD_KEY_VERSION)
+                                    .context("invalid encryption key")?;
+
+    let password_hash = Sha256::digest(password.as_bytes());
+
+    let key_encoded = decrypt_with_password(&encrypted_key, &password_hash)?;
+
+    load_checksummed_string(&String::from_utf8(key_encoded)?, &KEY_VERSION)
+}
+
+fn decrypt_with_password(ciphertext: &[u8], password: &[u8]) -> OperationResult<Vec<u8>> {
+    let salt = get_salt(ciphertext)?;
+
+    let prehash = Sha256::digest(password);
+
+    let mut prekey = [0u8; KEY_SIZE];
+    pbkdf2::pbkdf2_hmac::<Sha256>(&prehash, salt, PBKDF2_ITERATIONS, &mut prekey);
+
+    decrypt(ciphertext, &prekey)
+}
+
+/// Ciphertext format: version (4) | salt (32) | iv (16) | encrypted data | hmac (32)
+fn decrypt(ciphertext: &[u8], prekey: &[u8]) -> OperationResult<Vec<u8>> {
+    let salt = get_salt(ciphertext)?;
+
+    if ciphertext[..CIPHERTEXT_VERSION.len()] != CIPHERTEXT_VERSION {
+        return Err(anyhow!("unsupported ciphertext version"))
+    }
+
+    let mac_position = ciphertext.len() - MAC_SIZE;
+    let iv_position = CIPHERTEXT_VERSION.len() + SALT_SIZE;
+    let iv = &ciphertext[iv_position..iv_position + IV_SIZE];
+
+    let authentication_key = derive_key(prekey, AUTHENTICATION_INFO, salt)?;
+    let encryption_key = derive_key(prekey, ENCRYPTION_INFO, salt)?;
+
+    let mut mac = HmacSha256::new_from_slice(&authentication_key)?;
+    mac.update(&ciphertext[..mac_position]);
+    mac.verify_slice(&ciphertext[mac_position..]).map_err(|_| anyhow!(WRONG_PASSWORD_ERROR))?;
+
+    let mut plaintext = ciphertext[iv_position + IV_SIZE..mac_position].to_vec();
+
+    let mut cipher = Aes256Ctr::new_from_slices(&encryption_key, iv)
+                                            .map_err(|e| anyhow!("invalid key or iv: {}", e))?;
+    cipher.apply_keystream(&mut plaintext);
+
+    Ok(plaintext)
+}
+
+fn get_salt(ciphertext: &[u8]) -> OperationResult<&[u8]> {
+    let min_size = CIPHERTEXT_VERSION.len() + SALT_SIZE + IV_SIZE + MAC_SIZE;
+
+    if ciphertext.len() < min_size {
+        return Err(anyhow!("ciphertext is too short"))
+    }
+
+    Ok(&ciphertext[CIPHERTEXT_VERSION.len()..CIPHERTEXT_VERSION.len() + SALT_SIZE])
+}
+
+fn derive_key(prekey: &[u8], info: &[u8], salt: &[u8]) -> OperationResult<[u8; KEY_SIZE]> {
+    let mut key = [0u8; KEY_SIZE];
+    hkdf::Hkdf::<Sha256>::new(Some(salt), prekey).expand(info, &mut key)
+        .map_err(|e| anyhow!("key derivation error: {}", e))?;
+    Ok(key)
+}
+
+/// Hex-encoded: version (4) | data | sha256 of version and data (32)
+fn load_checksummed_string(value: &str, version: &[u8; 4]) -> OperationResult<Vec<u8>> {
+    let bytes = hex::decode(value.trim()).context("value isn't hex-encoded")?;
+
+    if bytes.len() < version.len() + MAC_SIZE {
+        return Err(anyhow!("value is too short"))
+    }
+
+    let checksum_position = bytes.len() - MAC_SIZE;
+
+    if Sha256::digest(&bytes[..checksum_position]).as_slice() != &bytes[checksum_position..] {
+        return Err(anyhow!("checksum mismatch"))
+    }
+
+    if &bytes[..version.len()] != version {
+        return Err(anyhow!("unsupported version"))
+    }
+
+    Ok(bytes[version.len()..checksum_position].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypt::{decrypt, load_checksummed_string, KEY_VERSION, WRONG_PASSWORD_ERROR};
+
+    #[test]
+    fn return_error_for_checksum_mismatch() {
+        let value = format!("def00000{}{}", "01".repeat(32), "00".repeat(32));
+        assert!(load_checksummed_string(&value, &KEY_VERSION).is_err());
+    }
+
+    #[test]
+    fn return_error_for_tampered_ciphertext() {
+        let ciphertext = [vec![0xDE, 0xF5, 0x02, 0x00], vec![7; 32 + 16 + 5 + 32]].concat();
+
+        let error = decrypt(&ciphertext, &[1; 32]).unwrap_err();
+
+        assert_eq!(WRONG_PASSWORD_ERROR, error.to_string());
+    }
+
+    #[test]
+    fn return_error_for_short_ciphertext() {
+        assert!(decrypt(&[0xDE, 0xF5, 0x02, 0x00, 1, 2, 3], &[1; 32]).is_err());
+    }
+}
@@ -0,0 +1,172 @@
+use std::path::Path;
+
+use anyhow::anyhow;
+use log::{error, info};
+
+use crate::cache::{ACCOUNTS_APPLY_CACHE_FILENAME, CommandProgress, SharedProgress};
+use crate::config::{AppConfig, PermissionsConfig};
+use crate::feature::perms::export::{AccountSnapshot, read_snapshot_from_file};
+use crate::feature::perms::plan::AccountChanges;
+use crate::feature::perms::set::{AccountTask, process_account_tasks, SetOptions};
+use crate::syspass::backend::{create_logged_backends, SyspassBackend};
+use crate::types::{EmptyResult, OperationResult};
+
+/// Restore permissions from snapshot file (see `export` command).
+///
+/// Users and groups of each account are replaced with ones from snapshot, see `apply_snapshot`.
+pub async fn apply_snapshot_in_syspass(config: &AppConfig, snapshot_file: &Path,
+                                       progress: CommandProgress, workers: u16,
+                                       options: &SetOptions) -> OperationResult<Vec<AccountChanges>> {
+    let snapshot = read_snapshot_from_file(snapshot_file)?;
+
+    info!("accounts in snapshot: {}", snapshot.len());
+
+    let mut backends = create_logged_backends(config, workers).await?;
+
+    let mut backend_refs: Vec<&mut dyn SyspassBackend> = backends.iter_mut()
+        .map(|backend| backend.as_mut() as &mut dyn SyspassBackend).collect();
+
+    let shared_progress = SharedProgress::new(
+        progress, Path::new(ACCOUNTS_APPLY_CACHE_FILENAME), config.progress_cache.set_accounts
+    );
+
+    apply_snapshot(&mut backend_refs, config, &snapshot, &shared_progress, options).await
+}
+
+/// Snapshot is authoritative, so it's rejected before any change if owner or main group of some account is blank
+/// (i.e. secondary properties weren't visible for exporting user): blank value would keep current one.
+pub async fn apply_snapshot(backends: &mut [&mut dyn SyspassBackend], config: &AppConfig,
+                            snapshot: &[AccountSnapshot], progress: &SharedProgress,
+                            options: &SetOptions) -> OperationResult<Vec<AccountChanges>> {
+    check_snapshot_is_complete(snapshot)?;
+
+    let permissions: Vec<PermissionsConfig> = snapshot.iter()
+        .map(|item| item.permissions.to_config()).collect();
+
+    let mut tasks: Vec<AccountTask> = vec![];
+
+    for (index, (item, permissions)) in snapshot.iter().zip(&permissions).enumerate() {
+        if progress.is_processed(&item.account) {
+            info!("account with login '{}' (name '{}') has been processed already, skip",
+                  item.account.login, item.account.name);
+
+        } else {
+            tasks.push(AccountTask { index, account: item.account.clone(), permissions });
+        }
+    }
+
+    process_account_tasks(backends, config, tasks.into_iter().map(Ok), snapshot.len(), progress, options).await
+}
+
+fn check_snapshot_is_complete(snapshot: &[AccountSnapshot]) -> EmptyResult {
+    let incomplete_logins: Vec<String> = snapshot.iter()
+        .filter(|item| item.permissions.owner.is_empty() || item.permissions.main_group.is_empty())
+        .map(|item| format!("'{}'", item.account.login))
+        .collect();
+
+    if incomplete_logins.is_empty() {
+        Ok(())
+
+    } else {
+        error!("owner or main group is blank in snapshot for account(s): {}", incomplete_logins.join(", "));
+        Err(anyhow!("snapshot is incomplete, owner or main group is blank for account(s) {}, \
+                     nothing has been changed", incomplete_logins.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::NamedTempFile;
+
+    use crate::cache::{CommandProgress, SharedProgress};
+    use crate::feature::perms::apply::apply_snapshot;
+    use crate::feature::perms::export::AccountSnapshot;
+    use crate::feature::perms::set::SetOptions;
+    use crate::syspass::AccountPermissions;
+    use crate::tests::backend::FakeSyspassBackend;
+    use crate::tests::config::get_test_config;
+    use crate::tests::xml::get_xml_accounts;
+
+    #[tokio::test]
+    async fn exact_permissions_should_be_restored_from_snapshot() {
+        let config = get_test_config();
+
+        let accounts = get_xml_accounts();
+
+        let mut backend = FakeSyspassBackend::new(accounts.clone());
+        backend.logged_in = true;
+
+        backend.permissions.insert(accounts[0].clone(), AccountPermissions {
+            user_view: vec!["j.doe".to_string()],
+            group_edit: vec!["Old Team".to_string()],
+            owner: "j.doe".to_string(),
+            private_account: true,
+            ..AccountPermissions::default()
+        });
+
+        let snapshot_permissions = AccountPermissions {
+            user_edit: vec!["m.smith".to_string()],
+            group_view: vec!["Admins".to_string()],
+            owner: "m.smith".to_string(),
+            main_group: "Admins".to_string(),
+            ..AccountPermissions::default()
+        };
+
+        let snapshot = vec![
+            AccountSnapshot { account: accounts[0].clone(), permissions: snapshot_permissions.clone() },
+            AccountSnapshot { account: accounts[2].clone(), permissions: get_blank_permissions() },
+        ];
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = SharedProgress::new(CommandProgress::default(), cache_file.path(), 10);
+
+        let options = SetOptions { dry_run: false, verify: true, skip_unchanged: false };
+
+        let mismatches = apply_snapshot(&mut [&mut backend], &config, &snapshot, &progress, &options).await.unwrap();
+
+        assert!(mismatches.is_empty());
+        assert_eq!(vec![accounts[0].clone(), accounts[2].clone()], backend.written);
+        assert_eq!(Some(&snapshot_permissions), backend.permissions.get(&accounts[0]));
+    }
+
+    #[tokio::test]
+    async fn snapshot_with_blank_owner_or_main_group_should_be_rejected() {
+        let config = get_test_config();
+
+        let accounts = get_xml_accounts();
+
+        let mut backend = FakeSyspassBackend::new(accounts.clone());
+        backend.logged_in = true;
+
+        let snapshot = vec![
+            AccountSnapshot { account: accounts[0].clone(), permissions: get_blank_permissions() },
+            AccountSnapshot {
+                account: accounts[1].clone(),
+                permissions: AccountPermissions { owner: String::new(), ..get_blank_permissions() }
+            },
+            AccountSnapshot {
+                account: accounts[2].clone(),
+                permissions: AccountPermissions { main_group: String::new(), ..get_blank_permissions() }
+            },
+        ];
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = SharedProgress::new(CommandProgress::default(), cache_file.path(), 10);
+
+        let options = SetOptions { dry_run: false, verify: false, skip_unchanged: false };
+
+        let error = apply_snapshot(&mut [&mut backend], &config, &snapshot, &progress, &options).await.unwrap_err();
+
+        assert!(error.to_string().contains("blank for account(s) 'n.abramova', 'p.sidorov'"));
+        assert!(backend.written.is_empty());
+    }
+
+    /// Nobody except owner and main group has access
+    fn get_blank_permissions() -> AccountPermissions {
+        AccountPermissions {
+            owner: "sysPass Admin".to_string(),
+            main_group: "Admins".to_string(),
+            ..AccountPermissions::default()
+        }
+    }
+}
@@ -0,0 +1,388 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+use anyhow::anyhow;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::cache::{ACCOUNTS_AUDIT_CACHE_FILENAME, CommandProgress, SharedProgress};
+use crate::config::{AccountMatcherConfig, AppConfig};
+use crate::feature::perms::export::{AccountSnapshot, read_snapshot};
+use crate::feature::perms::get::AccountFilterOptions;
+use crate::feature::perms::rules::account_matches;
+use crate::syspass::{Account, AccountPermissions};
+use crate::syspass::backend::{create_logged_backends, SyspassBackend};
+use crate::types::OperationResult;
+
+#[derive(Deserialize,PartialEq,Debug)]
+pub struct AuditPolicy {
+    pub rules: Vec<AuditRule>,
+}
+
+/// All checks of the rule are applied to matching accounts
+#[derive(Deserialize,PartialEq,Debug)]
+pub struct AuditRule {
+    pub name: String,
+
+    /// Rule is applied to all accounts if not set
+    #[serde(rename(deserialize = "match"), default)]
+    pub matcher: AccountMatcherConfig,
+
+    /// Users and groups which must be present
+    #[serde(default)]
+    pub require: PermissionListsConfig,
+
+    /// Users and groups which mustn't be present
+    #[serde(default)]
+    pub forbid: PermissionListsConfig,
+
+    #[serde(rename(deserialize = "must-be-empty"), default)]
+    pub must_be_empty: Vec<PermissionList>,
+
+    /// Allowed owners, any owner is allowed if empty
+    #[serde(rename(deserialize = "owner-in"), default)]
+    pub owner_in: Vec<String>,
+
+    /// Allowed main groups, any group is allowed if empty
+    #[serde(rename(deserialize = "main-group-in"), default)]
+    pub main_group_in: Vec<String>,
+
+    #[serde(rename(deserialize = "private-account"), default)]
+    pub private_account: Option<bool>,
+
+    #[serde(rename(deserialize = "private-account-for-group"), default)]
+    pub private_account_for_group: Option<bool>,
+}
+
+#[derive(Deserialize,PartialEq,Debug,Default)]
+#[serde(default)]
+pub struct PermissionListsConfig {
+    #[serde(rename(deserialize = "user-view"))]
+    pub user_view: Vec<String>,
+
+    #[serde(rename(deserialize = "user-edit"))]
+    pub user_edit: Vec<String>,
+
+    #[serde(rename(deserialize = "group-view"))]
+    pub group_view: Vec<String>,
+
+    #[serde(rename(deserialize = "group-edit"))]
+    pub group_edit: Vec<String>,
+}
+
+impl PermissionListsConfig {
+    fn get(&self, list: PermissionList) -> &[String] {
+        match list {
+            PermissionList::UserView => &self.user_view,
+            PermissionList::UserEdit => &self.user_edit,
+            PermissionList::GroupView => &self.group_view,
+            PermissionList::GroupEdit => &self.group_edit,
+        }
+    }
+}
+
+#[derive(Deserialize,PartialEq,Debug,Clone,Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum PermissionList {
+    UserView,
+    UserEdit,
+    GroupView,
+    GroupEdit,
+}
+
+const PERMISSION_LISTS: [PermissionList; 4] = [
+    PermissionList::UserView, PermissionList::UserEdit,
+    PermissionList::GroupView, PermissionList::GroupEdit,
+];
+
+impl PermissionList {
+    fn get_values(self, permissions: &AccountPermissions) -> &[String] {
+        match self {
+            PermissionList::UserView => &permissions.user_view,
+            PermissionList::UserEdit => &permissions.user_edit,
+            PermissionList::GroupView => &permissions.group_view,
+            PermissionList::GroupEdit => &permissions.group_edit,
+        }
+    }
+}
+
+impl Display for PermissionList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let title = match self {
+            PermissionList::UserView => "user-view",
+            PermissionList::UserEdit => "user-edit",
+            PermissionList::GroupView => "group-view",
+            PermissionList::GroupEdit => "group-edit",
+        };
+
+        write!(f, "{}", title)
+    }
+}
+
+#[derive(Serialize,PartialEq,Debug,Clone)]
+pub struct PolicyViolation {
+    pub rule: String,
+    pub message: String,
+}
+
+#[derive(Serialize,PartialEq,Debug,Clone)]
+pub struct AccountViolations {
+    pub account: Account,
+    pub violations: Vec<PolicyViolation>,
+}
+
+impl Display for AccountViolations {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "account '{}' (login '{}', client '{}', category '{}'):",
+                 self.account.name, self.account.login, self.account.client, self.account.category)?;
+
+        for violation in &self.violations {
+            writeln!(f, "  [{}] {}", violation.rule, violation.message)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn load_audit_policy_from_file(file_path: &Path) -> OperationResult<AuditPolicy> {
+    info!("load audit policy from file '{}'", file_path.display());
+    let content = fs::read_to_string(file_path)?;
+    let policy: AuditPolicy = serde_yaml::from_str(&content)?;
+    info!("audit policy rules: {}", policy.rules.len());
+    Ok(policy)
+}
+
+/// Read permissions for every account matching filters and check them against policy.
+///
+/// Returns accounts with violations.
+pub async fn audit_permissions_in_syspass(config: &AppConfig, policy: &AuditPolicy,
+                                          progress: CommandProgress<AccountSnapshot>,
+                                          filter_options: &AccountFilterOptions,
+                                          workers: u16) -> OperationResult<Vec<AccountViolations>> {
+    info!("audit permissions in syspass instance");
+
+    let mut backends = create_logged_backends(config, workers).await?;
+
+    let mut backend_refs: Vec<&mut dyn SyspassBackend> = backends.iter_mut()
+        .map(|backend| backend.as_mut() as &mut dyn SyspassBackend).collect();
+
+    let shared_progress = SharedProgress::new(
+        progress, Path::new(ACCOUNTS_AUDIT_CACHE_FILENAME), config.progress_cache.get_accounts
+    );
+
+    audit_permissions(&mut backend_refs, config, policy, &shared_progress, filter_options).await
+}
+
+/// Audit fails if permissions of some account couldn't be read, regardless of `ignore-errors`:
+/// accounts which weren't read would be reported as compliant otherwise.
+pub async fn audit_permissions(backends: &mut [&mut dyn SyspassBackend], config: &AppConfig, policy: &AuditPolicy,
+                               progress: &SharedProgress<AccountSnapshot>,
+                               filter_options: &AccountFilterOptions) -> OperationResult<Vec<AccountViolations>> {
+    let outcome = read_snapshot(backends, config, progress, filter_options).await?;
+
+    if let Some(e) = outcome.error {
+        let logins: Vec<String> = outcome.failed_accounts.iter()
+            .map(|account| format!("'{}'", account.login)).collect();
+
+        error!("accounts haven't been audited: {}", logins.join(", "));
+
+        return Err(anyhow!("audit is incomplete, permissions couldn't be read for account(s) {}: {}",
+                           logins.join(", "), e))
+    }
+
+    Ok(audit_snapshot(policy, &outcome.results))
+}
+
+/// Returns accounts with violations
+pub fn audit_snapshot(policy: &AuditPolicy, snapshot: &[AccountSnapshot]) -> Vec<AccountViolations> {
+    snapshot.iter()
+        .map(|item| AccountViolations {
+            account: item.account.clone(),
+            violations: get_policy_violations(policy, &item.account, &item.permissions),
+        })
+        .filter(|item| !item.violations.is_empty())
+        .collect()
+}
+
+pub fn get_policy_violations(policy: &AuditPolicy, account: &Account,
+                             permissions: &AccountPermissions) -> Vec<PolicyViolation> {
+    let mut violations = vec![];
+
+    for rule in &policy.rules {
+        if !account_matches(&rule.matcher, account) {
+            continue;
+        }
+
+        for message in check_rule(rule, permissions) {
+            violations.push(PolicyViolation { rule: rule.name.to_string(), message });
+        }
+    }
+
+    violations
+}
+
+fn check_rule(rule: &AuditRule, permissions: &AccountPermissions) -> Vec<String> {
+    let mut messages = vec![];
+
+    for list in PERMISSION_LISTS {
+        let values = list.get_values(permissions);
+
+        for name in rule.require.get(list) {
+            if !values.contains(name) {
+                messages.push(format!("{} must contain '{}'", list, name));
+            }
+        }
+
+        for name in rule.forbid.get(list) {
+            if values.contains(name) {
+                messages.push(format!("{} mustn't contain '{}'", list, name));
+            }
+        }
+
+        if rule.must_be_empty.contains(&list) && !values.is_empty() {
+            messages.push(format!("{} must be empty, found: {}", list, values.join(", ")));
+        }
+    }
+
+    if !rule.owner_in.is_empty() && !rule.owner_in.contains(&permissions.owner) {
+        messages.push(format!("owner '{}' isn't allowed", permissions.owner));
+    }
+
+    if !rule.main_group_in.is_empty() && !rule.main_group_in.contains(&permissions.main_group) {
+        messages.push(format!("main group '{}' isn't allowed", permissions.main_group));
+    }
+
+    if let Some(expected) = rule.private_account {
+        if permissions.private_account != expected {
+            messages.push(format!("private-account must be {}", expected));
+        }
+    }
+
+    if let Some(expected) = rule.private_account_for_group {
+        if permissions.private_account_for_group != expected {
+            messages.push(format!("private-account-for-group must be {}", expected));
+        }
+    }
+
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use tempfile::NamedTempFile;
+
+    use crate::cache::{CommandProgress, SharedProgress};
+    use crate::feature::perms::audit::{audit_permissions, audit_snapshot, AuditPolicy, get_policy_violations, load_audit_policy_from_file, PermissionList};
+    use crate::feature::perms::export::AccountSnapshot;
+    use crate::feature::perms::get::AccountFilterOptions;
+    use crate::syspass::{Account, AccountPermissions};
+    use crate::tests::backend::FakeSyspassBackend;
+    use crate::tests::config::get_test_config;
+    use crate::tests::xml::get_xml_accounts;
+
+    #[test]
+    fn load_policy_from_file() {
+        let policy = get_policy();
+
+        assert_eq!(2, policy.rules.len());
+
+        let rule = &policy.rules[0];
+        assert_eq!("production admins", rule.name);
+        assert_eq!(vec!["Admins".to_string()], rule.require.group_edit);
+        assert_eq!(vec![PermissionList::UserEdit], rule.must_be_empty);
+        assert_eq!(Some(true), rule.private_account);
+        assert_eq!(None, rule.private_account_for_group);
+    }
+
+    #[test]
+    fn return_violations_for_matching_rules() {
+        let policy = get_policy();
+
+        let permissions = AccountPermissions {
+            user_view: vec!["j.doe".to_string()],
+            user_edit: vec!["j.doe".to_string()],
+            owner: "j.doe".to_string(),
+            ..AccountPermissions::default()
+        };
+
+        let messages: Vec<String> = get_policy_violations(&policy, &get_account("Production"), &permissions)
+            .into_iter().map(|violation| format!("{}: {}", violation.rule, violation.message)).collect();
+
+        assert_eq!(vec![
+            "production admins: user-edit must be empty, found: j.doe",
+            "production admins: group-edit must contain 'Admins'",
+            "production admins: owner 'j.doe' isn't allowed",
+            "production admins: private-account must be true",
+            "no personal access: user-view must be empty, found: j.doe",
+        ], messages);
+
+        let violations = get_policy_violations(&policy, &get_account("Staging"), &permissions);
+        assert_eq!(1, violations.len());
+        assert_eq!("no personal access", violations[0].rule);
+    }
+
+    #[test]
+    fn accounts_without_violations_should_be_skipped() {
+        let policy = get_policy();
+
+        let permissions = AccountPermissions {
+            group_edit: vec!["Admins".to_string(), "Beta Group".to_string()],
+            owner: "sysPass Admin".to_string(),
+            private_account: true,
+            ..AccountPermissions::default()
+        };
+
+        let snapshot = vec![
+            AccountSnapshot { account: get_account("Production"), permissions: permissions.clone() },
+            AccountSnapshot { account: get_account("Staging"), permissions: AccountPermissions::default() },
+        ];
+
+        assert!(audit_snapshot(&policy, &snapshot).is_empty());
+    }
+
+    #[tokio::test]
+    async fn audit_should_fail_if_some_accounts_were_not_read() {
+        let mut config = get_test_config();
+        config.ignore_errors = true;
+
+        let accounts = get_xml_accounts();
+
+        let mut backend = FakeSyspassBackend::new(accounts.clone());
+        backend.logged_in = true;
+        backend.failing_logins = vec![accounts[1].login.to_string()];
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = SharedProgress::new(CommandProgress::default(), cache_file.path(), 10);
+
+        let filter_options = AccountFilterOptions {
+            category_name: String::new(),
+            client_name: String::new(),
+            login_starts_with: String::new(),
+            name_starts_with: String::new(),
+        };
+
+        let error = audit_permissions(&mut [&mut backend], &config, &get_policy(), &progress,
+                                      &filter_options).await.unwrap_err();
+
+        assert!(error.to_string().starts_with("audit is incomplete, permissions couldn't be read for account(s) 'n.abramova'"));
+
+        // Other accounts have been read, they are skipped on resume
+        assert_eq!(2, progress.get_progress().processed.len());
+    }
+
+    fn get_policy() -> AuditPolicy {
+        load_audit_policy_from_file(Path::new("test-data").join("audit-policy.yml").as_path()).unwrap()
+    }
+
+    fn get_account(category: &str) -> Account {
+        Account {
+            name: "Hercules IV".to_string(),
+            login: "demo".to_string(),
+            category: category.to_string(),
+            client: "BirchStore".to_string(),
+        }
+    }
+}
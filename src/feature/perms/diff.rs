@@ -0,0 +1,232 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+use serde::Serialize;
+
+use crate::feature::perms::export::AccountSnapshot;
+use crate::feature::perms::plan::{AccountChanges, diff_permissions};
+use crate::syspass::{Account, AccountPermissions};
+
+/// Difference between two permission snapshots
+#[derive(Serialize,PartialEq,Debug,Default)]
+pub struct SnapshotDiff {
+    /// Accounts which are present in new snapshot only
+    pub added: Vec<AccountSnapshot>,
+
+    /// Accounts which are present in old snapshot only
+    pub removed: Vec<AccountSnapshot>,
+
+    /// Permission changes for accounts from both snapshots
+    pub changed: Vec<AccountChanges>,
+
+    /// Accounts which are present more than once in any snapshot, they aren't compared
+    pub duplicates: Vec<Account>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty() && self.duplicates.is_empty()
+    }
+}
+
+impl Display for SnapshotDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no changes")
+        }
+
+        if !self.duplicates.is_empty() {
+            writeln!(f, "duplicate accounts (not compared):")?;
+
+            for account in &self.duplicates {
+                writeln!(f, "  ! {}", get_account_title(account))?;
+            }
+        }
+
+        if !self.added.is_empty() {
+            writeln!(f, "added accounts:")?;
+
+            for item in &self.added {
+                writeln!(f, "  + {}", get_account_title(&item.account))?;
+            }
+        }
+
+        if !self.removed.is_empty() {
+            writeln!(f, "removed accounts:")?;
+
+            for item in &self.removed {
+                writeln!(f, "  - {}", get_account_title(&item.account))?;
+            }
+        }
+
+        if !self.changed.is_empty() {
+            writeln!(f, "changed accounts:")?;
+
+            for account_changes in &self.changed {
+                write!(f, "{}", account_changes)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn get_account_title(account: &Account) -> String {
+    format!("'{}' (login '{}', client '{}', category '{}')",
+            account.name, account.login, account.client, account.category)
+}
+
+/// Compare snapshots, accounts are matched by name, login, client and category.
+///
+/// Account can't be matched if it's present more than once in any snapshot, such accounts are reported as duplicates.
+pub fn diff_snapshots(old: &[AccountSnapshot], new: &[AccountSnapshot]) -> SnapshotDiff {
+    let mut duplicates: Vec<&Account> = vec![];
+
+    let old_permissions = get_permissions_by_account(old, &mut duplicates);
+    let new_permissions = get_permissions_by_account(new, &mut duplicates);
+
+    let duplicate_set: HashSet<&Account> = duplicates.iter().copied().collect();
+
+    let mut diff = SnapshotDiff {
+        duplicates: duplicates.into_iter().cloned().collect(),
+        ..SnapshotDiff::default()
+    };
+
+    for item in new.iter().filter(|item| !duplicate_set.contains(&item.account)) {
+        match old_permissions.get(&item.account) {
+            Some(old_account_permissions) => {
+                let changes = diff_permissions(old_account_permissions, &item.permissions);
+
+                if !changes.is_empty() {
+                    diff.changed.push(AccountChanges { account: item.account.clone(), changes });
+                }
+            }
+            None => diff.added.push(item.clone())
+        }
+    }
+
+    diff.removed = old.iter()
+        .filter(|item| !duplicate_set.contains(&item.account) && !new_permissions.contains_key(&item.account))
+        .cloned().collect();
+
+    diff
+}
+
+fn get_permissions_by_account<'a>(snapshot: &'a [AccountSnapshot],
+                                  duplicates: &mut Vec<&'a Account>) -> HashMap<&'a Account, &'a AccountPermissions> {
+    let mut permissions: HashMap<&Account, &AccountPermissions> = HashMap::new();
+
+    for item in snapshot {
+        if permissions.insert(&item.account, &item.permissions).is_some() && !duplicates.contains(&&item.account) {
+            duplicates.push(&item.account);
+        }
+    }
+
+    permissions
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::feature::perms::diff::diff_snapshots;
+    use crate::feature::perms::export::AccountSnapshot;
+    use crate::syspass::AccountPermissions;
+    use crate::tests::xml::get_xml_accounts;
+
+    #[test]
+    fn return_added_removed_and_changed_accounts() {
+        let accounts = get_xml_accounts();
+
+        let permissions = AccountPermissions {
+            user_view: vec!["j.doe".to_string()],
+            owner: "j.doe".to_string(),
+            ..AccountPermissions::default()
+        };
+
+        let changed_permissions = AccountPermissions {
+            user_view: vec!["m.smith".to_string()],
+            owner: "m.smith".to_string(),
+            private_account: true,
+            ..AccountPermissions::default()
+        };
+
+        let old = vec![
+            get_snapshot_item(0, &permissions),
+            get_snapshot_item(1, &permissions),
+        ];
+
+        let new = vec![
+            get_snapshot_item(1, &changed_permissions),
+            get_snapshot_item(2, &permissions),
+        ];
+
+        let diff = diff_snapshots(&old, &new);
+
+        assert_eq!(vec![new[1].clone()], diff.added);
+        assert_eq!(vec![old[0].clone()], diff.removed);
+
+        assert_eq!(1, diff.changed.len());
+        assert_eq!(accounts[1], diff.changed[0].account);
+
+        let changes = &diff.changed[0].changes;
+        assert_eq!(vec!["m.smith".to_string()], changes.user_view.added);
+        assert_eq!(vec!["j.doe".to_string()], changes.user_view.removed);
+        assert_eq!("m.smith", changes.owner.as_ref().unwrap().new);
+        assert!(changes.private_account.as_ref().unwrap().new);
+        assert!(changes.main_group.is_none());
+    }
+
+    #[test]
+    fn return_empty_diff_for_same_snapshots() {
+        let snapshot = vec![get_snapshot_item(0, &AccountPermissions::default())];
+
+        let diff = diff_snapshots(&snapshot, &snapshot);
+
+        assert!(diff.is_empty());
+        assert_eq!("no changes\n", diff.to_string());
+    }
+
+    #[test]
+    fn duplicate_accounts_should_be_reported_and_not_compared() {
+        let accounts = get_xml_accounts();
+
+        let permissions = AccountPermissions {
+            owner: "j.doe".to_string(),
+            ..AccountPermissions::default()
+        };
+
+        let changed_permissions = AccountPermissions {
+            owner: "m.smith".to_string(),
+            ..AccountPermissions::default()
+        };
+
+        let old = vec![
+            get_snapshot_item(0, &permissions),
+            get_snapshot_item(0, &changed_permissions),
+            get_snapshot_item(1, &permissions),
+            get_snapshot_item(2, &permissions),
+        ];
+
+        let new = vec![
+            get_snapshot_item(0, &permissions),
+            get_snapshot_item(1, &changed_permissions),
+            get_snapshot_item(1, &permissions),
+            get_snapshot_item(1, &permissions),
+        ];
+
+        let diff = diff_snapshots(&old, &new);
+
+        assert_eq!(vec![accounts[0].clone(), accounts[1].clone()], diff.duplicates);
+        assert!(diff.added.is_empty());
+        assert_eq!(vec![old[3].clone()], diff.removed);
+        assert!(diff.changed.is_empty());
+
+        assert!(diff.to_string().starts_with("duplicate accounts (not compared):\n  ! 'Ivan Petrov'"));
+    }
+
+    fn get_snapshot_item(account_index: usize, permissions: &AccountPermissions) -> AccountSnapshot {
+        AccountSnapshot {
+            account: get_xml_accounts()[account_index].clone(),
+            permissions: permissions.clone(),
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::{ACCOUNTS_EXPORT_CACHE_FILENAME, CommandProgress, SharedProgress};
+use crate::config::AppConfig;
+use crate::feature::perms::get::{AccountFilterOptions, read_all_permissions_for_accounts, read_permissions_for_accounts, ReadOutcome};
+use crate::syspass::{Account, AccountPermissions};
+use crate::syspass::backend::{create_logged_backends, SyspassBackend};
+use crate::types::{EmptyResult, OperationResult};
+
+/// Separator for user/group names inside csv cell
+pub const CSV_LIST_SEPARATOR: &str = ";";
+
+const CSV_HEADERS: [&str; 12] = [
+    "name", "login", "category", "client",
+    "user-view", "user-edit", "group-view", "group-edit",
+    "owner", "main-group", "private-account", "private-account-for-group"
+];
+
+/// Account with its permissions
+#[derive(Serialize,Deserialize,PartialEq,Debug,Clone)]
+pub struct AccountSnapshot {
+    pub account: Account,
+    pub permissions: AccountPermissions,
+}
+
+#[derive(PartialEq,Debug,Clone,Copy)]
+pub enum SnapshotFormat {
+    Json,
+    Csv,
+}
+
+/// Read permissions for every account matching filters
+pub async fn export_permissions_from_syspass(config: &AppConfig, progress: CommandProgress<AccountSnapshot>,
+                                             filter_options: &AccountFilterOptions,
+                                             workers: u16) -> OperationResult<Vec<AccountSnapshot>> {
+    info!("export permissions from syspass instance");
+
+    let mut backends = create_logged_backends(config, workers).await?;
+
+    let mut backend_refs: Vec<&mut dyn SyspassBackend> = backends.iter_mut()
+        .map(|backend| backend.as_mut() as &mut dyn SyspassBackend).collect();
+
+    let shared_progress = SharedProgress::new(
+        progress, Path::new(ACCOUNTS_EXPORT_CACHE_FILENAME), config.progress_cache.get_accounts
+    );
+
+    export_permissions(&mut backend_refs, config, &shared_progress, filter_options).await
+}
+
+/// Returns error on the first read error unless `ignore-errors` is enabled, see `read_permissions_for_accounts`
+pub async fn export_permissions(backends: &mut [&mut dyn SyspassBackend], config: &AppConfig,
+                                progress: &SharedProgress<AccountSnapshot>,
+                                filter_options: &AccountFilterOptions) -> OperationResult<Vec<AccountSnapshot>> {
+    read_permissions_for_accounts(backends, config, progress, filter_options, get_snapshot_item).await
+}
+
+/// Snapshot with accounts which couldn't be read, see `read_all_permissions_for_accounts`
+pub async fn read_snapshot(backends: &mut [&mut dyn SyspassBackend], config: &AppConfig,
+                           progress: &SharedProgress<AccountSnapshot>,
+                           filter_options: &AccountFilterOptions) -> OperationResult<ReadOutcome<AccountSnapshot>> {
+    read_all_permissions_for_accounts(backends, config, progress, filter_options, get_snapshot_item).await
+}
+
+fn get_snapshot_item(account: &Account, permissions: AccountPermissions) -> Option<AccountSnapshot> {
+    Some(AccountSnapshot { account: account.clone(), permissions })
+}
+
+/// Read snapshot from json or yaml (`.yml`, `.yaml`) file
+pub fn read_snapshot_from_file(file_path: &Path) -> OperationResult<Vec<AccountSnapshot>> {
+    info!("read snapshot from file '{}'", file_path.display());
+
+    let content = fs::read_to_string(file_path)?;
+
+    let extension = file_path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase()).unwrap_or_default();
+
+    let snapshot = match extension.as_str() {
+        "yml" | "yaml" => serde_yaml::from_str(&content)?,
+        _ => serde_json::from_str(&content)?
+    };
+
+    Ok(snapshot)
+}
+
+pub fn write_snapshot<W: Write>(snapshot: &[AccountSnapshot], format: SnapshotFormat, writer: W) -> EmptyResult {
+    match format {
+        SnapshotFormat::Json => serde_json::to_writer_pretty(writer, snapshot)?,
+        SnapshotFormat::Csv => {
+            let mut csv_writer = csv::Writer::from_writer(writer);
+
+            csv_writer.write_record(CSV_HEADERS)?;
+
+            for item in snapshot {
+                csv_writer.write_record(get_csv_record(item))?;
+            }
+
+            csv_writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn get_csv_record(item: &AccountSnapshot) -> Vec<String> {
+    let account = &item.account;
+    let permissions = &item.permissions;
+
+    vec![
+        account.name.to_string(), account.login.to_string(),
+        account.category.to_string(), account.client.to_string(),
+        permissions.user_view.join(CSV_LIST_SEPARATOR), permissions.user_edit.join(CSV_LIST_SEPARATOR),
+        permissions.group_view.join(CSV_LIST_SEPARATOR), permissions.group_edit.join(CSV_LIST_SEPARATOR),
+        permissions.owner.to_string(), permissions.main_group.to_string(),
+        permissions.private_account.to_string(), permissions.private_account_for_group.to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::{Builder, NamedTempFile};
+
+    use crate::cache::{CommandProgress, SharedProgress};
+    use crate::feature::perms::export::{AccountSnapshot, export_permissions, read_snapshot_from_file, SnapshotFormat, write_snapshot};
+    use crate::feature::perms::get::AccountFilterOptions;
+    use crate::syspass::AccountPermissions;
+    use crate::tests::account::get_sample_account;
+    use crate::tests::backend::FakeSyspassBackend;
+    use crate::tests::config::get_test_config;
+    use crate::tests::xml::get_xml_accounts;
+
+    #[tokio::test]
+    async fn export_permissions_for_all_accounts() {
+        let config = get_test_config();
+
+        let accounts = get_xml_accounts();
+
+        let mut backend = FakeSyspassBackend::new(accounts.clone());
+        backend.logged_in = true;
+
+        let permissions = get_permissions();
+        backend.permissions.insert(accounts[1].clone(), permissions.clone());
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = SharedProgress::new(CommandProgress::default(), cache_file.path(), 10);
+
+        let filter_options = AccountFilterOptions {
+            category_name: String::new(),
+            client_name: String::new(),
+            login_starts_with: String::new(),
+            name_starts_with: String::new(),
+        };
+
+        let snapshot = export_permissions(&mut [&mut backend], &config, &progress, &filter_options).await.unwrap();
+
+        assert_eq!(3, snapshot.len());
+        assert_eq!(AccountSnapshot { account: accounts[1].clone(), permissions }, snapshot[1]);
+        assert_eq!(AccountPermissions::default(), snapshot[0].permissions);
+    }
+
+    #[tokio::test]
+    async fn export_should_fail_on_read_error() {
+        let mut config = get_test_config();
+        config.ignore_errors = false;
+
+        let accounts = get_xml_accounts();
+
+        let mut backend = FakeSyspassBackend::new(accounts.clone());
+        backend.logged_in = true;
+        backend.failing_logins = vec![accounts[1].login.to_string()];
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = SharedProgress::new(CommandProgress::default(), cache_file.path(), 10);
+
+        let filter_options = AccountFilterOptions {
+            category_name: String::new(),
+            client_name: String::new(),
+            login_starts_with: String::new(),
+            name_starts_with: String::new(),
+        };
+
+        assert!(export_permissions(&mut [&mut backend], &config, &progress, &filter_options).await.is_err());
+    }
+
+    #[test]
+    fn write_snapshot_as_csv() {
+        let mut account = get_sample_account();
+        account.name = "Hercules, IV".to_string();
+        account.login = "demo".to_string();
+        account.category = "Apps".to_string();
+        account.client = "BirchStore".to_string();
+
+        let snapshot = vec![AccountSnapshot { account, permissions: get_permissions() }];
+
+        let mut output: Vec<u8> = vec![];
+        write_snapshot(&snapshot, SnapshotFormat::Csv, &mut output).unwrap();
+
+        let lines: Vec<String> = String::from_utf8(output).unwrap().lines().map(|line| line.to_string()).collect();
+
+        assert_eq!(2, lines.len());
+        assert!(lines[0].starts_with("name,login,category,client,user-view"));
+        assert_eq!("\"Hercules, IV\",demo,Apps,BirchStore,,j.doe;m.smith,Admins,,j.doe,Admins,false,true", lines[1]);
+    }
+
+    #[test]
+    fn write_snapshot_as_json() {
+        let snapshot = vec![AccountSnapshot { account: get_sample_account(), permissions: get_permissions() }];
+
+        let mut output: Vec<u8> = vec![];
+        write_snapshot(&snapshot, SnapshotFormat::Json, &mut output).unwrap();
+
+        let result: Vec<AccountSnapshot> = serde_json::from_slice(&output).unwrap();
+
+        assert_eq!(snapshot, result);
+    }
+
+    #[test]
+    fn read_snapshot_from_json_and_yaml_files() {
+        let snapshot = vec![AccountSnapshot { account: get_sample_account(), permissions: get_permissions() }];
+
+        let json_file = Builder::new().suffix(".json").tempfile().unwrap();
+        write_snapshot(&snapshot, SnapshotFormat::Json, json_file.as_file()).unwrap();
+
+        assert_eq!(snapshot, read_snapshot_from_file(json_file.path()).unwrap());
+
+        let yaml_file = Builder::new().suffix(".yml").tempfile().unwrap();
+        fs::write(yaml_file.path(), serde_yaml::to_string(&snapshot).unwrap()).unwrap();
+
+        assert_eq!(snapshot, read_snapshot_from_file(yaml_file.path()).unwrap());
+    }
+
+    fn get_permissions() -> AccountPermissions {
+        AccountPermissions {
+            user_edit: vec!["j.doe".to_string(), "m.smith".to_string()],
+            group_view: vec!["Admins".to_string()],
+            owner: "j.doe".to_string(),
+            main_group: "Admins".to_string(),
+            private_account_for_group: true,
+            ..AccountPermissions::default()
+        }
+    }
+}
@@ -1,19 +1,19 @@
 use std::fmt::{Display, Formatter};
 use std::path::Path;
-use std::thread;
-use std::time::Duration;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Error};
+use futures::future::join_all;
 use log::{debug, error, info, trace};
-use thirtyfour::{By, DesiredCapabilities, WebDriver, WebElement};
+use serde::Serialize;
 
-use crate::cache::{ACCOUNTS_GET_CACHE_FILENAME, save_cache_data_into_file};
+use crate::cache::{ACCOUNTS_GET_CACHE_FILENAME, CommandProgress, SharedProgress};
 use crate::config::AppConfig;
-use crate::syspass::{Account, ELEMENT_NOT_FOUND_ERROR, UNSUPPORTED_UI_VERSION_ERROR};
-use crate::syspass::login::login_to_syspass;
-use crate::syspass::perms::{get_tags_from_list_box_in_view_mode, go_to_account_view_page, open_permissions_tab};
-use crate::syspass::search::{clear_search_input, get_search_item_category, get_search_item_client, get_search_item_login, get_search_item_name, next_page_available};
-use crate::types::OperationResult;
+use crate::feature::perms::session::SessionSupervisor;
+use crate::syspass::{Account, AccountPermissions};
+use crate::syspass::backend::{create_logged_backends, SyspassBackend};
+use crate::types::{EmptyResult, OperationResult};
 
 pub struct AccountFilterOptions {
     pub category_name: String,
@@ -30,281 +30,514 @@ impl Display for AccountFilterOptions {
     }
 }
 
-pub async fn get_accounts_with_empty_permissions(config: &AppConfig,
-                         accounts_from_cache: &mut Vec<Account>,
-                         filter_options: &AccountFilterOptions) -> OperationResult<Vec<Account>> {
+/// Get accounts with empty permissions from sysPass.
+///
+/// `progress` - progress of previous run, processed accounts will be skipped.
+///
+/// `workers` - webdriver sessions count, accounts are processed in parallel.
+pub async fn get_accounts_with_empty_permissions(config: &AppConfig, progress: CommandProgress,
+                         filter_options: &AccountFilterOptions,
+                         workers: u16) -> OperationResult<Vec<Account>> {
 
     info!("get accounts with empty permissions from syspass instance");
-    debug!("accounts in cache: {:?}", accounts_from_cache);
-    debug!("filter options: {}", filter_options);
 
-    let mut caps = DesiredCapabilities::chrome();
+    let mut backends = create_logged_backends(config, workers).await?;
 
-    for arg in config.webdriver.args.iter() {
-        caps.add_chrome_arg(&arg)?;
-    }
+    let mut backend_refs: Vec<&mut dyn SyspassBackend> = backends.iter_mut()
+        .map(|backend| backend.as_mut() as &mut dyn SyspassBackend).collect();
 
-    let driver = WebDriver::new(&config.webdriver.url, caps).await?;
+    let shared_progress = SharedProgress::new(
+        progress, Path::new(ACCOUNTS_GET_CACHE_FILENAME), config.progress_cache.get_accounts
+    );
 
-    login_to_syspass(&driver, &config.syspass_url,
-                     &config.auth.login, &config.auth.password).await?;
+    find_accounts_with_empty_permissions(&mut backend_refs, config,
+                                         &shared_progress, filter_options).await
+}
 
-    debug!("wait after login redirect {} ms", config.delays.after_login);
-    thread::sleep(Duration::from_millis(config.delays.after_login));
+/// Search results pages are distributed between all backends, see `read_permissions_for_accounts`.
+pub async fn find_accounts_with_empty_permissions(backends: &mut [&mut dyn SyspassBackend],
+                                                  config: &AppConfig,
+                                                  progress: &SharedProgress,
+                                                  filter_options: &AccountFilterOptions) -> OperationResult<Vec<Account>> {
+    read_permissions_for_accounts(backends, config, progress, filter_options, |account, permissions| {
+        if permissions.is_empty() {
+            info!("add account: {:?}", account);
+            Some(account.clone())
+
+        } else {
+            None
+        }
+    }).await
+}
 
-    clear_search_input(&driver).await?;
-    thread::sleep(Duration::from_millis(config.delays.after_login));
+/// Results of `read_all_permissions_for_accounts`
+pub struct ReadOutcome<T> {
+    pub results: Vec<T>,
 
-    let mut last_page = false;
+    /// Accounts which permissions couldn't be read, they aren't marked as processed
+    pub failed_accounts: Vec<Account>,
 
-    let mut has_errors = false;
+    /// The first read error
+    pub error: Option<Error>,
+}
 
-    let accounts_from_cache_clone = accounts_from_cache.clone();
-    let resume_cache_item = accounts_from_cache_clone.last();
-    let mut resumed_from_cache = resume_cache_item.is_none();
-    debug!("process resumed from cache: {}", resumed_from_cache);
+/// Read permissions for all accounts matching filters, see `read_all_permissions_for_accounts`.
+///
+/// The first read error is returned unless `ignore-errors` is enabled, so partial results
+/// (i.e. truncated snapshot) aren't taken for complete ones. Otherwise failed accounts are skipped.
+pub async fn read_permissions_for_accounts<T: Serialize + Clone + Send>(
+    backends: &mut [&mut dyn SyspassBackend], config: &AppConfig, progress: &SharedProgress<T>,
+    filter_options: &AccountFilterOptions,
+    get_result: fn(&Account, AccountPermissions) -> Option<T>) -> OperationResult<Vec<T>> {
+    let outcome = read_all_permissions_for_accounts(backends, config, progress, filter_options, get_result).await?;
+
+    match outcome.error {
+        Some(e) if !config.ignore_errors => Err(e),
+        Some(_) => {
+            info!("accounts have been partially processed");
+            Ok(outcome.results)
+        }
+        None => Ok(outcome.results)
+    }
+}
 
-    let mut accounts: Vec<Account> = vec![];
-    accounts.append(accounts_from_cache);
+/// Read permissions for all accounts matching filters.
+///
+/// Search results pages are split between backends: worker N reads pages N, N + workers, N + 2 * workers, ..
+/// until the first missing page. Accounts are opened by id from search results, without another search.
+///
+/// `get_result` - returns command result for account and its permissions, if any.
+///
+/// Read errors are returned with results, outer error means process couldn't be continued (i.e. session is lost).
+pub async fn read_all_permissions_for_accounts<T: Serialize + Clone + Send>(
+    backends: &mut [&mut dyn SyspassBackend], config: &AppConfig, progress: &SharedProgress<T>,
+    filter_options: &AccountFilterOptions,
+    get_result: fn(&Account, AccountPermissions) -> Option<T>) -> OperationResult<ReadOutcome<T>> {
 
-    let mut search_item_offset = 0;
+    debug!("filter options: {}", filter_options);
 
-    let mut cache_items_counter: u16 = 0;
+    if backends.is_empty() {
+        return Err(anyhow!("no backends to process accounts"))
+    }
 
-    let cache_file_path = Path::new(ACCOUNTS_GET_CACHE_FILENAME);
+    info!("read search results pages with {} workers", backends.len());
 
-    while !last_page {
+    let context = ReadContext {
+        config,
+        filter_options,
+        progress,
+        workers_count: backends.len(),
+        stop: AtomicBool::new(false),
+        failed: Mutex::new(vec![]),
+        get_result,
+    };
 
-        let mut search_items = driver.find_all(By::ClassName("account-label")).await?;
+    let workers = backends.iter_mut().enumerate().map(|(worker_index, backend)| {
+        let context = &context;
 
-        debug!("search item: {}", search_items.len());
-        debug!("search item offset: {}", search_item_offset);
+        async move {
+            let result = read_permissions_worker(worker_index, &mut **backend, context).await;
 
-        while search_items.len() > search_item_offset {
-            let item = search_items.iter().skip(search_item_offset)
-                                                   .into_iter().collect::<Vec<&WebElement>>();
+            if result.is_err() {
+                context.stop.store(true, Ordering::SeqCst);
+            }
 
-            match item.iter().next() {
-                Some(search_item) => {
-                    let account_category = get_search_item_category(&search_item).await?;
-                    let account_client = get_search_item_client(&search_item).await?;
-                    let account_login = get_search_item_login(&search_item).await?;
-                    let account_name = get_search_item_name(&search_item).await?;
+            result
+        }
+    });
 
-                    info!("processing account '{}' (login '{}')", account_name, account_login);
+    for worker_result in join_all(workers).await {
+        worker_result?;
+    }
 
-                    let account = Account {
-                        name: account_name.to_string(),
-                        login: account_login.to_string(),
-                        category: account_category.to_string(),
-                        client: account_client.to_string(),
-                    };
+    let (failed_accounts, errors): (Vec<Account>, Vec<Error>) = context.failed.into_inner()
+        .expect("failed accounts lock error").into_iter().unzip();
 
-                    if !resumed_from_cache {
-                        match resume_cache_item {
-                            Some(last_account_from_cache) => {
-                                debug!("expect account '{}' with login '{}'",
-                                    last_account_from_cache.name, last_account_from_cache.login);
-
-                                if last_account_from_cache == &account {
-                                    info!("resume process from account name '{}' and login '{}'",
-                                          account_name, account_login);
-                                    resumed_from_cache = true;
-
-                                } else {
-                                    info!("skip account, looking for account from cache");
-                                }
-                            }
-                            None => {}
-                        }
-
-                        search_item_offset += 1;
-                        continue;
-                    }
+    Ok(ReadOutcome {
+        results: progress.get_progress().results,
+        failed_accounts,
+        error: errors.into_iter().next(),
+    })
+}
 
-                    if !account_matches_filters(&account, &filter_options) {
-                        info!("account '{}' (login '{}') doesn't match filter options, skip",
-                            account.name, account.login);
-                        continue;
-                    }
+pub async fn search_accounts(backend: &mut dyn SyspassBackend, config: &AppConfig) -> OperationResult<Vec<Account>> {
+    let mut session_supervisor = SessionSupervisor::new(config);
 
-                    search_item.scroll_into_view().await?;
+    let mut search_result = backend.search_accounts().await;
 
-                    go_to_account_view_page(&search_item).await?;
+    while let Err(e) = &search_result {
+        if !session_supervisor.recover(backend, e).await? {
+            break;
+        }
 
-                    thread::sleep(Duration::from_millis(config.delays.after_redirect_to_edit));
+        search_result = backend.search_accounts().await;
+    }
 
-                    open_permissions_tab(&driver).await?;
+    search_result
+}
 
-                    let permissions_panel = driver.find(By::Id("permission-panel")).await?;
+async fn search_accounts_page(backend: &mut dyn SyspassBackend, session_supervisor: &mut SessionSupervisor,
+                              page: usize) -> OperationResult<Option<Vec<Account>>> {
+    let mut search_result = backend.search_accounts_page(page).await;
 
-                    match account_has_empty_permissions(&permissions_panel).await {
-                        Ok(has_empty_permissions) => {
+    while let Err(e) = &search_result {
+        if !session_supervisor.recover(backend, e).await? {
+            break;
+        }
 
-                            if has_empty_permissions {
-                                let account = Account {
-                                    name: account_name,
-                                    login: account_login,
-                                    category: account_category,
-                                    client: account_client,
-                                };
+        search_result = backend.search_accounts_page(page).await;
+    }
 
-                                info!("add account: {:?}", account);
+    search_result
+}
 
-                                accounts.push(account);
+/// State shared between workers
+struct ReadContext<'a, T: Serialize + Clone> {
+    config: &'a AppConfig,
+    filter_options: &'a AccountFilterOptions,
+    progress: &'a SharedProgress<T>,
+    workers_count: usize,
+    stop: AtomicBool,
 
-                                cache_items_counter += 1;
-                                debug!("cache items counter: {}", cache_items_counter);
+    /// Accounts with read errors, in order of errors
+    failed: Mutex<Vec<(Account, Error)>>,
 
-                                if cache_items_counter >= config.progress_cache.get_accounts {
-                                    match save_cache_data_into_file(&accounts, cache_file_path) {
-                                        Ok(_) => {
-                                            info!("accounts cache has been updated");
-                                            cache_items_counter = 0;
-                                        },
-                                        Err(e) => error!("cannot update accounts cache: {}", e)
-                                    }
-                                }
-                            }
+    get_result: fn(&Account, AccountPermissions) -> Option<T>,
+}
 
-                        },
-                        Err(_) => {
-                            has_errors = true;
-                            break;
-                        }
-                    }
+/// Read permissions for accounts from own search results pages.
+/// Read error stops all workers unless `ignore-errors` is enabled.
+async fn read_permissions_worker<T: Serialize + Clone>(worker_index: usize, backend: &mut dyn SyspassBackend,
+                                                       context: &ReadContext<'_, T>) -> EmptyResult {
+    let worker_id = worker_index + 1;
 
-                    info!("back to search page");
-                    let back_button = driver.find(By::Id("btnBack")).await?;
-                    back_button.click().await?;
+    let mut session_supervisor = SessionSupervisor::new(context.config);
 
-                    debug!("wait after redirect {} ms", config.delays.after_redirect_to_edit);
-                    thread::sleep(Duration::from_millis(config.delays.after_redirect_to_edit));
+    let mut page = worker_index;
 
-                    search_item_offset += 1;
+    while !context.stop.load(Ordering::SeqCst) {
+        let accounts = match search_accounts_page(backend, &mut session_supervisor, page).await? {
+            Some(accounts) => accounts,
+            None => break
+        };
 
-                    search_items = driver.find_all(By::ClassName("account-label")).await?;
-                }
-                None => {
-                    search_item_offset = 0;
-                    break
-                }
+        info!("[worker {}] search results page {}, accounts: {}", worker_id, page + 1, accounts.len());
+
+        for account in accounts {
+            if context.stop.load(Ordering::SeqCst) {
+                break;
             }
 
+            if context.progress.is_processed(&account) {
+                info!("account '{}' (login '{}') has been processed already, skip",
+                    account.name, account.login);
+                continue;
+            }
 
-        }
+            if !account_matches_filters(&account, context.filter_options) {
+                info!("account '{}' (login '{}') doesn't match filter options, skip",
+                    account.name, account.login);
+                continue;
+            }
 
-        if has_errors {
-            error!("interrupt process due error(s). check logs for details.");
-            break;
-        }
+            info!("[worker {}] processing account '{}' (login '{}')", worker_id, account.name, account.login);
+
+            let mut read_result = backend.read_permissions(&account).await;
 
-        last_page = !next_page_available(&driver).await;
-        debug!("is it last page: {}", last_page);
+            while let Err(e) = &read_result {
+                if !session_supervisor.recover(backend, e).await? {
+                    break;
+                }
+
+                read_result = backend.read_permissions(&account).await;
+            }
+
+            session_supervisor.account_processed(backend).await?;
+
+            match read_result {
+                Ok(permissions) => {
+                    let result = (context.get_result)(&account, permissions);
+                    context.progress.add_processed(&account, result);
+                },
+                Err(e) => {
+                    error!("{}", e);
+                    error!("couldn't read permissions for account '{}'", account.login);
+
+                    if !context.config.ignore_errors {
+                        error!("interrupt process due error(s). check logs for details.");
+                        context.stop.store(true, Ordering::SeqCst);
+                    }
 
-        if !last_page {
-            search_item_offset = 0;
-            info!("go to next search results page..");
-            let next_page_button = driver.find(By::Id("btn-pager-next")).await?;
-            next_page_button.scroll_into_view().await?;
-            next_page_button.click().await?;
-            thread::sleep(Duration::from_millis(1000));
+                    context.failed.lock().expect("failed accounts lock error").push((account, e));
+                }
+            }
         }
+
+        page += context.workers_count;
     }
 
-    Ok(accounts)
+    Ok(())
 }
 
-fn account_matches_filters(account: &Account, filter_options: &AccountFilterOptions) -> bool {
+pub fn account_matches_filters(account: &Account, filter_options: &AccountFilterOptions) -> bool {
     let mut account_match = true;
 
-    if !filter_options.login_starts_with.is_empty() {
-        if !account.login.starts_with(&filter_options.login_starts_with) {
-            account_match = false;
-            trace!("login-start-with '{}' doesn't match with '{}'",
-                filter_options.login_starts_with, account.login);
-        }
+    if !filter_options.login_starts_with.is_empty()
+        && !account.login.starts_with(&filter_options.login_starts_with) {
+        account_match = false;
+        trace!("login-start-with '{}' doesn't match with '{}'",
+            filter_options.login_starts_with, account.login);
     }
 
-    if !filter_options.name_starts_with.is_empty() {
-        if !account.name.starts_with(&filter_options.name_starts_with) {
-            account_match = false;
-            trace!("name-start-with '{}' doesn't match with '{}'",
-                filter_options.name_starts_with, account.name);
-        }
+    if !filter_options.name_starts_with.is_empty()
+        && !account.name.starts_with(&filter_options.name_starts_with) {
+        account_match = false;
+        trace!("name-start-with '{}' doesn't match with '{}'",
+            filter_options.name_starts_with, account.name);
     }
 
-    if !filter_options.category_name.is_empty() {
-        if account.category != filter_options.category_name {
-            account_match = false;
-            trace!("category-name '{}' doesn't match with '{}'",
-                filter_options.category_name, account.category);
-        }
+    if !filter_options.category_name.is_empty()
+        && account.category != filter_options.category_name {
+        account_match = false;
+        trace!("category-name '{}' doesn't match with '{}'",
+            filter_options.category_name, account.category);
     }
 
-    if !filter_options.client_name.is_empty() {
-        if account.client != filter_options.client_name {
-            account_match = false;
-            trace!("client-name '{}' doesn't match with '{}'",
-                filter_options.client_name, account.client);
-        }
+    if !filter_options.client_name.is_empty()
+        && account.client != filter_options.client_name {
+        account_match = false;
+        trace!("client-name '{}' doesn't match with '{}'",
+            filter_options.client_name, account.client);
     }
 
-    return account_match
+    account_match
 }
 
-async fn account_has_empty_permissions(permissions_panel_element: &WebElement) -> OperationResult<bool> {
-    let permission_rows = permissions_panel_element.find_all(By::Tag("tr")).await?;
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tempfile::NamedTempFile;
 
-    if permission_rows.len() >= 2 {
+    use crate::cache::{CommandProgress, SharedProgress};
 
-        let users_block_element = permission_rows.first()
-            .expect(ELEMENT_NOT_FOUND_ERROR);
-        let users_perms = users_block_element.find_all(By::ClassName("tag-list-box")).await?;
+    use crate::feature::perms::get::{account_matches_filters, AccountFilterOptions, find_accounts_with_empty_permissions, get_accounts_with_empty_permissions};
+    use crate::syspass::{Account, AccountPermissions};
+    use crate::syspass::backend::SyspassBackend;
+    use crate::tests::{get_random_string, init_logging};
+    use crate::tests::backend::FakeSyspassBackend;
+    use crate::tests::config::get_test_config;
+    use crate::tests::rpc::start_mock_rpc_server;
 
-        let users_view_perms = users_perms.first()
-            .expect(ELEMENT_NOT_FOUND_ERROR);
-        let users_view_tags = get_tags_from_list_box_in_view_mode(&users_view_perms).await?;
-        debug!("users view tags: {:?}", users_view_tags);
+    #[tokio::test]
+    async fn return_accounts_with_empty_permissions() {
+        init_logging();
 
-        let users_edit_perms = users_perms.last()
-            .expect(ELEMENT_NOT_FOUND_ERROR);
-        let users_edit_tags = get_tags_from_list_box_in_view_mode(&users_edit_perms).await?;
-        debug!("users edit tags: {:?}", users_edit_tags);
+        let config = get_test_config();
+        let mut backend = get_backend_with_accounts();
 
-        let groups_block_element = permission_rows.get(1)
-            .expect(ELEMENT_NOT_FOUND_ERROR);
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
 
-        let groups_perms = groups_block_element.find_all(By::ClassName("tag-list-box")).await?;
+        let accounts = find_accounts_with_empty_permissions(
+            &mut [&mut backend], &config, &progress, &get_blank_filter_options()).await.unwrap();
 
-        let groups_view_perms = groups_perms.first()
-            .expect(ELEMENT_NOT_FOUND_ERROR);
-        let groups_view_tags = get_tags_from_list_box_in_view_mode(&groups_view_perms).await?;
-        debug!("group view tags: {:?}", groups_view_tags);
+        assert_eq!(get_logins(&accounts), vec!["demo-acc", "demo-db"]);
+    }
+
+    #[tokio::test]
+    async fn apply_filters_for_accounts() {
+        let config = get_test_config();
+        let mut backend = get_backend_with_accounts();
 
-        let groups_edit_perms = groups_perms.last()
-            .expect(ELEMENT_NOT_FOUND_ERROR);
-        let groups_edit_tags = get_tags_from_list_box_in_view_mode(&groups_edit_perms).await?;
-        debug!("group edit tags: {:?}", groups_edit_tags);
+        let mut filter_options = get_blank_filter_options();
+        filter_options.name_starts_with = "Hercules IV".to_string();
 
-        let has_empty_permissions = users_view_tags.is_empty() &&
-            users_edit_tags.is_empty() &&
-            groups_view_tags.is_empty() &&
-            groups_edit_tags.is_empty();
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
 
-        Ok(has_empty_permissions)
+        let accounts = find_accounts_with_empty_permissions(
+            &mut [&mut backend], &config, &progress, &filter_options).await.unwrap();
 
-    } else {
-        error!("expected at least two 'tr' rows on permissions tab");
-        Err(anyhow!("{}", UNSUPPORTED_UI_VERSION_ERROR))
+        assert_eq!(get_logins(&accounts), vec!["demo-db"]);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::feature::perms::get::{account_matches_filters, AccountFilterOptions};
-    use crate::syspass::Account;
-    use crate::tests::{get_random_string, init_logging};
+    #[tokio::test]
+    async fn processed_accounts_should_be_skipped() {
+        let config = get_test_config();
+        let mut backend = get_backend_with_accounts();
+
+        let first_account = backend.accounts[0].clone();
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress {
+            processed: vec![first_account.clone()],
+            results: vec![first_account],
+        }, &cache_file);
+
+        let accounts = find_accounts_with_empty_permissions(
+            &mut [&mut backend], &config, &progress, &get_blank_filter_options()).await.unwrap();
+
+        assert_eq!(get_logins(&accounts), vec!["demo-acc", "demo-db"]);
+        assert_eq!(2, backend.read.len());
+    }
+
+    #[tokio::test]
+    async fn accounts_should_be_distributed_between_workers() {
+        let config = get_test_config();
+        let mut backend1 = get_backend_with_accounts();
+        let mut backend2 = get_backend_with_accounts();
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+
+        let mut backends: Vec<&mut dyn SyspassBackend> = vec![&mut backend1, &mut backend2];
+
+        let accounts = find_accounts_with_empty_permissions(
+            &mut backends, &config, &progress, &get_blank_filter_options()).await.unwrap();
+
+        let mut logins = get_logins(&accounts);
+        logins.sort();
+
+        assert_eq!(logins, vec!["demo-acc", "demo-db"]);
+        assert!(!backend1.read.is_empty());
+        assert!(!backend2.read.is_empty());
+        assert_eq!(3, backend1.read.len() + backend2.read.len());
+
+        // Each worker reads own pages only, the last page of each worker is missing
+        assert_eq!(vec![0, 2], backend1.searched_pages);
+        assert_eq!(vec![1, 3], backend2.searched_pages);
+    }
+
+    #[tokio::test]
+    async fn process_should_be_interrupted_on_read_error() {
+        let mut config = get_test_config();
+        config.ignore_errors = false;
+
+        let mut backend = get_backend_with_accounts();
+        backend.failing_logins = vec!["demo-web".to_string()];
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+
+        assert!(find_accounts_with_empty_permissions(
+            &mut [&mut backend], &config, &progress, &get_blank_filter_options()).await.is_err());
+
+        assert_eq!(vec!["demo-acc"], get_logins(&backend.read));
+        assert_eq!(vec!["demo-acc"], get_logins(&progress.get_progress().results));
+    }
+
+    #[tokio::test]
+    async fn read_errors_should_be_skipped_if_ignore_errors_enabled() {
+        let mut config = get_test_config();
+        config.ignore_errors = true;
+
+        let mut backend = get_backend_with_accounts();
+        backend.failing_logins = vec!["demo-web".to_string()];
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+
+        let accounts = find_accounts_with_empty_permissions(
+            &mut [&mut backend], &config, &progress, &get_blank_filter_options()).await.unwrap();
+
+        assert_eq!(get_logins(&accounts), vec!["demo-acc", "demo-db"]);
+    }
+
+    #[tokio::test]
+    async fn process_should_be_continued_after_session_restart() {
+        let config = get_test_config();
+        let mut backend = get_backend_with_accounts();
+        backend.session_crashes = 1;
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+
+        let accounts = find_accounts_with_empty_permissions(
+            &mut [&mut backend], &config, &progress, &get_blank_filter_options()).await.unwrap();
+
+        assert_eq!(1, backend.restarts);
+        assert_eq!(get_logins(&accounts), vec!["demo-acc", "demo-db"]);
+    }
+
+    #[tokio::test]
+    async fn get_accounts_with_empty_permissions_via_api() {
+        init_logging();
+
+        let url = start_mock_rpc_server(|method, params| {
+            match method {
+                "account/search" => Ok(json!([
+                    { "id": 1, "name": "Hercules II", "login": "demo-acc", "categoryName": "Apps", "clientName": "BirchShop" },
+                    { "id": 2, "name": "Hercules III", "login": "demo-web", "categoryName": "Apps", "clientName": "BirchShop" },
+                    { "id": 3, "name": "Hercules IV", "login": "demo-db", "categoryName": "Apps", "clientName": "BirchShop" }
+                ])),
+                "account/view" => {
+                    let id = params["id"].as_u64().unwrap();
+
+                    let users = if id == 2 {
+                        json!([{ "id": 1, "name": "sysPass Admin", "isEdit": 0 }])
+                    } else {
+                        json!([])
+                    };
+
+                    Ok(json!({
+                        "id": id, "name": "", "login": "", "categoryName": "", "clientName": "",
+                        "users": users,
+                        "userGroups": []
+                    }))
+                },
+                _ => Err((-32601, "Method not found".to_string()))
+            }
+        }).await;
+
+        let mut config = get_test_config();
+        config.api_url = Some(url);
+        config.api_token = Some(get_random_string());
+
+        let accounts = get_accounts_with_empty_permissions(
+            &config, CommandProgress::default(), &get_blank_filter_options(), 1).await.unwrap();
+
+        assert_eq!(get_logins(&accounts), vec!["demo-acc", "demo-db"]);
+    }
+
+    fn get_progress(progress: CommandProgress, cache_file: &NamedTempFile) -> SharedProgress {
+        SharedProgress::new(progress, cache_file.path(), 10)
+    }
+
+    fn get_backend_with_accounts() -> FakeSyspassBackend {
+        let mut account1 = get_account();
+        account1.login = "demo-acc".to_string();
+
+        let mut account2 = get_account();
+        account2.name = "Hercules III".to_string();
+        account2.login = "demo-web".to_string();
+
+        let mut account3 = get_account();
+        account3.name = "Hercules IV".to_string();
+        account3.login = "demo-db".to_string();
+
+        let mut backend = FakeSyspassBackend::new(vec![account1, account2.clone(), account3]);
+        backend.logged_in = true;
+
+        backend.permissions.insert(account2, AccountPermissions {
+            group_view: vec!["Admins".to_string()],
+            ..AccountPermissions::default()
+        });
+
+        backend
+    }
+
+    fn get_blank_filter_options() -> AccountFilterOptions {
+        AccountFilterOptions {
+            category_name: String::new(),
+            client_name: String::new(),
+            login_starts_with: String::new(),
+            name_starts_with: String::new(),
+        }
+    }
+
+    fn get_logins(accounts: &[Account]) -> Vec<&str> {
+        accounts.iter().map(|account| account.login.as_str()).collect()
+    }
 
     #[test]
     fn return_true_for_match() {
@@ -1,2 +1,12 @@
 pub mod set;
 pub mod get;
+pub mod session;
+pub mod rules;
+pub mod plan;
+pub mod preflight;
+pub mod verify;
+pub mod export;
+pub mod apply;
+pub mod diff;
+pub mod audit;
+pub mod reconcile;
@@ -0,0 +1,197 @@
+use std::fmt::{Display, Formatter};
+
+use serde::Serialize;
+
+use crate::config::PermissionsConfig;
+use crate::syspass::{Account, AccountPermissions};
+
+/// Added and removed users or groups
+#[derive(Serialize,PartialEq,Debug,Clone,Default)]
+pub struct EntityChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl EntityChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl Display for EntityChanges {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let changes: Vec<String> = self.added.iter().map(|name| format!("+ '{}'", name))
+            .chain(self.removed.iter().map(|name| format!("- '{}'", name)))
+            .collect();
+
+        write!(f, "{}", changes.join(", "))
+    }
+}
+
+#[derive(Serialize,PartialEq,Debug,Clone)]
+pub struct ValueChange<T> {
+    pub old: T,
+    pub new: T,
+}
+
+/// Difference between current and expected permissions
+#[derive(Serialize,PartialEq,Debug,Clone,Default)]
+pub struct PermissionChanges {
+    pub user_view: EntityChanges,
+    pub user_edit: EntityChanges,
+    pub group_view: EntityChanges,
+    pub group_edit: EntityChanges,
+
+    pub owner: Option<ValueChange<String>>,
+    pub main_group: Option<ValueChange<String>>,
+
+    pub private_account: Option<ValueChange<bool>>,
+    pub private_account_for_group: Option<ValueChange<bool>>,
+}
+
+impl PermissionChanges {
+    pub fn is_empty(&self) -> bool {
+        self == &PermissionChanges::default()
+    }
+}
+
+impl Display for PermissionChanges {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "  no changes")
+        }
+
+        let entities = [
+            ("user-view", &self.user_view), ("user-edit", &self.user_edit),
+            ("group-view", &self.group_view), ("group-edit", &self.group_edit),
+        ];
+
+        for (title, changes) in entities {
+            if !changes.is_empty() {
+                writeln!(f, "  {}: {}", title, changes)?;
+            }
+        }
+
+        if let Some(change) = &self.owner {
+            writeln!(f, "  owner: '{}' -> '{}'", change.old, change.new)?;
+        }
+
+        if let Some(change) = &self.main_group {
+            writeln!(f, "  main-group: '{}' -> '{}'", change.old, change.new)?;
+        }
+
+        if let Some(change) = &self.private_account {
+            writeln!(f, "  private-account: {} -> {}", change.old, change.new)?;
+        }
+
+        if let Some(change) = &self.private_account_for_group {
+            writeln!(f, "  private-account-for-group: {} -> {}", change.old, change.new)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Planned changes for account
+#[derive(Serialize,PartialEq,Debug,Clone)]
+pub struct AccountChanges {
+    pub account: Account,
+    pub changes: PermissionChanges,
+}
+
+impl Display for AccountChanges {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "account '{}' (login '{}', client '{}', category '{}'):",
+                 self.account.name, self.account.login, self.account.client, self.account.category)?;
+        write!(f, "{}", self.changes)
+    }
+}
+
+/// Changes required to apply `desired` config to `current` permissions
+pub fn plan_changes(current: &AccountPermissions, desired: &PermissionsConfig) -> PermissionChanges {
+    diff_permissions(current, &current.with_config(desired))
+}
+
+pub fn diff_permissions(current: &AccountPermissions, expected: &AccountPermissions) -> PermissionChanges {
+    PermissionChanges {
+        user_view: diff_entities(&current.user_view, &expected.user_view),
+        user_edit: diff_entities(&current.user_edit, &expected.user_edit),
+        group_view: diff_entities(&current.group_view, &expected.group_view),
+        group_edit: diff_entities(&current.group_edit, &expected.group_edit),
+        owner: diff_values(&current.owner, &expected.owner),
+        main_group: diff_values(&current.main_group, &expected.main_group),
+        private_account: diff_values(&current.private_account, &expected.private_account),
+        private_account_for_group: diff_values(&current.private_account_for_group,
+                                               &expected.private_account_for_group),
+    }
+}
+
+fn diff_entities(current: &[String], expected: &[String]) -> EntityChanges {
+    EntityChanges {
+        added: expected.iter().filter(|name| !current.contains(name)).cloned().collect(),
+        removed: current.iter().filter(|name| !expected.contains(name)).cloned().collect(),
+    }
+}
+
+fn diff_values<T: PartialEq + Clone>(current: &T, expected: &T) -> Option<ValueChange<T>> {
+    if current != expected {
+        Some(ValueChange { old: current.clone(), new: expected.clone() })
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::PermissionsMode;
+    use crate::feature::perms::plan::{EntityChanges, plan_changes, ValueChange};
+    use crate::syspass::AccountPermissions;
+    use crate::tests::config::get_test_config;
+
+    #[test]
+    fn return_no_changes_if_permissions_match_config() {
+        let config = get_test_config();
+        let permissions = config.permissions.as_ref().unwrap();
+
+        let current = AccountPermissions::default().with_config(permissions);
+
+        assert!(plan_changes(&current, permissions).is_empty());
+    }
+
+    #[test]
+    fn return_added_and_changed_values() {
+        let config = get_test_config();
+        let permissions = config.permissions.as_ref().unwrap();
+
+        let current = AccountPermissions {
+            user_view: vec!["sysPass Admin".to_string()],
+            owner: "j.doe".to_string(),
+            private_account_for_group: false,
+            ..AccountPermissions::default()
+        };
+
+        let changes = plan_changes(&current, permissions);
+
+        assert!(changes.user_view.is_empty());
+        assert_eq!(permissions.user.edit, changes.user_edit.added);
+        assert_eq!(Some(ValueChange { old: "j.doe".to_string(), new: "Mr.Editor".to_string() }), changes.owner);
+        assert_eq!(Some(ValueChange { old: false, new: true }), changes.private_account_for_group);
+        assert_eq!(None, changes.private_account);
+    }
+
+    #[test]
+    fn return_removed_values_for_replace_mode() {
+        let mut config = get_test_config();
+        let permissions = config.permissions.as_mut().unwrap();
+        permissions.group.mode = PermissionsMode::Replace;
+
+        let current = AccountPermissions {
+            group_view: vec!["Admins".to_string(), "Old Team".to_string()],
+            ..AccountPermissions::default()
+        };
+
+        let changes = plan_changes(&current, permissions);
+
+        assert_eq!(EntityChanges { added: vec![], removed: vec!["Old Team".to_string()] }, changes.group_view);
+    }
+}
@@ -0,0 +1,184 @@
+use std::fmt::{Display, Formatter};
+
+use anyhow::anyhow;
+use log::{error, info};
+
+use crate::config::{AppConfig, EntityPermissionsConfig, PermissionsConfig, PermissionsMode};
+use crate::feature::perms::session::SessionSupervisor;
+use crate::syspass::{Account, SecurityEntities};
+use crate::syspass::backend::SyspassBackend;
+use crate::types::EmptyResult;
+
+/// Users and groups from config which don't exist in sysPass
+#[derive(PartialEq,Debug,Default)]
+pub struct UnknownNames {
+    pub users: Vec<String>,
+    pub groups: Vec<String>,
+}
+
+impl UnknownNames {
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty() && self.groups.is_empty()
+    }
+}
+
+impl Display for UnknownNames {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut parts: Vec<String> = vec![];
+
+        if !self.users.is_empty() {
+            parts.push(format!("users {}", quote_names(&self.users)));
+        }
+
+        if !self.groups.is_empty() {
+            parts.push(format!("groups {}", quote_names(&self.groups)));
+        }
+
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+fn quote_names(names: &[String]) -> String {
+    names.iter().map(|name| format!("'{}'", name)).collect::<Vec<String>>().join(", ")
+}
+
+/// Check that users and groups from default permissions and `permission-rules` exist in sysPass.
+///
+/// Available names are read from edit page of given account, nothing is changed.
+pub async fn check_config_security_entities(backend: &mut dyn SyspassBackend, config: &AppConfig,
+                                            account: &Account) -> EmptyResult {
+    info!("check that configured users and groups exist in syspass");
+
+    let mut session_supervisor = SessionSupervisor::new(config);
+
+    let mut read_result = backend.read_security_entities(account).await;
+
+    while let Err(e) = &read_result {
+        if !session_supervisor.recover(backend, e).await? {
+            break;
+        }
+
+        read_result = backend.read_security_entities(account).await;
+    }
+
+    let entities = read_result?;
+
+    let permissions: Vec<&PermissionsConfig> = config.permissions.iter()
+        .chain(config.permission_rules.iter().map(|rule| &rule.permissions))
+        .collect();
+
+    let unknown_names = get_unknown_names(&entities, &permissions);
+
+    if unknown_names.is_empty() {
+        info!("all configured users and groups exist in syspass");
+        Ok(())
+
+    } else {
+        error!("unknown names in config: {}", unknown_names);
+        Err(anyhow!("unknown names in config, nothing has been changed: {}", unknown_names))
+    }
+}
+
+/// Same as `check_config_security_entities`, the first account from sysPass search results is used
+/// if `account` isn't set. Check fails if sysPass has no accounts.
+pub async fn check_config_security_entities_with_any_account(backend: &mut dyn SyspassBackend, config: &AppConfig,
+                                                             account: Option<Account>) -> EmptyResult {
+    let account = match account {
+        Some(account) => account,
+        None => {
+            info!("no account to check configured users and groups against, search syspass accounts");
+
+            backend.search_accounts_page(0).await?
+                .and_then(|accounts| accounts.into_iter().next())
+                .ok_or_else(|| anyhow!("no syspass accounts found to check configured users and groups, \
+                                        nothing has been changed"))?
+        }
+    };
+
+    check_config_security_entities(backend, config, &account).await
+}
+
+/// Names to remove aren't checked, they could belong to users or groups deleted from sysPass
+pub fn get_unknown_names(entities: &SecurityEntities, permissions: &[&PermissionsConfig]) -> UnknownNames {
+    let mut unknown_names = UnknownNames::default();
+
+    for item in permissions {
+        let users = get_names_to_grant(&item.user, &item.owner);
+        add_unknown_names(&mut unknown_names.users, &entities.users, &users);
+
+        let groups = get_names_to_grant(&item.group, &item.main_group);
+        add_unknown_names(&mut unknown_names.groups, &entities.groups, &groups);
+    }
+
+    unknown_names
+}
+
+/// View and edit lists (except remove mode) plus owner or main group, if set
+fn get_names_to_grant<'a>(entity: &'a EntityPermissionsConfig, single_value: &'a str) -> Vec<&'a str> {
+    let mut names: Vec<&str> = match entity.mode {
+        PermissionsMode::Remove => vec![],
+        _ => entity.view.iter().chain(entity.edit.iter()).map(|name| name.as_str()).collect()
+    };
+
+    if !single_value.is_empty() {
+        names.push(single_value);
+    }
+
+    names
+}
+
+fn add_unknown_names(unknown_names: &mut Vec<String>, available_names: &[String], names: &[&str]) {
+    for name in names {
+        if !available_names.iter().any(|available_name| available_name == name) &&
+            !unknown_names.iter().any(|unknown_name| unknown_name == name) {
+            unknown_names.push(name.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::PermissionsMode;
+    use crate::feature::perms::preflight::{get_unknown_names, UnknownNames};
+    use crate::tests::backend::get_test_security_entities;
+    use crate::tests::config::get_test_config;
+
+    #[test]
+    fn return_no_unknown_names_for_existing_users_and_groups() {
+        let config = get_test_config();
+
+        let unknown_names = get_unknown_names(&get_test_security_entities(),
+                                              &[config.permissions.as_ref().unwrap()]);
+
+        assert!(unknown_names.is_empty());
+    }
+
+    #[test]
+    fn return_unknown_users_and_groups() {
+        let mut config = get_test_config();
+        let permissions = config.permissions.as_mut().unwrap();
+        permissions.user.edit.push("Mr.Edtior".to_string());
+        permissions.group.view.push("Admns".to_string());
+        permissions.owner = "Mr.Edtior".to_string();
+        permissions.main_group = "sysPass Admin".to_string();
+
+        let unknown_names = get_unknown_names(&get_test_security_entities(), &[permissions]);
+
+        assert_eq!(UnknownNames {
+            users: vec!["Mr.Edtior".to_string()],
+            groups: vec!["Admns".to_string(), "sysPass Admin".to_string()],
+        }, unknown_names);
+
+        assert_eq!("users 'Mr.Edtior'; groups 'Admns', 'sysPass Admin'", unknown_names.to_string());
+    }
+
+    #[test]
+    fn names_to_remove_should_not_be_checked() {
+        let mut config = get_test_config();
+        let permissions = config.permissions.as_mut().unwrap();
+        permissions.user.mode = PermissionsMode::Remove;
+        permissions.user.view = vec!["Deleted User".to_string()];
+
+        assert!(get_unknown_names(&get_test_security_entities(), &[permissions]).is_empty());
+    }
+}
@@ -0,0 +1,200 @@
+use std::path::Path;
+
+use anyhow::anyhow;
+use log::{info, warn};
+
+use crate::cache::{ACCOUNTS_RECONCILE_CACHE_FILENAME, CommandProgress, SharedProgress};
+use crate::config::AppConfig;
+use crate::feature::perms::get::{account_matches_filters, AccountFilterOptions, search_accounts};
+use crate::feature::perms::plan::AccountChanges;
+use crate::feature::perms::preflight::check_config_security_entities;
+use crate::feature::perms::rules::get_permissions_for_account;
+use crate::feature::perms::set::{AccountTask, process_account_tasks, SetOptions};
+use crate::syspass::backend::{create_logged_backends, SyspassBackend};
+use crate::types::OperationResult;
+
+/// Bring permissions of all sysPass accounts matching filters in line with config.
+///
+/// Only accounts with permissions different from config are changed.
+pub async fn reconcile_permissions_in_syspass(config: &AppConfig, progress: CommandProgress,
+                                              filter_options: &AccountFilterOptions, workers: u16,
+                                              options: &SetOptions) -> OperationResult<Vec<AccountChanges>> {
+    info!("reconcile permissions in syspass instance");
+
+    let mut backends = create_logged_backends(config, workers).await?;
+
+    let mut backend_refs: Vec<&mut dyn SyspassBackend> = backends.iter_mut()
+        .map(|backend| backend.as_mut() as &mut dyn SyspassBackend).collect();
+
+    let shared_progress = SharedProgress::new(
+        progress, Path::new(ACCOUNTS_RECONCILE_CACHE_FILENAME), config.progress_cache.set_accounts
+    );
+
+    reconcile_permissions(&mut backend_refs, config, &shared_progress, filter_options, options).await
+}
+
+/// Accounts list is received with the first backend, permissions are resolved
+/// with `permission-rules`, see `process_account_tasks`.
+///
+/// Returns accounts with differences: planned changes in dry-run mode or mismatches after verification.
+pub async fn reconcile_permissions(backends: &mut [&mut dyn SyspassBackend], config: &AppConfig,
+                                   progress: &SharedProgress, filter_options: &AccountFilterOptions,
+                                   options: &SetOptions) -> OperationResult<Vec<AccountChanges>> {
+    let syspass_accounts = match backends.first_mut() {
+        Some(backend) => search_accounts(&mut **backend, config).await?,
+        None => return Err(anyhow!("no backends to process accounts"))
+    };
+
+    let accounts_count = syspass_accounts.len();
+
+    let mut tasks: Vec<AccountTask> = vec![];
+
+    for (index, account) in syspass_accounts.into_iter().enumerate() {
+        if progress.is_processed(&account) {
+            info!("account '{}' (login '{}') has been processed already, skip", account.name, account.login);
+
+        } else if !account_matches_filters(&account, filter_options) {
+            info!("account '{}' (login '{}') doesn't match filter options, skip", account.name, account.login);
+
+        } else {
+            match get_permissions_for_account(config, &account) {
+                Some(permissions) => tasks.push(AccountTask { index, account, permissions }),
+                None => warn!("no permission rule matches account '{}' and default permissions aren't set, skip",
+                              account.login)
+            }
+        }
+    }
+
+    if let (Some(backend), Some(task)) = (backends.first_mut(), tasks.first()) {
+        check_config_security_entities(&mut **backend, config, &task.account).await?;
+    }
+
+    let reconcile_options = SetOptions {
+        dry_run: options.dry_run,
+        verify: options.verify,
+        skip_unchanged: true,
+    };
+
+    let changes = process_account_tasks(backends, config, tasks.into_iter().map(Ok), accounts_count,
+                                        progress, &reconcile_options).await?;
+
+    Ok(changes.into_iter().filter(|item| !item.changes.is_empty()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::NamedTempFile;
+
+    use crate::cache::{CommandProgress, SharedProgress};
+    use crate::config::{AppConfig, PermissionsMode};
+    use crate::feature::perms::get::AccountFilterOptions;
+    use crate::feature::perms::reconcile::reconcile_permissions;
+    use crate::feature::perms::set::SetOptions;
+    use crate::syspass::AccountPermissions;
+    use crate::tests::backend::FakeSyspassBackend;
+    use crate::tests::config::get_test_config;
+    use crate::tests::xml::get_xml_accounts;
+
+    #[tokio::test]
+    async fn only_accounts_with_differences_should_be_changed() {
+        let config = get_test_config();
+
+        let accounts = get_xml_accounts();
+        let mut backend = get_backend_with_applied_permissions(&config);
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = SharedProgress::new(CommandProgress::default(), cache_file.path(), 10);
+
+        let changes = reconcile_permissions(&mut [&mut backend], &config, &progress,
+                                            &get_blank_filter_options(), &get_options(false)).await.unwrap();
+
+        assert!(changes.is_empty());
+        assert_eq!(vec![accounts[1].clone()], backend.written);
+        assert_eq!(3, progress.get_progress().processed.len());
+        assert_eq!(Some(&AccountPermissions::default().with_config(config.permissions.as_ref().unwrap())),
+                   backend.permissions.get(&accounts[1]));
+    }
+
+    #[tokio::test]
+    async fn return_planned_changes_for_accounts_with_differences_in_dry_run_mode() {
+        let config = get_test_config();
+
+        let accounts = get_xml_accounts();
+        let mut backend = get_backend_with_applied_permissions(&config);
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = SharedProgress::new(CommandProgress::default(), cache_file.path(), 10);
+
+        let changes = reconcile_permissions(&mut [&mut backend], &config, &progress,
+                                            &get_blank_filter_options(), &get_options(true)).await.unwrap();
+
+        assert_eq!(1, changes.len());
+        assert_eq!(accounts[1], changes[0].account);
+        assert!(backend.written.is_empty());
+    }
+
+    #[tokio::test]
+    async fn only_differences_should_be_written_in_replace_mode() {
+        let mut config = get_test_config();
+
+        let permissions = config.permissions.as_mut().unwrap();
+        permissions.group.mode = PermissionsMode::Replace;
+
+        let accounts = get_xml_accounts();
+        let mut backend = get_backend_with_applied_permissions(&config);
+
+        let expected_permissions = backend.permissions.get(&accounts[0]).unwrap().clone();
+
+        let mut current_permissions = expected_permissions.clone();
+        current_permissions.group_view.push("Old Team".to_string());
+        backend.permissions.insert(accounts[0].clone(), current_permissions);
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = SharedProgress::new(CommandProgress::default(), cache_file.path(), 10);
+
+        let changes = reconcile_permissions(&mut [&mut backend], &config, &progress,
+                                            &get_blank_filter_options(), &get_options(false)).await.unwrap();
+
+        assert!(changes.is_empty());
+        assert_eq!(vec![accounts[0].clone(), accounts[1].clone()], backend.written);
+
+        let correction = &backend.corrections[0];
+        assert!(correction.group_view.add.is_empty());
+        assert_eq!(vec!["Old Team".to_string()], correction.group_view.remove);
+        assert!(correction.user_view.is_empty() && correction.user_edit.is_empty());
+        assert!(correction.group_edit.is_empty());
+        assert_eq!("", correction.owner);
+        assert_eq!("", correction.main_group);
+
+        assert_eq!(Some(&expected_permissions), backend.permissions.get(&accounts[0]));
+    }
+
+    /// Permissions from config are applied for all accounts except the second one
+    fn get_backend_with_applied_permissions(config: &AppConfig) -> FakeSyspassBackend {
+        let accounts = get_xml_accounts();
+
+        let mut backend = FakeSyspassBackend::new(accounts.clone());
+        backend.logged_in = true;
+
+        let applied_permissions = AccountPermissions::default()
+            .with_config(config.permissions.as_ref().unwrap());
+
+        backend.permissions.insert(accounts[0].clone(), applied_permissions.clone());
+        backend.permissions.insert(accounts[2].clone(), applied_permissions);
+
+        backend
+    }
+
+    fn get_options(dry_run: bool) -> SetOptions {
+        SetOptions { dry_run, verify: false, skip_unchanged: true }
+    }
+
+    fn get_blank_filter_options() -> AccountFilterOptions {
+        AccountFilterOptions {
+            category_name: String::new(),
+            client_name: String::new(),
+            login_starts_with: String::new(),
+            name_starts_with: String::new(),
+        }
+    }
+}
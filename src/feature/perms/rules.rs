@@ -0,0 +1,102 @@
+use log::debug;
+
+use crate::config::{AccountMatcherConfig, AppConfig, PermissionsConfig, ValueMatcherConfig};
+use crate::syspass::Account;
+
+/// Returns permissions of the first matching rule, otherwise default permissions (if provided)
+pub fn get_permissions_for_account<'a>(config: &'a AppConfig, account: &Account) -> Option<&'a PermissionsConfig> {
+    let rule = config.permission_rules.iter().enumerate()
+        .find(|(_, rule)| account_matches(&rule.matcher, account));
+
+    match rule {
+        Some((index, rule)) => {
+            debug!("account '{}' matches permission rule #{}", account.login, index + 1);
+            Some(&rule.permissions)
+        }
+        None => config.permissions.as_ref()
+    }
+}
+
+pub fn account_matches(matcher: &AccountMatcherConfig, account: &Account) -> bool {
+    value_matches(&matcher.category, &account.category) &&
+        value_matches(&matcher.client, &account.client) &&
+        value_matches(&matcher.login, &account.login) &&
+        value_matches(&matcher.name, &account.name)
+}
+
+fn value_matches(matcher: &Option<ValueMatcherConfig>, value: &str) -> bool {
+    match matcher {
+        None => true,
+        Some(ValueMatcherConfig::Exact(expected)) => value == expected,
+        Some(ValueMatcherConfig::Prefix(prefix)) => value.starts_with(prefix),
+        Some(ValueMatcherConfig::Regex(regex)) => regex.is_match(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{AccountMatcherConfig, RegexMatcher, ValueMatcherConfig};
+    use crate::feature::perms::rules::{account_matches, get_permissions_for_account};
+    use crate::syspass::Account;
+    use crate::tests::config::get_test_config;
+
+    #[test]
+    fn return_permissions_of_first_matching_rule() {
+        let config = get_test_config();
+
+        let account = get_account("db-main", "Production");
+
+        assert_eq!(Some(&config.permission_rules[0].permissions),
+                   get_permissions_for_account(&config, &account));
+    }
+
+    #[test]
+    fn return_default_permissions_if_no_rule_matches() {
+        let config = get_test_config();
+
+        let account = get_account("db-main", "Staging");
+
+        assert_eq!(config.permissions.as_ref(), get_permissions_for_account(&config, &account));
+    }
+
+    #[test]
+    fn return_none_without_default_permissions() {
+        let mut config = get_test_config();
+        config.permissions = None;
+
+        assert!(get_permissions_for_account(&config, &get_account("web", "Production")).is_none());
+    }
+
+    #[test]
+    fn all_conditions_should_be_met() {
+        let matcher = AccountMatcherConfig {
+            client: Some(ValueMatcherConfig::Prefix("Birch".to_string())),
+            name: Some(ValueMatcherConfig::Regex(RegexMatcher::new("^Hercules [IV]+$").unwrap())),
+            ..AccountMatcherConfig::default()
+        };
+
+        let mut account = get_account("demo", "Apps");
+        assert!(account_matches(&matcher, &account));
+
+        account.name = "Hercules 4".to_string();
+        assert!(!account_matches(&matcher, &account));
+
+        account.name = "Hercules IV".to_string();
+        account.client = "KalinkaStore".to_string();
+        assert!(!account_matches(&matcher, &account));
+    }
+
+    #[test]
+    fn blank_matcher_should_match_any_account() {
+        assert!(account_matches(&AccountMatcherConfig::default(), &get_account("demo", "Apps")));
+    }
+
+    fn get_account(login: &str, category: &str) -> Account {
+        Account {
+            name: "Hercules IV".to_string(),
+            login: login.to_string(),
+            category: category.to_string(),
+            client: "BirchStore".to_string(),
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use anyhow::anyhow;
+use log::{error, info, warn};
+
+use crate::config::AppConfig;
+use crate::syspass::backend::SyspassBackend;
+use crate::syspass::webdriver::is_fatal_session_error;
+use crate::types::{EmptyResult, OperationResult};
+
+/// Restarts backend session after fatal webdriver errors (page crash, etc.)
+/// and recycles it every N accounts (`webdriver.recycle-after`).
+pub struct SessionSupervisor {
+    max_restarts: u16,
+    restarts: u16,
+
+    recycle_after: Option<u16>,
+    processed_accounts: u16,
+}
+
+impl SessionSupervisor {
+    pub fn new(config: &AppConfig) -> SessionSupervisor {
+        SessionSupervisor {
+            max_restarts: config.webdriver.max_restarts,
+            restarts: 0,
+            recycle_after: config.webdriver.recycle_after,
+            processed_accounts: 0,
+        }
+    }
+
+    /// Call after each account processed with backend.
+    ///
+    /// Recycles session every `recycle-after` accounts.
+    pub async fn account_processed(&mut self, backend: &mut dyn SyspassBackend) -> EmptyResult {
+        if let Some(recycle_after) = self.recycle_after {
+            self.processed_accounts += 1;
+
+            if recycle_after > 0 && self.processed_accounts >= recycle_after {
+                info!("recycle webdriver session after {} accounts", self.processed_accounts);
+                backend.restart().await?;
+                self.processed_accounts = 0;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if session has been restarted and operation should be repeated.
+    ///
+    /// Returns error if session is broken and restarts limit has been reached.
+    pub async fn recover(&mut self, backend: &mut dyn SyspassBackend,
+                         error: &anyhow::Error) -> OperationResult<bool> {
+        if !is_fatal_session_error(error) {
+            return Ok(false)
+        }
+
+        if self.restarts >= self.max_restarts {
+            error!("webdriver session is broken: {}", error);
+            return Err(anyhow!("webdriver session restarts limit ({}) has been reached", self.max_restarts))
+        }
+
+        self.restarts += 1;
+        warn!("webdriver session is broken: {}", error);
+        warn!("restart webdriver session [{}/{}]", self.restarts, self.max_restarts);
+
+        backend.restart().await?;
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use crate::feature::perms::session::SessionSupervisor;
+    use crate::tests::backend::FakeSyspassBackend;
+    use crate::tests::config::get_test_config;
+
+    #[tokio::test]
+    async fn session_should_be_restarted_after_page_crash() {
+        let config = get_test_config();
+        let mut supervisor = SessionSupervisor::new(&config);
+        let mut backend = FakeSyspassBackend::default();
+
+        let error = anyhow!("webdriver returned error: unknown error: session deleted because of page crash");
+
+        assert!(supervisor.recover(&mut backend, &error).await.unwrap());
+        assert_eq!(1, backend.restarts);
+    }
+
+    #[tokio::test]
+    async fn session_should_not_be_restarted_for_regular_errors() {
+        let config = get_test_config();
+        let mut supervisor = SessionSupervisor::new(&config);
+        let mut backend = FakeSyspassBackend::default();
+
+        let error = anyhow!("webdriver returned error: element not interactable");
+
+        assert!(!supervisor.recover(&mut backend, &error).await.unwrap());
+        assert_eq!(0, backend.restarts);
+    }
+
+    #[tokio::test]
+    async fn session_should_be_recycled_every_n_accounts() {
+        let mut config = get_test_config();
+        config.webdriver.recycle_after = Some(2);
+
+        let mut supervisor = SessionSupervisor::new(&config);
+        let mut backend = FakeSyspassBackend::default();
+
+        for _ in 0..5 {
+            supervisor.account_processed(&mut backend).await.unwrap();
+        }
+
+        assert_eq!(2, backend.restarts);
+    }
+
+    #[tokio::test]
+    async fn session_should_not_be_recycled_without_option() {
+        let mut config = get_test_config();
+        config.webdriver.recycle_after = None;
+
+        let mut supervisor = SessionSupervisor::new(&config);
+        let mut backend = FakeSyspassBackend::default();
+
+        for _ in 0..5 {
+            supervisor.account_processed(&mut backend).await.unwrap();
+        }
+
+        assert_eq!(0, backend.restarts);
+    }
+
+    #[tokio::test]
+    async fn return_error_when_restarts_limit_reached() {
+        let mut config = get_test_config();
+        config.webdriver.max_restarts = 1;
+
+        let mut supervisor = SessionSupervisor::new(&config);
+        let mut backend = FakeSyspassBackend::default();
+
+        let error = anyhow!("invalid session id");
+
+        assert!(supervisor.recover(&mut backend, &error).await.unwrap());
+        assert!(supervisor.recover(&mut backend, &error).await.is_err());
+        assert_eq!(1, backend.restarts);
+    }
+}
@@ -1,240 +1,935 @@
 use std::path::Path;
-use std::thread;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 
 use anyhow::anyhow;
-use log::{debug, error, info};
-use thirtyfour::{DesiredCapabilities, WebDriver};
+use futures::future::join_all;
+use log::{error, info, warn};
+
+use crate::cache::{ACCOUNTS_SET_CACHE_FILENAME, CommandProgress, SharedProgress};
+use crate::config::{AppConfig, PermissionsConfig};
+use crate::feature::perms::plan::{AccountChanges, PermissionChanges, plan_changes};
+use crate::feature::perms::preflight::check_config_security_entities_with_any_account;
+use crate::feature::perms::rules::get_permissions_for_account;
+use crate::feature::perms::session::SessionSupervisor;
+use crate::syspass::{Account, AccountPermissions, PermissionsCorrection};
+use crate::syspass::api::SyspassApiClient;
+use crate::syspass::backend::{create_logged_backends, SyspassBackend};
+use crate::types::{EmptyResult, OperationResult};
+use crate::xml::{open_xml_accounts, XmlAccount, XmlConfig, XmlIndex, XmlLoadOptions};
+
+/// Account with permissions to set
+pub struct AccountTask<'a> {
+    /// Position in source file
+    pub index: usize,
+
+    pub account: Account,
+    pub permissions: &'a PermissionsConfig,
+}
+
+pub struct SetOptions {
+    /// Don't change anything, return planned changes for each account
+    pub dry_run: bool,
 
-use crate::cache::{ACCOUNTS_SET_CACHE_FILENAME, save_cache_data_into_file};
-use crate::config::AppConfig;
-use crate::syspass::Account;
-use crate::syspass::login::{login_to_syspass, relogin_if_required};
-use crate::syspass::perms::set_permissions_for_account;
-use crate::types::EmptyResult;
-use crate::xml::get_xml_config_from_file;
+    /// Re-read permissions after save, return accounts with mismatches
+    pub verify: bool,
+
+    /// Read permissions before save, accounts which already match config aren't changed
+    pub skip_unchanged: bool,
+}
 
 /// Set permissions for accounts from given xml-file
 ///
-/// `latest_processed` - last successfully processed account.
-pub async fn set_permissions_for_accounts_in_syspass(config: &AppConfig, xml_file: &Path,
-                                               latest_processed_account: &Account) -> EmptyResult {
-    let mut caps = DesiredCapabilities::chrome();
+/// `xml_tag` - process accounts with given xml tag only.
+///
+/// `progress` - progress of previous run, processed accounts will be skipped.
+///
+/// `workers` - webdriver sessions count, accounts are processed in parallel.
+pub async fn set_permissions_for_accounts_in_syspass(config: &AppConfig, xml_file: &Path, xml_tag: Option<&str>,
+                                                     progress: CommandProgress, workers: u16,
+                                                     options: &SetOptions) -> OperationResult<Vec<AccountChanges>> {
+    let (xml_index, xml_accounts, accounts_count) = open_xml_accounts_with_tag(config, xml_file, xml_tag)?;
+
+    if let Some(api_client) = SyspassApiClient::from_config(config) {
+        check_xml_meta_properties_via_api(&api_client, &xml_index).await?;
+    }
 
-    for arg in config.webdriver.args.iter() {
-        caps.add_chrome_arg(&arg)?;
+    let mut backends = create_logged_backends(config, workers).await?;
+
+    let mut backend_refs: Vec<&mut dyn SyspassBackend> = backends.iter_mut()
+        .map(|backend| backend.as_mut() as &mut dyn SyspassBackend).collect();
+
+    let shared_progress = SharedProgress::new(
+        progress, Path::new(ACCOUNTS_SET_CACHE_FILENAME), config.progress_cache.set_accounts
+    );
+
+    set_permissions_for_xml_accounts(&mut backend_refs, config, &xml_index, xml_accounts, accounts_count,
+                                     &shared_progress, options).await
+}
+
+type XmlAccountsIterator = Box<dyn Iterator<Item = OperationResult<XmlAccount>>>;
+
+/// Returns xml index, accounts iterator and accounts count. Accounts are filtered by `xml_tag` if it's set.
+///
+/// Index pass validates the whole file in `xml-strict` mode, so invalid file is rejected before any change.
+fn open_xml_accounts_with_tag(config: &AppConfig, xml_file: &Path,
+                              xml_tag: Option<&str>) -> OperationResult<(XmlIndex, XmlAccountsIterator, usize)> {
+    let xml_options = XmlLoadOptions {
+        password: config.xml_password.as_deref(),
+        sign_key: config.xml_sign_key.as_deref(),
+        strict: config.xml_strict,
+    };
+
+    let (xml_index, xml_accounts) = open_xml_accounts(xml_file, &xml_options)?;
+
+    match xml_tag {
+        Some(tag) => {
+            let accounts_count = xml_index.get_accounts_count_with_tag(tag);
+            info!("xml accounts with tag '{}': {}", tag, accounts_count);
+
+            let tag = tag.to_string();
+            let xml_accounts = xml_accounts.filter(move |xml_account| match xml_account {
+                Ok(xml_account) => xml_account.has_tag(&tag),
+                Err(_) => true
+            });
+
+            Ok((xml_index, Box::new(xml_accounts), accounts_count))
+        }
+        None => {
+            let accounts_count = xml_index.accounts_count;
+            Ok((xml_index, Box::new(xml_accounts), accounts_count))
+        }
     }
+}
+
+/// Set permissions for accounts which are already loaded, see `set_permissions_for_xml_accounts`
+pub async fn set_permissions_for_accounts(backends: &mut [&mut dyn SyspassBackend], config: &AppConfig,
+                                          xml_config: &XmlConfig, progress: &SharedProgress,
+                                          options: &SetOptions) -> OperationResult<Vec<AccountChanges>> {
+    let xml_index = xml_config.get_index();
+
+    let xml_accounts = xml_config.accounts.iter().cloned().map(Ok);
+
+    set_permissions_for_xml_accounts(backends, config, &xml_index, xml_accounts, xml_config.accounts.len(),
+                                     progress, options).await
+}
+
+/// Permissions are resolved for each xml account with `permission-rules`, see `process_account_tasks`.
+///
+/// Accounts are read lazily by workers, so processing starts before the whole file is parsed.
+/// Clients and categories are resolved with `xml_index`.
+///
+/// Process is aborted before any change if configured users or groups don't exist in sysPass.
+pub async fn set_permissions_for_xml_accounts<'a>(backends: &mut [&mut dyn SyspassBackend], config: &'a AppConfig,
+                                                  xml_index: &'a XmlIndex,
+                                                  xml_accounts: impl Iterator<Item = OperationResult<XmlAccount>> + 'a,
+                                                  accounts_count: usize, progress: &'a SharedProgress,
+                                                  options: &'a SetOptions) -> OperationResult<Vec<AccountChanges>> {
+    let tasks = xml_accounts.enumerate().filter_map(move |(index, xml_account)| {
+        let xml_account = match xml_account {
+            Ok(xml_account) => xml_account,
+            Err(e) => return Some(Err(e))
+        };
+
+        let account = match get_account_for_xml_account(&xml_account, xml_index) {
+            Ok(account) => account,
+            Err(e) if config.ignore_errors => {
+                error!("{}", e);
+                error!("couldn't process xml account with login '{}', skip", xml_account.login);
+                return None
+            }
+            Err(e) => return Some(Err(e))
+        };
+
+        if progress.is_processed(&account) {
+            info!("xml account with login '{}' (name '{}') has been processed already, skip",
+                  &account.login, &account.name);
+            return None
+        }
+
+        match get_permissions_for_account(config, &account) {
+            Some(permissions) => Some(Ok(AccountTask { index, account, permissions })),
+            None => {
+                warn!("no permission rule matches account '{}' and default permissions aren't set, skip",
+                      account.login);
+                None
+            }
+        }
+    });
+
+    let mut tasks = tasks.peekable();
+
+    let check_account = match tasks.peek() {
+        Some(Ok(task)) => Some(task.account.clone()),
+        _ => None
+    };
+
+    match backends.first_mut() {
+        Some(backend) => check_config_security_entities_with_any_account(&mut **backend, config, check_account).await?,
+        None => return Err(anyhow!("no backends to process accounts"))
+    }
+
+    process_account_tasks(backends, config, tasks, accounts_count, progress, options).await
+}
+
+/// Accounts are distributed between backends, each backend is a separate worker.
+/// Workers take tasks from shared iterator one by one. Task error (i.e. xml file can't be read further)
+/// stops the whole process regardless of `ignore-errors`, otherwise following accounts would be lost silently.
+///
+/// Returns (in tasks order):
+/// - planned changes for each account in dry-run mode
+/// - changes which weren't applied, if verification is enabled
+pub async fn process_account_tasks<'a>(backends: &mut [&mut dyn SyspassBackend], config: &'a AppConfig,
+                                       tasks: impl Iterator<Item = OperationResult<AccountTask<'a>>> + 'a,
+                                       accounts_count: usize, progress: &'a SharedProgress,
+                                       options: &'a SetOptions) -> OperationResult<Vec<AccountChanges>> {
+    let mut has_errors = false;
+
+    info!("accounts: {}, workers: {}, dry-run: {}, verify: {}",
+          accounts_count, backends.len(), options.dry_run, options.verify);
+
+    let context = WorkerContext {
+        config,
+        options,
+        accounts_count,
+        tasks: Mutex::new(Box::new(tasks)),
+        progress,
+        stop: AtomicBool::new(false),
+        changes: Mutex::new(vec![]),
+    };
+
+    let workers = backends.iter_mut().enumerate().map(|(worker_index, backend)| {
+        let context = &context;
+
+        async move {
+            let result = set_permissions_worker(worker_index + 1, &mut **backend, context).await;
+
+            if result.is_err() {
+                context.stop.store(true, Ordering::SeqCst);
+            }
+
+            result
+        }
+    });
+
+    for worker_result in join_all(workers).await {
+        has_errors |= worker_result?;
+    }
+
+    let mut changes = context.changes.into_inner().expect("changes lock error");
+    changes.sort_by_key(|(index, _)| *index);
+
+    let changes = changes.into_iter().map(|(_, changes)| changes).collect();
+
+    if !has_errors {
+        info!("accounts have been processed");
+        Ok(changes)
 
-    let driver = WebDriver::new(&config.webdriver.url, caps).await?;
+    } else if !config.ignore_errors {
+        Err(anyhow!("process has been interrupted due error"))
 
-    let xml_config = get_xml_config_from_file(xml_file)?;
+    } else {
+        info!("accounts have been partially processed");
+        Ok(changes)
+    }
+}
 
-    login_to_syspass(&driver, &config.syspass_url,
-                     &config.auth.login, &config.auth.password).await?;
+/// State shared between workers
+struct WorkerContext<'a> {
+    config: &'a AppConfig,
+    options: &'a SetOptions,
+    accounts_count: usize,
+    tasks: Mutex<Box<dyn Iterator<Item = OperationResult<AccountTask<'a>>> + 'a>>,
+    progress: &'a SharedProgress,
+    stop: AtomicBool,
+
+    /// (xml index, changes)
+    changes: Mutex<Vec<(usize, AccountChanges)>>,
+}
 
-    info!("user '{}' logged to syspass", &config.auth.login);
+impl WorkerContext<'_> {
+    fn add_changes(&self, task: &AccountTask, changes: PermissionChanges) {
+        self.changes.lock().expect("changes lock error").push(
+            (task.index, AccountChanges { account: task.account.clone(), changes })
+        );
+    }
+}
 
-    debug!("wait after login redirect {} ms", config.delays.after_login);
-    thread::sleep(Duration::from_millis(config.delays.after_login));
+/// Process accounts from shared iterator until it's empty.
+///
+/// Returns `true` if some accounts weren't processed due errors.
+async fn set_permissions_worker(worker_id: usize, backend: &mut dyn SyspassBackend,
+                                context: &WorkerContext<'_>) -> OperationResult<bool> {
+    let config = context.config;
 
     let mut has_errors = false;
 
-    let accounts_count = xml_config.accounts.len();
+    let mut session_supervisor = SessionSupervisor::new(config);
 
     let separator = "-".repeat(128);
 
-    let cache_file_path = Path::new(ACCOUNTS_SET_CACHE_FILENAME);
-    let mut cache_items_counter: u16 = 0;
+    while !context.stop.load(Ordering::SeqCst) {
+        let task = context.tasks.lock().expect("tasks lock error").next();
 
-    let mut process_resumed = false;
+        let task = match task {
+            Some(Ok(task)) => task,
+            Some(Err(e)) => {
+                error!("{}", e);
+                info!("process has been interrupted due error");
+                context.stop.store(true, Ordering::SeqCst);
+                return Err(e)
+            }
+            None => break
+        };
 
-    for (i, account) in xml_config.accounts.iter().enumerate() {
         info!("{}", separator);
-        info!("PROCESSING '{}' [{}/{}]", account, i, accounts_count);
+        info!("[worker {}] PROCESSING '{}' (login '{}') [{}/{}]", worker_id, task.account.name,
+              task.account.login, task.index + 1, context.accounts_count);
         info!("{}", separator);
-        let client_found = xml_config.clients.iter()
-            .find(|client|client.id == account.client_id);
-
-        match client_found {
-            Some(client) => {
-
-                let category_found = xml_config.categories.iter()
-                                .find(|category| category.id == account.category_id);
-
-                match category_found {
-                    Some(category) => {
-
-                        relogin_if_required(&driver, &config).await?;
-
-                        if !process_resumed {
-                            if !xml_account_matches_latest_processed(
-                                &account.login, &account.name,
-                                &category.name, &client.name,
-                                &latest_processed_account) {
-                                info!("xml account with login '{}' (name '{}') doesn't match with latest processed account '{}', skip", &latest_processed_account.login, &account.login, &account.name);
-
-                            } else {
-                                info!("xml account with login '{}' (name '{}') matched with latest processed account, process resumed", &account.login, &account.name);
-                                process_resumed = true;
-                            }
-
-                            continue;
-                        }
-
-                        match set_permissions_for_account(
-                            &config, &driver,
-                            &account.login, &client.name,
-                            &category.name
-                        ).await {
-                            Ok(_) => {
-                                cache_items_counter += 1;
-                                debug!("cache items counter: {}", cache_items_counter);
-
-                                info!("permissions have been set for account login '{}'", account.login);
-
-                                if cache_items_counter >= config.progress_cache.set_accounts {
-                                    let account = get_account_from_xml_account(
-                                        &account.login, &account.name,
-                                        &category.name, &client.name
-                                    );
-
-                                    match save_cache_data_into_file(&account, cache_file_path) {
-                                        Ok(_) => {
-                                            info!("accounts cache has been updated");
-                                            cache_items_counter = 0;
-                                        },
-                                        Err(e) => error!("cannot update accounts cache: {}", e)
-                                    }
-                                }
-
-                            },
-                            Err(e) => {
-                                error!("{}", e);
-                                error!("couldn't find account '{}'", account.login);
-                                has_errors = true;
-                            },
-                        }
-                    }
-                    None => {
-                        error!("account configuration error, client wasn't found by id {}", account.category_id);
-                        has_errors = true;
+
+        let permissions = task.permissions;
+
+        let result = if context.options.dry_run {
+            read_permissions(backend, &mut session_supervisor, &task.account).await?
+                .map(|current_permissions| {
+                    let changes = plan_changes(&current_permissions, permissions);
+                    info!("planned changes for account login '{}': {:?}", task.account.login, changes);
+                    context.add_changes(&task, changes);
+                })
+
+        } else if context.options.skip_unchanged {
+            match read_permissions(backend, &mut session_supervisor, &task.account).await? {
+                Ok(current_permissions) => {
+                    let changes = plan_changes(&current_permissions, permissions);
+
+                    if changes.is_empty() {
+                        info!("permissions match config for account login '{}', skip", task.account.login);
+                        context.progress.add_processed(&task.account, None);
+                        Ok(())
+
+                    } else {
+                        info!("correct permissions for account login '{}': {:?}", task.account.login, changes);
+                        let correction = PermissionsCorrection::new(
+                            &current_permissions, &current_permissions.with_config(permissions)
+                        );
+                        set_account_permissions(backend, &mut session_supervisor, context, &task,
+                                                Some(&correction)).await?
                     }
                 }
-
+                Err(e) => Err(e)
             }
-            None => {
-                error!("account configuration error, client wasn't found by id {}", account.client_id);
-                has_errors = true;
+
+        } else {
+            set_account_permissions(backend, &mut session_supervisor, context, &task, None).await?
+        };
+
+        session_supervisor.account_processed(backend).await?;
+
+        if let Err(e) = result {
+            error!("{}", e);
+            error!("couldn't process account '{}'", task.account.login);
+            has_errors = true;
+
+            if !config.ignore_errors {
+                info!("process has been interrupted due error");
+                context.stop.store(true, Ordering::SeqCst);
             }
         }
+    }
+
+    Ok(has_errors)
+}
 
-        if has_errors && !config.ignore_errors {
-            info!("process has been interrupted due error");
+/// Write permissions for account, then verify them if required.
+///
+/// Only `correction` is written if it's set, otherwise whole permissions config is applied.
+///
+/// Outer error means session couldn't be recovered
+async fn set_account_permissions(backend: &mut dyn SyspassBackend, session_supervisor: &mut SessionSupervisor,
+                                 context: &WorkerContext<'_>, task: &AccountTask<'_>,
+                                 correction: Option<&PermissionsCorrection>) -> OperationResult<EmptyResult> {
+    let permissions = task.permissions;
+
+    let set_result = write_permissions(backend, session_supervisor, &task.account, permissions,
+                                       correction).await?;
+
+    let result = match set_result {
+        Ok(_) if context.options.verify => {
+            info!("verify permissions for account login '{}'", task.account.login);
+
+            read_permissions(backend, session_supervisor, &task.account).await?
+                .map(|current_permissions| {
+                    let changes = plan_changes(&current_permissions, permissions);
+
+                    if changes.is_empty() {
+                        info!("permissions have been verified for account login '{}'", task.account.login);
+                        context.progress.add_processed(&task.account, None);
+
+                    } else {
+                        error!("permissions mismatch for account login '{}': {:?}", task.account.login, changes);
+                        context.add_changes(task, changes);
+                    }
+                })
+        }
+        Ok(_) => {
+            info!("permissions have been set for account login '{}'", task.account.login);
+            context.progress.add_processed(&task.account, None);
+            Ok(())
+        }
+        Err(e) => Err(e)
+    };
+
+    Ok(result)
+}
+
+/// Outer error means session couldn't be recovered
+async fn read_permissions(backend: &mut dyn SyspassBackend, session_supervisor: &mut SessionSupervisor,
+                          account: &Account) -> OperationResult<OperationResult<AccountPermissions>> {
+    let mut read_result = backend.read_permissions(account).await;
+
+    while let Err(e) = &read_result {
+        if !session_supervisor.recover(backend, e).await? {
             break;
         }
 
+        read_result = backend.read_permissions(account).await;
     }
 
-    if !has_errors {
-        info!("permissions have been set for accounts");
-        Ok(())
+    Ok(read_result)
+}
 
-    } else {
-        if !config.ignore_errors {
-            Err(anyhow!("process has been interrupted due error"))
+/// Outer error means session couldn't be recovered
+async fn write_permissions(backend: &mut dyn SyspassBackend, session_supervisor: &mut SessionSupervisor,
+                           account: &Account, permissions: &PermissionsConfig,
+                           correction: Option<&PermissionsCorrection>) -> OperationResult<EmptyResult> {
+    let mut set_result = write_permissions_or_correction(backend, account, permissions, correction).await;
 
-        } else {
-            info!("permissions have been partially set for accounts");
-            Ok(())
+    while let Err(e) = &set_result {
+        if !session_supervisor.recover(backend, e).await? {
+            break;
         }
+
+        set_result = write_permissions_or_correction(backend, account, permissions, correction).await;
     }
 
+    Ok(set_result)
 }
 
-fn xml_account_matches_latest_processed(xml_account_login: &str,
-                                        xml_account_name: &str,
-                                        xml_account_category: &str,
-                                        xml_account_client: &str,
-                                        latest_processed_account: &Account) -> bool {
-    (latest_processed_account.login.is_empty() &&
-    latest_processed_account.name.is_empty() &&
-    latest_processed_account.category.is_empty() &&
-    latest_processed_account.client.is_empty()) ||
+async fn write_permissions_or_correction(backend: &mut dyn SyspassBackend, account: &Account,
+                                         permissions: &PermissionsConfig,
+                                         correction: Option<&PermissionsCorrection>) -> EmptyResult {
+    match correction {
+        Some(correction) => backend.correct_permissions(account, correction).await,
+        None => backend.write_permissions(account, permissions).await
+    }
+}
 
-    (latest_processed_account.login == xml_account_login &&
-    latest_processed_account.name == xml_account_name &&
-    latest_processed_account.category == xml_account_category &&
-    latest_processed_account.client == xml_account_client)
+fn get_account_for_xml_account(xml_account: &XmlAccount, xml_index: &XmlIndex) -> OperationResult<Account> {
+    let client = xml_index.clients.iter()
+        .find(|client| client.id == xml_account.client_id)
+        .ok_or_else(|| anyhow!("account configuration error, client wasn't found by id {}", xml_account.client_id))?;
+
+    let category = xml_index.categories.iter()
+        .find(|category| category.id == xml_account.category_id)
+        .ok_or_else(|| anyhow!("account configuration error, category wasn't found by id {}", xml_account.category_id))?;
+
+    Ok(
+        Account {
+            name: xml_account.name.to_string(),
+            login: xml_account.login.to_string(),
+            category: category.name.to_string(),
+            client: client.name.to_string(),
+        }
+    )
 }
 
-fn get_account_from_xml_account(xml_account_login: &str,
-                                xml_account_name: &str,
-                                xml_account_category: &str,
-                                xml_account_client: &str) -> Account {
-    Account {
-        name: xml_account_name.to_string(),
-        login: xml_account_login.to_string(),
-        category: xml_account_category.to_string(),
-        client: xml_account_client.to_string(),
+/// Warn about xml categories and clients which are absent in sysPass
+async fn check_xml_meta_properties_via_api(api_client: &SyspassApiClient, xml_index: &XmlIndex) -> EmptyResult {
+    let categories = api_client.search_categories("").await?;
+
+    for category in &xml_index.categories {
+        if !categories.iter().any(|c| c.name == category.name) {
+            warn!("category '{}' wasn't found in syspass, related accounts won't be found", category.name);
+        }
+    }
+
+    let clients = api_client.search_clients("").await?;
+
+    for client in &xml_index.clients {
+        if !clients.iter().any(|c| c.name == client.name) {
+            warn!("client '{}' wasn't found in syspass, related accounts won't be found", client.name);
+        }
     }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::feature::perms::set::xml_account_matches_latest_processed;
-    use crate::tests::account::get_sample_account;
-    use crate::tests::get_random_string;
+    use std::fs;
+    use std::io::Write;
+    use std::path::Path;
+
+    use tempfile::NamedTempFile;
+
+    use crate::cache::{CommandProgress, SharedProgress};
+    use crate::config::{AccountMatcherConfig, PermissionsMode, ValueMatcherConfig};
+    use anyhow::anyhow;
+
+    use crate::feature::perms::set::{open_xml_accounts_with_tag, set_permissions_for_accounts, set_permissions_for_xml_accounts, SetOptions};
+    use crate::syspass::{Account, AccountPermissions};
+    use crate::syspass::backend::SyspassBackend;
+    use crate::tests::backend::FakeSyspassBackend;
+    use crate::tests::config::get_test_config;
+    use crate::tests::init_logging;
+    use crate::tests::xml::{get_test_xml_config, get_xml_accounts};
+
+    #[tokio::test]
+    async fn permissions_should_be_set_for_all_xml_accounts() {
+        init_logging();
+
+        let config = get_test_config();
+        let mut backend = get_logged_backend();
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+
+        set_permissions_for_accounts(&mut [&mut backend], &config, &get_test_xml_config(),
+                                     &progress, &get_options(false, false)).await.unwrap();
+
+        assert_eq!(get_xml_accounts(), backend.written);
+        assert_eq!(get_xml_accounts(), progress.get_progress().processed);
+
+        let permissions = backend.permissions.get(&get_xml_accounts()[0]).unwrap();
+        let default_permissions = config.permissions.as_ref().unwrap();
+        assert_eq!(default_permissions.user.edit, permissions.user_edit);
+        assert_eq!(default_permissions.owner, permissions.owner);
+    }
+
+    #[tokio::test]
+    async fn permissions_should_be_set_by_matching_rule() {
+        let mut config = get_test_config();
+        config.permission_rules[0].matcher = AccountMatcherConfig {
+            category: Some(ValueMatcherConfig::Exact("APP".to_string())),
+            ..AccountMatcherConfig::default()
+        };
+
+        let mut backend = get_logged_backend();
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+
+        set_permissions_for_accounts(&mut [&mut backend], &config, &get_test_xml_config(),
+                                     &progress, &get_options(false, false)).await.unwrap();
+
+        let accounts = get_xml_accounts();
 
-    #[test]
-    fn return_true_if_all_fields_match() {
-        let account = get_sample_account();
+        let rule_permissions = &config.permission_rules[0].permissions;
+        let permissions = backend.permissions.get(&accounts[1]).unwrap();
+        assert_eq!(rule_permissions.owner, permissions.owner);
+        assert_eq!(rule_permissions.group.edit, permissions.group_edit);
 
-        let xml_account_login = account.login.to_string();
-        let xml_account_name = account.name.to_string();
-        let xml_account_category = account.category.to_string();
-        let xml_account_client = account.client.to_string();
+        let default_permissions = config.permissions.as_ref().unwrap();
+        let permissions = backend.permissions.get(&accounts[0]).unwrap();
+        assert_eq!(default_permissions.owner, permissions.owner);
+    }
+
+    #[tokio::test]
+    async fn existing_permissions_should_be_replaced_or_removed_by_mode() {
+        let mut config = get_test_config();
+        let default_permissions = config.permissions.as_mut().unwrap();
+        default_permissions.user.mode = PermissionsMode::Remove;
+        default_permissions.user.view = vec!["j.doe".to_string()];
+        default_permissions.group.mode = PermissionsMode::Replace;
+
+        let mut backend = get_logged_backend();
+
+        let account = get_xml_accounts()[0].clone();
+        backend.permissions.insert(account.clone(), AccountPermissions {
+            user_view: vec!["j.doe".to_string(), "m.smith".to_string()],
+            group_view: vec!["Old Team".to_string()],
+            ..AccountPermissions::default()
+        });
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+
+        set_permissions_for_accounts(&mut [&mut backend], &config, &get_test_xml_config(),
+                                     &progress, &get_options(false, false)).await.unwrap();
+
+        let permissions = backend.permissions.get(&account).unwrap();
+        assert_eq!(vec!["m.smith".to_string()], permissions.user_view);
+        assert_eq!(vec!["Admins".to_string()], permissions.group_view);
+    }
+
+    #[tokio::test]
+    async fn nothing_should_be_changed_in_dry_run_mode() {
+        let config = get_test_config();
+        let mut backend = get_logged_backend();
+
+        let accounts = get_xml_accounts();
+
+        let current_permissions = AccountPermissions::default()
+            .with_config(config.permissions.as_ref().unwrap());
+        backend.permissions.insert(accounts[1].clone(), current_permissions.clone());
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+
+        let planned_changes = set_permissions_for_accounts(
+            &mut [&mut backend], &config, &get_test_xml_config(), &progress, &get_options(true, false)).await.unwrap();
+
+        assert!(backend.written.is_empty());
+        assert_eq!(Some(&current_permissions), backend.permissions.get(&accounts[1]));
+        assert!(progress.get_progress().processed.is_empty());
+
+        let planned_accounts: Vec<Account> = planned_changes.iter()
+            .map(|item| item.account.clone()).collect();
+        assert_eq!(accounts, planned_accounts);
+
+        assert!(!planned_changes[0].changes.is_empty());
+        assert!(planned_changes[1].changes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn return_mismatches_after_verification() {
+        let config = get_test_config();
+
+        let mut backend = get_logged_backend();
+        backend.ignored_writes = vec!["p.sidorov".to_string()];
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+
+        let mismatches = set_permissions_for_accounts(
+            &mut [&mut backend], &config, &get_test_xml_config(), &progress, &get_options(false, true)).await.unwrap();
+
+        let accounts = get_xml_accounts();
+
+        assert_eq!(1, mismatches.len());
+        assert_eq!(accounts[2], mismatches[0].account);
+        assert_eq!(config.permissions.as_ref().unwrap().user.edit, mismatches[0].changes.user_edit.added);
+
+        assert_eq!(accounts[..2].to_vec(), progress.get_progress().processed);
+    }
+
+    #[tokio::test]
+    async fn process_should_be_aborted_for_unknown_names_in_config() {
+        let mut config = get_test_config();
+        config.ignore_errors = true;
+        config.permissions.as_mut().unwrap().user.edit.push("Mr.Edtior".to_string());
+
+        let mut backend = get_logged_backend();
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+
+        let error = set_permissions_for_accounts(&mut [&mut backend], &config, &get_test_xml_config(),
+                                                 &progress, &get_options(false, false)).await.unwrap_err();
+
+        assert!(error.to_string().contains("users 'Mr.Edtior'"));
+        assert!(backend.written.is_empty());
+    }
+
+    #[tokio::test]
+    async fn unknown_names_should_be_checked_if_first_account_has_error() {
+        let mut config = get_test_config();
+        config.ignore_errors = true;
+        config.permissions.as_mut().unwrap().user.edit.push("Mr.Edtior".to_string());
+
+        let mut xml_config = get_test_xml_config();
+        xml_config.accounts[0].client_id = 999;
+
+        let mut backend = get_logged_backend();
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+
+        let error = set_permissions_for_accounts(&mut [&mut backend], &config, &xml_config,
+                                                 &progress, &get_options(false, false)).await.unwrap_err();
+
+        assert!(error.to_string().contains("users 'Mr.Edtior'"));
+        assert!(backend.written.is_empty());
+    }
+
+    #[tokio::test]
+    async fn process_should_be_aborted_if_no_account_to_check_config() {
+        let mut config = get_test_config();
+        config.ignore_errors = true;
+
+        let xml_config = get_test_xml_config();
+        let xml_index = xml_config.get_index();
+
+        let xml_accounts = vec![Err(anyhow!("account configuration error"))];
+
+        let mut backend = FakeSyspassBackend::new(vec![]);
+        backend.logged_in = true;
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+        let options = get_options(false, false);
+
+        let error = set_permissions_for_xml_accounts(&mut [&mut backend], &config, &xml_index,
+                                                     xml_accounts.into_iter(), 1, &progress,
+                                                     &options).await.unwrap_err();
+
+        assert!(error.to_string().contains("no syspass accounts found"));
+        assert!(backend.written.is_empty());
+    }
+
+    #[tokio::test]
+    async fn accounts_without_matching_rule_should_be_skipped() {
+        let mut config = get_test_config();
+        config.permissions = None;
+        config.permission_rules[0].matcher = AccountMatcherConfig {
+            client: Some(ValueMatcherConfig::Prefix("Kalinka".to_string())),
+            ..AccountMatcherConfig::default()
+        };
+
+        let mut backend = get_logged_backend();
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+
+        set_permissions_for_accounts(&mut [&mut backend], &config, &get_test_xml_config(),
+                                     &progress, &get_options(false, false)).await.unwrap();
+
+        assert_eq!(vec![get_xml_accounts()[1].clone()], backend.written);
+    }
+
+    #[tokio::test]
+    async fn processed_accounts_should_be_skipped() {
+        let config = get_test_config();
+        let mut backend = get_logged_backend();
+
+        let accounts = get_xml_accounts();
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress {
+            processed: vec![accounts[0].clone()],
+            results: vec![],
+        }, &cache_file);
+
+        set_permissions_for_accounts(&mut [&mut backend], &config, &get_test_xml_config(),
+                                     &progress, &get_options(false, false)).await.unwrap();
 
-        assert!(xml_account_matches_latest_processed(
-            &xml_account_login, &xml_account_name, &xml_account_category,
-            &xml_account_client, &account
-        ));
+        assert_eq!(accounts[1..].to_vec(), backend.written);
     }
 
-    #[test]
-    fn return_false_if_one_field_does_not_match_at_least() {
-        let mut account = get_sample_account();
+    #[tokio::test]
+    async fn process_should_be_interrupted_on_error() {
+        let mut config = get_test_config();
+        config.ignore_errors = false;
 
-        let xml_account_login = account.login.to_string();
-        let xml_account_name = account.name.to_string();
-        let xml_account_category = account.category.to_string();
-        let xml_account_client = account.client.to_string();
+        let mut backend = get_logged_backend();
+        backend.failing_logins = vec!["n.abramova".to_string()];
 
-        account.login = get_random_string();
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
 
-        assert!(!xml_account_matches_latest_processed(
-            &xml_account_login, &xml_account_name, &xml_account_category,
-            &xml_account_client, &account
-        ));
+        assert!(set_permissions_for_accounts(&mut [&mut backend], &config, &get_test_xml_config(),
+                                             &progress, &get_options(false, false)).await.is_err());
 
-        account.name = get_random_string();
+        assert_eq!(get_xml_accounts()[..1].to_vec(), backend.written);
+    }
+
+    #[tokio::test]
+    async fn errors_should_be_skipped_if_ignore_errors_enabled() {
+        let mut config = get_test_config();
+        config.ignore_errors = true;
+
+        let mut backend = get_logged_backend();
+        backend.failing_logins = vec!["n.abramova".to_string()];
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+
+        set_permissions_for_accounts(&mut [&mut backend], &config, &get_test_xml_config(),
+                                     &progress, &get_options(false, false)).await.unwrap();
+
+        let accounts = get_xml_accounts();
+        assert_eq!(vec![accounts[0].clone(), accounts[2].clone()], backend.written);
+        assert!(!progress.is_processed(&accounts[1]));
+    }
+
+    #[tokio::test]
+    async fn process_should_be_continued_after_session_restart() {
+        let config = get_test_config();
+
+        let mut backend = get_logged_backend();
+        backend.session_crashes = 2;
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+
+        set_permissions_for_accounts(&mut [&mut backend], &config, &get_test_xml_config(),
+                                     &progress, &get_options(false, false)).await.unwrap();
+
+        assert_eq!(2, backend.restarts);
+        assert_eq!(get_xml_accounts(), backend.written);
+    }
+
+    #[tokio::test]
+    async fn session_should_be_recycled_with_progress_kept() {
+        let mut config = get_test_config();
+        config.webdriver.recycle_after = Some(2);
 
-        assert!(!xml_account_matches_latest_processed(
-            &xml_account_login, &xml_account_name, &xml_account_category,
-            &xml_account_client, &account
-        ));
+        let mut backend = get_logged_backend();
 
-        account.category = get_random_string();
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+
+        set_permissions_for_accounts(&mut [&mut backend], &config, &get_test_xml_config(),
+                                     &progress, &get_options(false, false)).await.unwrap();
+
+        assert_eq!(1, backend.restarts);
+        assert_eq!(get_xml_accounts(), backend.written);
+    }
+
+    #[tokio::test]
+    async fn accounts_should_be_distributed_between_workers() {
+        let config = get_test_config();
+
+        let mut backend1 = get_logged_backend();
+        let mut backend2 = get_logged_backend();
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+
+        let mut backends: Vec<&mut dyn SyspassBackend> = vec![&mut backend1, &mut backend2];
+
+        set_permissions_for_accounts(&mut backends, &config, &get_test_xml_config(),
+                                     &progress, &get_options(false, false)).await.unwrap();
+
+        assert!(!backend1.written.is_empty());
+        assert!(!backend2.written.is_empty());
+
+        let mut written = backend1.written.clone();
+        written.append(&mut backend2.written.clone());
+        written.sort_by(|a, b| a.login.cmp(&b.login));
+
+        let mut expected_accounts = get_xml_accounts();
+        expected_accounts.sort_by(|a, b| a.login.cmp(&b.login));
+
+        assert_eq!(expected_accounts, written);
+        assert_eq!(3, progress.get_progress().processed.len());
+    }
 
-        assert!(!xml_account_matches_latest_processed(
-            &xml_account_login, &xml_account_name, &xml_account_category,
-            &xml_account_client, &account
-        ));
+    #[tokio::test]
+    async fn return_error_for_unknown_client() {
+        let mut config = get_test_config();
+        config.ignore_errors = false;
 
-        account.client = get_random_string();
+        let mut xml_config = get_test_xml_config();
+        xml_config.accounts[0].client_id = 999;
+
+        let mut backend = get_logged_backend();
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+
+        assert!(set_permissions_for_accounts(&mut [&mut backend], &config, &xml_config,
+                                             &progress, &get_options(false, false)).await.is_err());
+        assert!(backend.written.is_empty());
+    }
+
+    #[tokio::test]
+    async fn accounts_before_stream_error_should_be_processed() {
+        let mut config = get_test_config();
+        config.ignore_errors = false;
+
+        let xml_config = get_test_xml_config();
+        let xml_index = xml_config.get_index();
+
+        let xml_accounts = vec![
+            Ok(xml_config.accounts[0].clone()),
+            Err(anyhow!("xml error at line 30, column 9: syntax error")),
+            Ok(xml_config.accounts[2].clone()),
+        ];
+
+        let mut backend = get_logged_backend();
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+        let options = get_options(false, false);
+
+        assert!(set_permissions_for_xml_accounts(&mut [&mut backend], &config, &xml_index, xml_accounts.into_iter(),
+                                                 3, &progress, &options).await.is_err());
+
+        assert_eq!(get_xml_accounts()[..1].to_vec(), backend.written);
+    }
+
+    #[tokio::test]
+    async fn stream_error_should_stop_process_if_ignore_errors_enabled() {
+        let mut config = get_test_config();
+        config.ignore_errors = true;
+
+        let xml_config = get_test_xml_config();
+        let xml_index = xml_config.get_index();
+
+        let xml_accounts = vec![
+            Ok(xml_config.accounts[0].clone()),
+            Err(anyhow!("xml error at line 30, column 9: syntax error")),
+            Ok(xml_config.accounts[2].clone()),
+        ];
+
+        let mut backend = get_logged_backend();
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+        let options = get_options(false, false);
+
+        let error = set_permissions_for_xml_accounts(&mut [&mut backend], &config, &xml_index,
+                                                     xml_accounts.into_iter(), 3, &progress,
+                                                     &options).await.unwrap_err();
+
+        assert!(error.to_string().contains("syntax error"));
+        assert_eq!(get_xml_accounts()[..1].to_vec(), backend.written);
+    }
+
+    #[tokio::test]
+    async fn malformed_account_in_strict_xml_should_prevent_any_write() {
+        let mut config = get_test_config();
+        config.ignore_errors = true;
+        config.xml_strict = true;
+
+        let malformed_account = r#"<Account id="3">
+            <name>Petr Sidorov</name>
+            <clientId>9</clientId>
+            <categoryId>1</categoryId>
+            <login>p.sidorov</login>
+        </Account>
+    </Accounts>"#;
+
+        let xml = fs::read_to_string(Path::new("test-data").join("import.xml")).unwrap()
+            .replace("</Accounts>", malformed_account);
+
+        let mut xml_file = NamedTempFile::new().unwrap();
+        xml_file.write_all(xml.as_bytes()).unwrap();
+
+        let mut backend = get_logged_backend();
+
+        let cache_file = NamedTempFile::new().unwrap();
+        let progress = get_progress(CommandProgress::default(), &cache_file);
+        let options = get_options(false, false);
+
+        let result = match open_xml_accounts_with_tag(&config, xml_file.path(), None) {
+            Ok((xml_index, xml_accounts, accounts_count)) =>
+                set_permissions_for_xml_accounts(&mut [&mut backend], &config, &xml_index, xml_accounts,
+                                                 accounts_count, &progress, &options).await,
+            Err(e) => Err(e)
+        };
+
+        assert!(result.unwrap_err().to_string().contains("account 3 refers to unknown client id 9"));
+        assert!(backend.written.is_empty());
+    }
+
+    fn get_options(dry_run: bool, verify: bool) -> SetOptions {
+        SetOptions { dry_run, verify, skip_unchanged: false }
+    }
+
+    fn get_progress(progress: CommandProgress, cache_file: &NamedTempFile) -> SharedProgress {
+        SharedProgress::new(progress, cache_file.path(), 10)
+    }
 
-        assert!(!xml_account_matches_latest_processed(
-            &xml_account_login, &xml_account_name, &xml_account_category,
-            &xml_account_client, &account
-        ));
+    fn get_logged_backend() -> FakeSyspassBackend {
+        let mut backend = FakeSyspassBackend::new(get_xml_accounts());
+        backend.logged_in = true;
+        backend
     }
 }
@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use log::info;
+
+use crate::cache::{ACCOUNTS_SET_CACHE_FILENAME, CommandProgress, SharedProgress};
+use crate::config::AppConfig;
+use crate::feature::perms::plan::AccountChanges;
+use crate::feature::perms::set::{set_permissions_for_accounts, set_permissions_for_accounts_in_syspass, SetOptions};
+use crate::syspass::backend::SyspassBackend;
+use crate::types::OperationResult;
+use crate::xml::XmlConfig;
+
+const VERIFY_OPTIONS: SetOptions = SetOptions { dry_run: true, verify: false, skip_unchanged: false };
+
+/// Check that permissions from config are applied for accounts from given xml-file.
+///
+/// Returns accounts with mismatches.
+pub async fn verify_permissions_for_accounts_in_syspass(config: &AppConfig, xml_file: &Path,
+                                                        workers: u16) -> OperationResult<Vec<AccountChanges>> {
+    info!("verify permissions for accounts from '{}'", xml_file.display());
+
+    let changes = set_permissions_for_accounts_in_syspass(
+        config, xml_file, None, CommandProgress::default(), workers, &VERIFY_OPTIONS).await?;
+
+    Ok(get_mismatches(changes))
+}
+
+pub async fn verify_permissions_for_accounts(backends: &mut [&mut dyn SyspassBackend], config: &AppConfig,
+                                             xml_config: &XmlConfig) -> OperationResult<Vec<AccountChanges>> {
+    // Progress isn't saved in dry-run mode
+    let progress = SharedProgress::new(CommandProgress::default(), Path::new(ACCOUNTS_SET_CACHE_FILENAME),
+                                       config.progress_cache.set_accounts);
+
+    let changes = set_permissions_for_accounts(backends, config, xml_config, &progress, &VERIFY_OPTIONS).await?;
+
+    Ok(get_mismatches(changes))
+}
+
+fn get_mismatches(changes: Vec<AccountChanges>) -> Vec<AccountChanges> {
+    changes.into_iter().filter(|item| !item.changes.is_empty()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::feature::perms::verify::verify_permissions_for_accounts;
+    use crate::syspass::AccountPermissions;
+    use crate::tests::backend::FakeSyspassBackend;
+    use crate::tests::config::get_test_config;
+    use crate::tests::xml::{get_test_xml_config, get_xml_accounts};
+
+    #[tokio::test]
+    async fn return_accounts_with_mismatches_only() {
+        let config = get_test_config();
+
+        let accounts = get_xml_accounts();
+
+        let mut backend = FakeSyspassBackend::new(accounts.clone());
+        backend.logged_in = true;
+
+        let applied_permissions = AccountPermissions::default()
+            .with_config(config.permissions.as_ref().unwrap());
+
+        backend.permissions.insert(accounts[0].clone(), applied_permissions.clone());
+        backend.permissions.insert(accounts[2].clone(), applied_permissions);
+
+        let mismatches = verify_permissions_for_accounts(
+            &mut [&mut backend], &config, &get_test_xml_config()).await.unwrap();
+
+        assert_eq!(1, mismatches.len());
+        assert_eq!(accounts[1], mismatches[0].account);
+        assert!(backend.written.is_empty());
+    }
+}
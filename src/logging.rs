@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod logging {
     use log4rs::append::rolling_file::policy::compound::CompoundPolicy;
     use log4rs::append::rolling_file::policy::compound::roll::fixed_window::FixedWindowRoller;
@@ -12,14 +13,14 @@ pub mod logging {
     pub const LOG_FILE_PATH: &str = "spt.log";
 
     fn get_logging_level_from_string(level: &str) -> LevelFilter {
-        return match level {
+        match level {
             "debug" => LevelFilter::Debug,
             "error" => LevelFilter::Error,
             "warn" => LevelFilter::Warn,
             "trace" => LevelFilter::Trace,
             "off" => LevelFilter::Off,
             _ => LevelFilter::Info
-        };
+        }
     }
 
     pub fn get_logging_config(logging_level: &str) -> Config {
@@ -32,7 +33,7 @@ pub mod logging {
             Root::builder()
                 .appender(ROLLING_APPENDER_NAME)
                 .build(level)
-            ).expect(&format!("unable to create log file '{}'", LOG_FILE_PATH))
+            ).unwrap_or_else(|_| panic!("unable to create log file '{}'", LOG_FILE_PATH))
     }
 
     fn get_rolling_appender() -> Appender {
@@ -1,3 +1,5 @@
+use std::fs::File;
+use std::io;
 use std::path::Path;
 use std::process::exit;
 
@@ -5,14 +7,23 @@ use clap::{Arg, ArgAction, ArgMatches, Command};
 use log::{error, info};
 use serde::Deserialize;
 
-use crate::cache::{ACCOUNTS_GET_CACHE_FILENAME, ACCOUNTS_SET_CACHE_FILENAME, load_cache_data_from_file};
+use crate::cache::{ACCOUNTS_APPLY_CACHE_FILENAME, ACCOUNTS_AUDIT_CACHE_FILENAME, ACCOUNTS_EXPORT_CACHE_FILENAME,
+                   ACCOUNTS_GET_CACHE_FILENAME, ACCOUNTS_RECONCILE_CACHE_FILENAME, ACCOUNTS_SET_CACHE_FILENAME,
+                   CommandProgress, load_cache_data_from_file};
 use crate::config::load_config_from_file;
+use crate::feature::perms::apply::apply_snapshot_in_syspass;
+use crate::feature::perms::audit::{audit_permissions_in_syspass, audit_snapshot, load_audit_policy_from_file};
+use crate::feature::perms::diff::diff_snapshots;
+use crate::feature::perms::export::{AccountSnapshot, export_permissions_from_syspass, read_snapshot_from_file, SnapshotFormat, write_snapshot};
 use crate::feature::perms::get::{AccountFilterOptions, get_accounts_with_empty_permissions};
-use crate::feature::perms::set::set_permissions_for_accounts_in_syspass;
+use crate::feature::perms::plan::AccountChanges;
+use crate::feature::perms::reconcile::reconcile_permissions_in_syspass;
+use crate::feature::perms::set::{set_permissions_for_accounts_in_syspass, SetOptions};
+use crate::feature::perms::verify::verify_permissions_for_accounts_in_syspass;
 use crate::logging::logging::get_logging_config;
-use crate::syspass::Account;
 
 pub mod config;
+pub mod crypt;
 pub mod types;
 pub mod logging;
 pub mod xml;
@@ -29,11 +40,34 @@ pub const CONFIG_FILE: &str = "spt.yml";
 
 pub const SET_CMD: &str = "set";
 pub const GET_EMPTY_CMD: &str = "get-empty";
+pub const EXPORT_CMD: &str = "export";
+pub const APPLY_SNAPSHOT_CMD: &str = "apply-snapshot";
+pub const DIFF_CMD: &str = "diff";
+pub const AUDIT_CMD: &str = "audit";
+pub const RECONCILE_CMD: &str = "reconcile";
+pub const VERIFY_CMD: &str = "verify";
 
 pub const XML_FILE_OPTION: &str = "xml-file";
+pub const XML_TAG_OPTION: &str = "xml-tag";
+pub const SNAPSHOT_FILE_OPTION: &str = "snapshot-file";
+pub const POLICY_FILE_OPTION: &str = "policy-file";
+pub const OLD_SNAPSHOT_OPTION: &str = "old";
+pub const NEW_SNAPSHOT_OPTION: &str = "new";
 
 pub const RESUME_OPTION: &str = "resume";
 
+pub const WORKERS_OPTION: &str = "workers";
+
+pub const DRY_RUN_OPTION: &str = "dry-run";
+pub const VERIFY_OPTION: &str = "verify";
+
+pub const FORMAT_OPTION: &str = "format";
+pub const OUTPUT_OPTION: &str = "output";
+
+pub const JSON_FORMAT: &str = "json";
+pub const CSV_FORMAT: &str = "csv";
+pub const TEXT_FORMAT: &str = "text";
+
 pub const CATEGORY_FILTER_OPTION: &str = "category";
 pub const CLIENT_FILTER_OPTION: &str = "client";
 pub const LOGIN_STARTS_WITH_FILTER_OPTION: &str = "login-starts-with";
@@ -61,6 +95,48 @@ async fn main() {
                         .action(ArgAction::Set)
                         .required(false),
                 )
+                .arg(
+                    Arg::new(XML_TAG_OPTION)
+                        .long(XML_TAG_OPTION)
+                        .help("process xml accounts with given tag only, i.e. 'SSO'")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new(RESUME_OPTION)
+                        .long(RESUME_OPTION)
+                        .help("resume process from last error")
+                        .action(ArgAction::SetTrue)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(DRY_RUN_OPTION)
+                        .long(DRY_RUN_OPTION)
+                        .help("print planned changes for accounts, don't change anything")
+                        .action(ArgAction::SetTrue)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(VERIFY_OPTION)
+                        .long(VERIFY_OPTION)
+                        .help("re-read permissions after save and report mismatches")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with(DRY_RUN_OPTION)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(WORKERS_OPTION)
+                        .long(WORKERS_OPTION)
+                        .help("webdriver sessions count to process accounts in parallel")
+                        .default_value("1")
+                        .value_parser(clap::value_parser!(u16).range(1..))
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+        )
+        .subcommand(
+            Command::new(RECONCILE_CMD)
+                .about("Fix permissions for all sysPass accounts which differ from config")
                 .arg(
                     Arg::new(RESUME_OPTION)
                         .long(RESUME_OPTION)
@@ -68,6 +144,83 @@ async fn main() {
                         .action(ArgAction::SetTrue)
                         .required(false)
                 )
+                .arg(
+                    Arg::new(DRY_RUN_OPTION)
+                        .long(DRY_RUN_OPTION)
+                        .help("print planned changes for accounts, don't change anything")
+                        .action(ArgAction::SetTrue)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(VERIFY_OPTION)
+                        .long(VERIFY_OPTION)
+                        .help("re-read permissions after save and report mismatches")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with(DRY_RUN_OPTION)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(WORKERS_OPTION)
+                        .long(WORKERS_OPTION)
+                        .help("webdriver sessions count to process accounts in parallel")
+                        .default_value("1")
+                        .value_parser(clap::value_parser!(u16).range(1..))
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(CATEGORY_FILTER_OPTION)
+                        .long(CATEGORY_FILTER_OPTION)
+                        .help("filter by category name")
+                        .default_value("")
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(CLIENT_FILTER_OPTION)
+                        .long(CLIENT_FILTER_OPTION)
+                        .help("filter by client name")
+                        .default_value("")
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(LOGIN_STARTS_WITH_FILTER_OPTION)
+                        .long(LOGIN_STARTS_WITH_FILTER_OPTION)
+                        .help("filter by login starts with")
+                        .default_value("")
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(NAME_STARTS_WITH_FILTER_OPTION)
+                        .long(NAME_STARTS_WITH_FILTER_OPTION)
+                        .help("filter by name starts with")
+                        .default_value("")
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+        )
+        .subcommand(
+            Command::new(VERIFY_CMD)
+                .about("Check that permissions from config are applied for accounts")
+                .arg(
+                    Arg::new(XML_FILE_OPTION)
+                        .long(XML_FILE_OPTION)
+                        .default_value("import.xml")
+                        .help("xml file with accounts")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new(WORKERS_OPTION)
+                        .long(WORKERS_OPTION)
+                        .help("webdriver sessions count to process accounts in parallel")
+                        .default_value("1")
+                        .value_parser(clap::value_parser!(u16).range(1..))
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
         )
         .subcommand(
             Command::new(GET_EMPTY_CMD)
@@ -79,6 +232,227 @@ async fn main() {
                         .action(ArgAction::SetTrue)
                         .required(false)
                 )
+                .arg(
+                    Arg::new(WORKERS_OPTION)
+                        .long(WORKERS_OPTION)
+                        .help("webdriver sessions count to process accounts in parallel")
+                        .default_value("1")
+                        .value_parser(clap::value_parser!(u16).range(1..))
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(CATEGORY_FILTER_OPTION)
+                        .long(CATEGORY_FILTER_OPTION)
+                        .help("filter by category name")
+                        .default_value("")
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(CLIENT_FILTER_OPTION)
+                        .long(CLIENT_FILTER_OPTION)
+                        .help("filter by client name")
+                        .default_value("")
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(LOGIN_STARTS_WITH_FILTER_OPTION)
+                        .long(LOGIN_STARTS_WITH_FILTER_OPTION)
+                        .help("filter by login starts with")
+                        .default_value("")
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(NAME_STARTS_WITH_FILTER_OPTION)
+                        .long(NAME_STARTS_WITH_FILTER_OPTION)
+                        .help("filter by name starts with")
+                        .default_value("")
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+        )
+        .subcommand(
+            Command::new(APPLY_SNAPSHOT_CMD)
+                .about("Restore permissions from snapshot file (json, yaml)")
+                .arg(
+                    Arg::new(SNAPSHOT_FILE_OPTION)
+                        .long(SNAPSHOT_FILE_OPTION)
+                        .help("snapshot file, see export command")
+                        .action(ArgAction::Set)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new(RESUME_OPTION)
+                        .long(RESUME_OPTION)
+                        .help("resume process from last error")
+                        .action(ArgAction::SetTrue)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(WORKERS_OPTION)
+                        .long(WORKERS_OPTION)
+                        .help("webdriver sessions count to process accounts in parallel")
+                        .default_value("1")
+                        .value_parser(clap::value_parser!(u16).range(1..))
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(DRY_RUN_OPTION)
+                        .long(DRY_RUN_OPTION)
+                        .help("print planned changes for accounts, don't change anything")
+                        .action(ArgAction::SetTrue)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(VERIFY_OPTION)
+                        .long(VERIFY_OPTION)
+                        .help("re-read permissions after save and report mismatches")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with(DRY_RUN_OPTION)
+                        .required(false)
+                )
+        )
+        .subcommand(
+            Command::new(DIFF_CMD)
+                .about("Compare two permission snapshots (offline)")
+                .arg(
+                    Arg::new(OLD_SNAPSHOT_OPTION)
+                        .long(OLD_SNAPSHOT_OPTION)
+                        .help("old snapshot file (json, yaml)")
+                        .action(ArgAction::Set)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new(NEW_SNAPSHOT_OPTION)
+                        .long(NEW_SNAPSHOT_OPTION)
+                        .help("new snapshot file (json, yaml)")
+                        .action(ArgAction::Set)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new(FORMAT_OPTION)
+                        .long(FORMAT_OPTION)
+                        .help("output format")
+                        .default_value(TEXT_FORMAT)
+                        .value_parser([TEXT_FORMAT, JSON_FORMAT])
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+        )
+        .subcommand(
+            Command::new(AUDIT_CMD)
+                .about("Check accounts permissions against policy")
+                .arg(
+                    Arg::new(POLICY_FILE_OPTION)
+                        .long(POLICY_FILE_OPTION)
+                        .help("audit policy file (yaml)")
+                        .action(ArgAction::Set)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new(SNAPSHOT_FILE_OPTION)
+                        .long(SNAPSHOT_FILE_OPTION)
+                        .help("audit snapshot file (see export command) instead of sysPass instance")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new(FORMAT_OPTION)
+                        .long(FORMAT_OPTION)
+                        .help("output format")
+                        .default_value(TEXT_FORMAT)
+                        .value_parser([TEXT_FORMAT, JSON_FORMAT])
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(RESUME_OPTION)
+                        .long(RESUME_OPTION)
+                        .help("resume process from last error")
+                        .action(ArgAction::SetTrue)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(WORKERS_OPTION)
+                        .long(WORKERS_OPTION)
+                        .help("webdriver sessions count to process accounts in parallel")
+                        .default_value("1")
+                        .value_parser(clap::value_parser!(u16).range(1..))
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(CATEGORY_FILTER_OPTION)
+                        .long(CATEGORY_FILTER_OPTION)
+                        .help("filter by category name")
+                        .default_value("")
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(CLIENT_FILTER_OPTION)
+                        .long(CLIENT_FILTER_OPTION)
+                        .help("filter by client name")
+                        .default_value("")
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(LOGIN_STARTS_WITH_FILTER_OPTION)
+                        .long(LOGIN_STARTS_WITH_FILTER_OPTION)
+                        .help("filter by login starts with")
+                        .default_value("")
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(NAME_STARTS_WITH_FILTER_OPTION)
+                        .long(NAME_STARTS_WITH_FILTER_OPTION)
+                        .help("filter by name starts with")
+                        .default_value("")
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+        )
+        .subcommand(
+            Command::new(EXPORT_CMD)
+                .about("Export permissions snapshot for accounts")
+                .arg(
+                    Arg::new(FORMAT_OPTION)
+                        .long(FORMAT_OPTION)
+                        .help("output format")
+                        .default_value(JSON_FORMAT)
+                        .value_parser([JSON_FORMAT, CSV_FORMAT])
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(OUTPUT_OPTION)
+                        .long(OUTPUT_OPTION)
+                        .help("output file, stdout by default")
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(RESUME_OPTION)
+                        .long(RESUME_OPTION)
+                        .help("resume process from last error")
+                        .action(ArgAction::SetTrue)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new(WORKERS_OPTION)
+                        .long(WORKERS_OPTION)
+                        .help("webdriver sessions count to process accounts in parallel")
+                        .default_value("1")
+                        .value_parser(clap::value_parser!(u16).range(1..))
+                        .action(ArgAction::Set)
+                        .required(false)
+                )
                 .arg(
                     Arg::new(CATEGORY_FILTER_OPTION)
                         .long(CATEGORY_FILTER_OPTION)
@@ -127,53 +501,113 @@ async fn main() {
         Some((SET_CMD, set_matches)) => {
             let xml_file_option = set_matches.get_one::<String>(XML_FILE_OPTION);
 
-            match xml_file_option {
-                Some(path) => {
-                    let xml_file = Path::new(path);
+            if let Some(path) = xml_file_option {
+                let xml_file = Path::new(path);
+
+                if xml_file.is_file() && xml_file.exists() {
+                    match load_config_from_file(config_file) {
+                        Ok(config) => {
+                            let progress = get_command_progress_data_from_cache(set_matches, ACCOUNTS_SET_CACHE_FILENAME, CommandProgress::default());
+
+                            let workers = get_workers_count(set_matches);
+
+                            let options = get_set_options(set_matches);
 
-                    if xml_file.is_file() && xml_file.exists() {
-                        match load_config_from_file(config_file) {
-                            Ok(config) => {
+                            let xml_tag = set_matches.get_one::<String>(XML_TAG_OPTION).map(|tag| tag.as_str());
 
-                                let fallback_account = Account {
-                                    name: "".to_string(),
-                                    login: "".to_string(),
-                                    category: "".to_string(),
-                                    client: "".to_string(),
-                                };
+                            match set_permissions_for_accounts_in_syspass(&config, xml_file, xml_tag, progress, workers, &options).await {
+                                Ok(changes) => print_set_results(&options, &changes),
+                                Err(e) => {
+                                    eprintln!("error: {}", e.root_cause());
+                                    exit(EXIT_CODE_ERROR)
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            eprintln!("couldn't load config: {}", e);
+                            exit(EXIT_CODE_ERROR)
+                        }
+                    }
+                } else {
+                    eprintln!("xml file wasn't found '{}'", xml_file.display());
+                    exit(EXIT_CODE_ERROR)
+                }
+            }
+        }
+        Some((RECONCILE_CMD, reconcile_matches)) => {
+            match load_config_from_file(config_file) {
+                Ok(config) => {
+                    let progress = get_command_progress_data_from_cache(reconcile_matches, ACCOUNTS_RECONCILE_CACHE_FILENAME, CommandProgress::default());
+
+                    let account_filter_options = get_account_filter_options(reconcile_matches);
+
+                    let workers = get_workers_count(reconcile_matches);
+
+                    let options = get_set_options(reconcile_matches);
+
+                    match reconcile_permissions_in_syspass(&config, progress, &account_filter_options,
+                                                           workers, &options).await {
+                        Ok(changes) => print_set_results(&options, &changes),
+                        Err(e) => {
+                            eprintln!("error: {}", e.root_cause());
+                            exit(EXIT_CODE_ERROR)
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("couldn't load config: {}", e);
+                    exit(EXIT_CODE_ERROR)
+                }
+            }
+        }
+        Some((VERIFY_CMD, verify_matches)) => {
+            let xml_file_option = verify_matches.get_one::<String>(XML_FILE_OPTION);
+
+            if let Some(path) = xml_file_option {
+                let xml_file = Path::new(path);
 
-                                let latest_progress_account: Account = get_command_progress_data_from_cache(set_matches, ACCOUNTS_SET_CACHE_FILENAME, fallback_account);
+                if xml_file.is_file() && xml_file.exists() {
+                    match load_config_from_file(config_file) {
+                        Ok(config) => {
+                            let workers = get_workers_count(verify_matches);
 
-                                match set_permissions_for_accounts_in_syspass(&config, xml_file, &latest_progress_account).await {
-                                    Ok(_) => println!("complete"),
-                                    Err(e) => {
-                                        eprintln!("error: {}", e.root_cause());
+                            match verify_permissions_for_accounts_in_syspass(&config, xml_file, workers).await {
+                                Ok(mismatches) => {
+                                    if !mismatches.is_empty() {
+                                        print_permission_mismatches(&mismatches);
                                         exit(EXIT_CODE_ERROR)
                                     }
+
+                                    println!("permissions match config")
+                                },
+                                Err(e) => {
+                                    eprintln!("error: {}", e.root_cause());
+                                    exit(EXIT_CODE_ERROR)
                                 }
                             }
-                            Err(e) => {
-                                eprintln!("couldn't load config: {}", e);
-                                exit(EXIT_CODE_ERROR)
-                            }
                         }
-                    } else {
-                        eprintln!("xml file wasn't found '{}'", xml_file.display());
-                        exit(EXIT_CODE_ERROR)
+                        Err(e) => {
+                            eprintln!("couldn't load config: {}", e);
+                            exit(EXIT_CODE_ERROR)
+                        }
                     }
+                } else {
+                    eprintln!("xml file wasn't found '{}'", xml_file.display());
+                    exit(EXIT_CODE_ERROR)
                 }
-                None => {}
             }
         }
         Some((GET_EMPTY_CMD, get_matches)) => {
             match load_config_from_file(config_file) {
                 Ok(config) => {
-                    let mut accounts_from_cache = get_command_progress_data_from_cache(get_matches, ACCOUNTS_GET_CACHE_FILENAME, vec![]);
+                    let progress = get_command_progress_data_from_cache(get_matches, ACCOUNTS_GET_CACHE_FILENAME, CommandProgress::default());
 
                     let account_filter_options = get_account_filter_options(get_matches);
 
-                    match get_accounts_with_empty_permissions(&config, &mut accounts_from_cache,
-                                                              &account_filter_options).await {
+                    let workers = get_workers_count(get_matches);
+
+                    match get_accounts_with_empty_permissions(&config, progress,
+                                                              &account_filter_options, workers).await {
                         Ok(accounts) => {
                             match serde_json::to_string(&accounts) {
                                 Ok(accounts_str) => println!("{}", accounts_str),
@@ -195,6 +629,159 @@ async fn main() {
                 }
             }
         }
+        Some((APPLY_SNAPSHOT_CMD, apply_matches)) => {
+            let snapshot_file_option = apply_matches.get_one::<String>(SNAPSHOT_FILE_OPTION);
+
+            if let Some(path) = snapshot_file_option {
+                let snapshot_file = Path::new(path);
+
+                if snapshot_file.is_file() {
+                    match load_config_from_file(config_file) {
+                        Ok(config) => {
+                            let progress = get_command_progress_data_from_cache(apply_matches, ACCOUNTS_APPLY_CACHE_FILENAME, CommandProgress::default());
+
+                            let workers = get_workers_count(apply_matches);
+
+                            let options = get_set_options(apply_matches);
+
+                            match apply_snapshot_in_syspass(&config, snapshot_file, progress, workers, &options).await {
+                                Ok(changes) => print_set_results(&options, &changes),
+                                Err(e) => {
+                                    eprintln!("error: {}", e.root_cause());
+                                    exit(EXIT_CODE_ERROR)
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            eprintln!("couldn't load config: {}", e);
+                            exit(EXIT_CODE_ERROR)
+                        }
+                    }
+                } else {
+                    eprintln!("snapshot file wasn't found '{}'", snapshot_file.display());
+                    exit(EXIT_CODE_ERROR)
+                }
+            }
+        }
+        Some((DIFF_CMD, diff_matches)) => {
+            let old_snapshot = get_snapshot_from_option(diff_matches, OLD_SNAPSHOT_OPTION);
+            let new_snapshot = get_snapshot_from_option(diff_matches, NEW_SNAPSHOT_OPTION);
+
+            let diff = diff_snapshots(&old_snapshot, &new_snapshot);
+
+            match diff_matches.get_one::<String>(FORMAT_OPTION).map(|format| format.as_str()) {
+                Some(JSON_FORMAT) => match serde_json::to_string_pretty(&diff) {
+                    Ok(diff_str) => println!("{}", diff_str),
+                    Err(e) => {
+                        eprintln!("error: {}", e);
+                        exit(EXIT_CODE_ERROR)
+                    }
+                },
+                _ => print!("{}", diff)
+            }
+        }
+        Some((AUDIT_CMD, audit_matches)) => {
+            let policy_file = audit_matches.get_one::<String>(POLICY_FILE_OPTION)
+                .map(|path| path.as_str()).unwrap_or_default();
+
+            let policy = match load_audit_policy_from_file(Path::new(policy_file)) {
+                Ok(policy) => policy,
+                Err(e) => {
+                    eprintln!("couldn't load audit policy: {}", e);
+                    exit(EXIT_CODE_ERROR)
+                }
+            };
+
+            let audit_result = if audit_matches.contains_id(SNAPSHOT_FILE_OPTION) {
+                let snapshot = get_snapshot_from_option(audit_matches, SNAPSHOT_FILE_OPTION);
+                Ok(audit_snapshot(&policy, &snapshot))
+
+            } else {
+                match load_config_from_file(config_file) {
+                    Ok(config) => {
+                        let progress = get_command_progress_data_from_cache(audit_matches, ACCOUNTS_AUDIT_CACHE_FILENAME, CommandProgress::default());
+
+                        let account_filter_options = get_account_filter_options(audit_matches);
+
+                        let workers = get_workers_count(audit_matches);
+
+                        audit_permissions_in_syspass(&config, &policy, progress,
+                                                     &account_filter_options, workers).await
+                    }
+                    Err(e) => {
+                        eprintln!("couldn't load config: {}", e);
+                        exit(EXIT_CODE_ERROR)
+                    }
+                }
+            };
+
+            match audit_result {
+                Ok(violations) => {
+                    match audit_matches.get_one::<String>(FORMAT_OPTION).map(|format| format.as_str()) {
+                        Some(JSON_FORMAT) => match serde_json::to_string_pretty(&violations) {
+                            Ok(violations_str) => println!("{}", violations_str),
+                            Err(e) => {
+                                eprintln!("error: {}", e);
+                                exit(EXIT_CODE_ERROR)
+                            }
+                        },
+                        _ => {
+                            for account_violations in &violations {
+                                print!("{}", account_violations);
+                            }
+
+                            println!("accounts with violations: {}", violations.len());
+                        }
+                    }
+
+                    if !violations.is_empty() {
+                        exit(EXIT_CODE_ERROR)
+                    }
+                }
+                Err(e) => {
+                    eprintln!("error: {}", e.root_cause());
+                    exit(EXIT_CODE_ERROR)
+                }
+            }
+        }
+        Some((EXPORT_CMD, export_matches)) => {
+            match load_config_from_file(config_file) {
+                Ok(config) => {
+                    let progress = get_command_progress_data_from_cache(export_matches, ACCOUNTS_EXPORT_CACHE_FILENAME, CommandProgress::default());
+
+                    let account_filter_options = get_account_filter_options(export_matches);
+
+                    let workers = get_workers_count(export_matches);
+
+                    match export_permissions_from_syspass(&config, progress,
+                                                          &account_filter_options, workers).await {
+                        Ok(snapshot) => {
+                            let format = get_snapshot_format(export_matches);
+
+                            let write_result = match export_matches.get_one::<String>(OUTPUT_OPTION) {
+                                Some(output_file) => File::create(output_file)
+                                    .map_err(|e| e.into())
+                                    .and_then(|file| write_snapshot(&snapshot, format, file)),
+                                None => write_snapshot(&snapshot, format, io::stdout())
+                            };
+
+                            if let Err(e) = write_result {
+                                eprintln!("couldn't write snapshot: {}", e);
+                                exit(EXIT_CODE_ERROR)
+                            }
+                        }
+                        Err(e) => {
+                            eprintln!("error: {}", e.root_cause());
+                            exit(EXIT_CODE_ERROR)
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("couldn't load config: {}", e);
+                    exit(EXIT_CODE_ERROR)
+                }
+            }
+        }
         _ => println!("Use -h for help")
     }
 }
@@ -211,7 +798,7 @@ fn get_command_progress_data_from_cache<T: for<'a> Deserialize<'a>>(matches: &Ar
         match load_cache_data_from_file(cache_file) {
             Ok(data) => data,
             Err(e) => {
-                info!("couldn't load data from cache file: {}, skip", e);
+                info!("couldn't load data from cache file (unsupported format?): {}, skip", e);
                 fallback_value
             }
         }
@@ -220,6 +807,61 @@ fn get_command_progress_data_from_cache<T: for<'a> Deserialize<'a>>(matches: &Ar
     }
 }
 
+fn get_set_options(matches: &ArgMatches) -> SetOptions {
+    SetOptions {
+        dry_run: matches.get_flag(DRY_RUN_OPTION),
+        verify: matches.get_flag(VERIFY_OPTION),
+        skip_unchanged: false,
+    }
+}
+
+/// Print planned changes (dry-run) or mismatches after verification
+fn print_set_results(options: &SetOptions, changes: &[AccountChanges]) {
+    if options.dry_run {
+        for account_changes in changes {
+            print!("{}", account_changes);
+        }
+
+    } else if !changes.is_empty() {
+        print_permission_mismatches(changes);
+        exit(EXIT_CODE_ERROR)
+    }
+
+    println!("complete")
+}
+
+fn print_permission_mismatches(mismatches: &[AccountChanges]) {
+    println!("permissions mismatch for {} account(s), expected changes:", mismatches.len());
+
+    for account_changes in mismatches {
+        print!("{}", account_changes);
+    }
+}
+
+/// Exit on error
+fn get_snapshot_from_option(matches: &ArgMatches, option: &str) -> Vec<AccountSnapshot> {
+    let path = matches.get_one::<String>(option).map(|path| path.as_str()).unwrap_or_default();
+
+    match read_snapshot_from_file(Path::new(path)) {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            eprintln!("couldn't read snapshot file '{}': {}", path, e);
+            exit(EXIT_CODE_ERROR)
+        }
+    }
+}
+
+fn get_snapshot_format(matches: &ArgMatches) -> SnapshotFormat {
+    match matches.get_one::<String>(FORMAT_OPTION).map(|format| format.as_str()) {
+        Some(CSV_FORMAT) => SnapshotFormat::Csv,
+        _ => SnapshotFormat::Json
+    }
+}
+
+fn get_workers_count(matches: &ArgMatches) -> u16 {
+    matches.get_one::<u16>(WORKERS_OPTION).copied().unwrap_or(1)
+}
+
 fn get_account_filter_options(matches: &ArgMatches) -> AccountFilterOptions {
     let category_name = matches.get_one::<String>(CATEGORY_FILTER_OPTION);
     let client_name = matches.get_one::<String>(CLIENT_FILTER_OPTION);
@@ -0,0 +1,459 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use log::{debug, info};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::config::{AppConfig, PermissionsConfig};
+use crate::syspass::{Account, ACCOUNT_NOT_FOUND_ERROR, AccountPermissions, PermissionsCorrection, SecurityEntities};
+use crate::syspass::backend::SyspassBackend;
+use crate::syspass::login::relogin_if_required;
+use crate::syspass::perms::{correct_permissions_for_account_by_id, get_security_entities_for_account_by_id, set_permissions_for_account_by_id};
+use crate::syspass::webdriver::WebDriverBackend;
+use crate::types::{EmptyResult, OperationResult};
+
+/// Max items returned by `account/search` method
+pub const API_SEARCH_COUNT: u32 = 100000;
+
+/// API returns all accounts at once, they are split into pages for workers
+pub const API_PAGE_SIZE: usize = 50;
+
+/// Client for sysPass JSON-RPC API (`api.php`).
+///
+/// API doesn't support permissions management, so it's used for lookups only.
+pub struct SyspassApiClient {
+    url: String,
+    token: String,
+    http: reqwest::Client,
+}
+
+#[derive(Serialize)]
+struct RpcRequest<'a> {
+    jsonrpc: &'a str,
+    method: &'a str,
+    params: Value,
+    id: u32,
+}
+
+#[derive(Deserialize,Debug)]
+struct RpcResponse {
+    result: Option<RpcResult>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize,Debug)]
+struct RpcResult {
+    result: Value,
+}
+
+#[derive(Deserialize,Debug)]
+struct RpcError {
+    message: String,
+    code: i32,
+}
+
+#[derive(Deserialize,PartialEq,Debug,Clone)]
+pub struct ApiAccount {
+    pub id: u32,
+    pub name: String,
+    pub login: String,
+    #[serde(rename = "categoryName")]
+    pub category_name: String,
+    #[serde(rename = "clientName")]
+    pub client_name: String,
+}
+
+impl ApiAccount {
+    pub fn matches(&self, login: &str, client: &str, category: &str) -> bool {
+        self.login == login && self.client_name == client && self.category_name == category
+    }
+
+    pub fn to_account(&self) -> Account {
+        Account {
+            name: self.name.to_string(),
+            login: self.login.to_string(),
+            category: self.category_name.to_string(),
+            client: self.client_name.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize,PartialEq,Debug,Clone)]
+pub struct ApiAccountDetails {
+    pub id: u32,
+    pub name: String,
+    pub login: String,
+    #[serde(rename = "categoryName")]
+    pub category_name: String,
+    #[serde(rename = "clientName")]
+    pub client_name: String,
+
+    /// Account owner
+    #[serde(rename = "userName", default)]
+    pub user_name: String,
+
+    /// Account main group
+    #[serde(rename = "userGroupName", default)]
+    pub user_group_name: String,
+
+    #[serde(rename = "isPrivate", default)]
+    pub is_private: u8,
+
+    #[serde(rename = "isPrivateGroup", default)]
+    pub is_private_group: u8,
+
+    #[serde(default)]
+    pub users: Vec<ApiSecurityEntity>,
+
+    #[serde(rename = "userGroups", default)]
+    pub user_groups: Vec<ApiSecurityEntity>,
+}
+
+impl ApiAccountDetails {
+    pub fn to_permissions(&self) -> AccountPermissions {
+        AccountPermissions {
+            user_view: get_entity_names(&self.users, false),
+            user_edit: get_entity_names(&self.users, true),
+            group_view: get_entity_names(&self.user_groups, false),
+            group_edit: get_entity_names(&self.user_groups, true),
+            owner: self.user_name.to_string(),
+            main_group: self.user_group_name.to_string(),
+            private_account: self.is_private == 1,
+            private_account_for_group: self.is_private_group == 1,
+        }
+    }
+}
+
+fn get_entity_names(entities: &[ApiSecurityEntity], edit: bool) -> Vec<String> {
+    entities.iter()
+        .filter(|entity| (entity.is_edit == 1) == edit)
+        .map(|entity| entity.name.to_string())
+        .collect()
+}
+
+/// User or group with access to account
+#[derive(Deserialize,PartialEq,Debug,Clone)]
+pub struct ApiSecurityEntity {
+    pub id: u32,
+    pub name: String,
+    #[serde(rename = "isEdit", default)]
+    pub is_edit: u8,
+}
+
+#[derive(Deserialize,PartialEq,Debug,Clone)]
+pub struct ApiMetaProperty {
+    pub id: u32,
+    pub name: String,
+}
+
+impl SyspassApiClient {
+    pub fn new(url: &str, token: &str) -> SyspassApiClient {
+        SyspassApiClient {
+            url: url.to_string(),
+            token: token.to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Returns api client if `api-url` and `api-token` were provided in config
+    pub fn from_config(config: &AppConfig) -> Option<SyspassApiClient> {
+        match (&config.api_url, &config.api_token) {
+            (Some(url), Some(token)) if !url.is_empty() && !token.is_empty() => {
+                info!("syspass api is available, url '{}'", url);
+                Some(SyspassApiClient::new(url, token))
+            }
+            _ => None
+        }
+    }
+
+    pub async fn search_accounts(&self, text: &str) -> OperationResult<Vec<ApiAccount>> {
+        self.call("account/search", json!({
+            "text": text,
+            "count": API_SEARCH_COUNT
+        })).await
+    }
+
+    /// Search account by login, client and category names
+    pub async fn find_account(&self, login: &str, client: &str,
+                              category: &str) -> OperationResult<Option<ApiAccount>> {
+        let accounts = self.search_accounts(login).await?;
+        Ok(accounts.into_iter().find(|account| account.matches(login, client, category)))
+    }
+
+    pub async fn view_account(&self, id: u32) -> OperationResult<ApiAccountDetails> {
+        self.call("account/view", json!({ "id": id })).await
+    }
+
+    pub async fn search_categories(&self, text: &str) -> OperationResult<Vec<ApiMetaProperty>> {
+        self.call("category/search", json!({ "text": text })).await
+    }
+
+    pub async fn search_clients(&self, text: &str) -> OperationResult<Vec<ApiMetaProperty>> {
+        self.call("client/search", json!({ "text": text })).await
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, mut params: Value) -> OperationResult<T> {
+        debug!("api call '{}', params: {}", method, params);
+
+        params["authToken"] = Value::String(self.token.to_string());
+
+        let request = RpcRequest {
+            jsonrpc: "2.0",
+            method,
+            params,
+            id: 1,
+        };
+
+        let response: RpcResponse = self.http.post(&self.url)
+            .json(&request)
+            .send().await
+            .context("couldn't send request to syspass api")?
+            .error_for_status()?
+            .json().await
+            .context("unexpected syspass api response")?;
+
+        match response.error {
+            Some(error) => Err(anyhow!("syspass api error (code {}): {}", error.code, error.message)),
+            None => {
+                match response.result {
+                    Some(result) => {
+                        let value = serde_json::from_value::<T>(result.result)
+                            .context("unexpected syspass api result")?;
+                        Ok(value)
+                    }
+                    None => Err(anyhow!("syspass api returned empty response"))
+                }
+            }
+        }
+    }
+}
+
+/// Lookups via sysPass API, permissions are written via web ui.
+///
+/// Webdriver session is created on first write.
+pub struct ApiBackend<'a> {
+    config: &'a AppConfig,
+    api_client: SyspassApiClient,
+    account_ids: HashMap<Account, u32>,
+
+    /// Search result for `search_accounts_page`, API is called once per backend
+    search_result: Option<Vec<Account>>,
+
+    webdriver_backend: Option<WebDriverBackend<'a>>,
+}
+
+impl<'a> ApiBackend<'a> {
+    pub fn new(config: &'a AppConfig, api_client: SyspassApiClient) -> ApiBackend<'a> {
+        ApiBackend {
+            config,
+            api_client,
+            account_ids: HashMap::new(),
+            search_result: None,
+            webdriver_backend: None,
+        }
+    }
+
+    async fn get_account_id(&mut self, account: &Account) -> OperationResult<u32> {
+        if let Some(id) = self.account_ids.get(account) {
+            return Ok(*id)
+        }
+
+        match self.api_client.find_account(&account.login, &account.client, &account.category).await? {
+            Some(api_account) => {
+                debug!("account '{}' has id {}", account.login, api_account.id);
+                self.account_ids.insert(account.clone(), api_account.id);
+                Ok(api_account.id)
+            }
+            None => Err(anyhow!("{} '{}' via syspass api", ACCOUNT_NOT_FOUND_ERROR, account.login))
+        }
+    }
+
+    /// Create webdriver session on first call, then login again if session has expired
+    async fn get_webdriver_backend(&mut self) -> OperationResult<&WebDriverBackend<'a>> {
+        if self.webdriver_backend.is_none() {
+            let mut webdriver_backend = WebDriverBackend::new(self.config).await?;
+            webdriver_backend.login().await?;
+            self.webdriver_backend = Some(webdriver_backend);
+        }
+
+        match &self.webdriver_backend {
+            Some(webdriver_backend) => {
+                relogin_if_required(&webdriver_backend.driver, self.config, &webdriver_backend.selectors).await?;
+                Ok(webdriver_backend)
+            }
+            None => Err(anyhow!("webdriver session wasn't created"))
+        }
+    }
+}
+
+#[async_trait]
+impl SyspassBackend for ApiBackend<'_> {
+    async fn login(&mut self) -> EmptyResult {
+        info!("syspass api uses token authentication, web ui login is delayed until first write");
+        Ok(())
+    }
+
+    async fn search_accounts(&mut self) -> OperationResult<Vec<Account>> {
+        let api_accounts = self.api_client.search_accounts("").await?;
+        info!("accounts found: {}", api_accounts.len());
+
+        let mut accounts: Vec<Account> = vec![];
+
+        for api_account in api_accounts {
+            let account = api_account.to_account();
+            self.account_ids.insert(account.clone(), api_account.id);
+            accounts.push(account);
+        }
+
+        Ok(accounts)
+    }
+
+    async fn search_accounts_page(&mut self, page: usize) -> OperationResult<Option<Vec<Account>>> {
+        if self.search_result.is_none() {
+            self.search_result = Some(self.search_accounts().await?);
+        }
+
+        let accounts = self.search_result.as_deref().unwrap_or_default();
+
+        Ok(accounts.chunks(API_PAGE_SIZE).nth(page).map(|page_accounts| page_accounts.to_vec()))
+    }
+
+    async fn read_permissions(&mut self, account: &Account) -> OperationResult<AccountPermissions> {
+        let id = self.get_account_id(account).await?;
+        let account_details = self.api_client.view_account(id).await?;
+        Ok(account_details.to_permissions())
+    }
+
+    async fn write_permissions(&mut self, account: &Account, permissions: &PermissionsConfig) -> EmptyResult {
+        let id = self.get_account_id(account).await?;
+        let config = self.config;
+        let webdriver_backend = self.get_webdriver_backend().await?;
+        set_permissions_for_account_by_id(config, &webdriver_backend.selectors, &webdriver_backend.driver,
+                                          id, permissions).await
+    }
+
+    async fn correct_permissions(&mut self, account: &Account, correction: &PermissionsCorrection) -> EmptyResult {
+        let id = self.get_account_id(account).await?;
+        let config = self.config;
+        let webdriver_backend = self.get_webdriver_backend().await?;
+        correct_permissions_for_account_by_id(config, &webdriver_backend.selectors, &webdriver_backend.driver,
+                                              id, correction).await
+    }
+
+    async fn read_security_entities(&mut self, account: &Account) -> OperationResult<SecurityEntities> {
+        let id = self.get_account_id(account).await?;
+        let config = self.config;
+        let webdriver_backend = self.get_webdriver_backend().await?;
+        get_security_entities_for_account_by_id(config, &webdriver_backend.selectors,
+                                                &webdriver_backend.driver, id).await
+    }
+
+    async fn restart(&mut self) -> EmptyResult {
+        if let Some(webdriver_backend) = &mut self.webdriver_backend {
+            webdriver_backend.restart().await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::syspass::api::SyspassApiClient;
+    use crate::tests::rpc::start_mock_rpc_server;
+
+    #[tokio::test]
+    async fn search_accounts() {
+        let url = start_mock_rpc_server(|method, params| {
+            assert_eq!("account/search", method);
+            assert_eq!("3jf84h", params["authToken"]);
+            assert_eq!("demo", params["text"]);
+
+            Ok(json!([
+                {
+                    "id": 5,
+                    "name": "Hercules II",
+                    "login": "demo",
+                    "categoryName": "Apps",
+                    "clientName": "BirchShop",
+                    "url": "https://demo.local"
+                }
+            ]))
+        }).await;
+
+        let client = SyspassApiClient::new(&url, "3jf84h");
+
+        let accounts = client.search_accounts("demo").await.unwrap();
+
+        assert_eq!(1, accounts.len());
+
+        let account = accounts.first().unwrap();
+        assert_eq!(5, account.id);
+        assert!(account.matches("demo", "BirchShop", "Apps"));
+        assert!(!account.matches("demo", "BirchShop", "Web"));
+    }
+
+    #[tokio::test]
+    async fn view_account() {
+        let url = start_mock_rpc_server(|method, params| {
+            assert_eq!("account/view", method);
+            assert_eq!(5, params["id"]);
+
+            Ok(json!({
+                "id": 5,
+                "name": "Hercules II",
+                "login": "demo",
+                "categoryName": "Apps",
+                "clientName": "BirchShop",
+                "userName": "Mr.Editor",
+                "userGroupName": "Admins",
+                "isPrivate": 0,
+                "isPrivateGroup": 1,
+                "users": [
+                    { "id": 2, "name": "sysPass Admin", "isEdit": 1 }
+                ],
+                "userGroups": []
+            }))
+        }).await;
+
+        let client = SyspassApiClient::new(&url, "3jf84h");
+
+        let account = client.view_account(5).await.unwrap();
+
+        assert_eq!("Mr.Editor", account.user_name);
+        assert_eq!(1, account.is_private_group);
+        assert_eq!(1, account.users.len());
+        assert!(account.user_groups.is_empty());
+    }
+
+    #[tokio::test]
+    async fn return_error_for_rpc_error() {
+        let url = start_mock_rpc_server(|_, _| Err((-32603, "Internal error".to_string()))).await;
+
+        let client = SyspassApiClient::new(&url, "3jf84h");
+
+        assert!(client.search_clients("").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn find_account_by_login_client_and_category() {
+        let url = start_mock_rpc_server(|_, _| {
+            Ok(json!([
+                { "id": 1, "name": "A", "login": "demo", "categoryName": "Web", "clientName": "BirchShop" },
+                { "id": 2, "name": "B", "login": "demo", "categoryName": "Apps", "clientName": "BirchShop" }
+            ]))
+        }).await;
+
+        let client = SyspassApiClient::new(&url, "3jf84h");
+
+        let account = client.find_account("demo", "BirchShop", "Apps").await.unwrap();
+        assert_eq!(2, account.unwrap().id);
+
+        assert!(client.find_account("demo", "KalinkaStore", "Apps").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,54 @@
+use async_trait::async_trait;
+
+use crate::config::{AppConfig, PermissionsConfig};
+use crate::syspass::{Account, AccountPermissions, PermissionsCorrection, SecurityEntities};
+use crate::syspass::api::{ApiBackend, SyspassApiClient};
+use crate::syspass::webdriver::WebDriverBackend;
+use crate::types::{EmptyResult, OperationResult};
+
+/// Operations with sysPass instance required by commands
+#[async_trait]
+pub trait SyspassBackend: Send {
+    async fn login(&mut self) -> EmptyResult;
+
+    /// Get all accounts available for user
+    async fn search_accounts(&mut self) -> OperationResult<Vec<Account>>;
+
+    /// Accounts from search results page (zero-based), `None` if page doesn't exist.
+    /// Pages are independent, so workers can read them in parallel.
+    async fn search_accounts_page(&mut self, page: usize) -> OperationResult<Option<Vec<Account>>>;
+
+    async fn read_permissions(&mut self, account: &Account) -> OperationResult<AccountPermissions>;
+
+    async fn write_permissions(&mut self, account: &Account, permissions: &PermissionsConfig) -> EmptyResult;
+
+    /// Add and remove listed users and groups only, see `PermissionsCorrection`
+    async fn correct_permissions(&mut self, account: &Account, correction: &PermissionsCorrection) -> EmptyResult;
+
+    /// Users and groups available on edit page of given account
+    async fn read_security_entities(&mut self, account: &Account) -> OperationResult<SecurityEntities>;
+
+    /// Close current session, open new one and login again
+    async fn restart(&mut self) -> EmptyResult;
+}
+
+/// Returns API backend if `api-url` and `api-token` were provided, otherwise webdriver backend
+pub async fn create_backend(config: &AppConfig) -> OperationResult<Box<dyn SyspassBackend + '_>> {
+    match SyspassApiClient::from_config(config) {
+        Some(api_client) => Ok(Box::new(ApiBackend::new(config, api_client))),
+        None => Ok(Box::new(WebDriverBackend::new(config).await?))
+    }
+}
+
+/// Create `count` backends and login with each of them
+pub async fn create_logged_backends(config: &AppConfig, count: u16) -> OperationResult<Vec<Box<dyn SyspassBackend + '_>>> {
+    let mut backends = vec![];
+
+    for _ in 0..count.max(1) {
+        let mut backend = create_backend(config).await?;
+        backend.login().await?;
+        backends.push(backend);
+    }
+
+    Ok(backends)
+}
@@ -1,16 +1,301 @@
+use std::fmt::{Display, Formatter};
+
 use serde::{Deserialize, Serialize};
 
+use crate::config::{EntityPermissionsConfig, PermissionsConfig, PermissionsMode};
+
+pub mod api;
+pub mod backend;
 pub mod login;
 pub mod perms;
 pub mod search;
+pub mod selectors;
+pub mod version;
+pub mod wait;
+pub mod webdriver;
 
 pub const UNSUPPORTED_UI_VERSION_ERROR: &str = "unsupported ui version, check logs for details";
-pub const ELEMENT_NOT_FOUND_ERROR: &str = "unexpected error, element wasn't found";
+pub const ACCOUNT_NOT_FOUND_ERROR: &str = "account wasn't found";
+pub const SAVE_REJECTED_ERROR: &str = "account changes have been rejected by syspass";
+pub const FIELD_NOT_FOUND_ERROR: &str = "field wasn't found on account page, unsupported ui version";
+
+/// User or group wasn't selected in permission input on account edit page,
+/// i.e. name doesn't exist in sysPass or ui didn't react in time
+#[derive(PartialEq,Debug,Clone)]
+pub struct EntityNotAppliedError {
+    /// Permission input, i.e. `user-view` or `owner`
+    pub field: String,
+
+    pub names: Vec<String>,
+}
+
+impl Display for EntityNotAppliedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<String> = self.names.iter().map(|name| format!("'{}'", name)).collect();
+        write!(f, "{} wasn't applied for {}", names.join(", "), self.field)
+    }
+}
 
-#[derive(Serialize,Deserialize,PartialEq,Debug,Clone)]
+impl std::error::Error for EntityNotAppliedError {}
+
+#[derive(Serialize,Deserialize,PartialEq,Eq,Hash,Debug,Clone)]
 pub struct Account {
     pub name: String,
     pub login: String,
     pub category: String,
     pub client: String
 }
+
+/// Account permissions as they are shown on permissions tab
+#[derive(Serialize,Deserialize,PartialEq,Debug,Clone,Default)]
+pub struct AccountPermissions {
+    pub user_view: Vec<String>,
+    pub user_edit: Vec<String>,
+    pub group_view: Vec<String>,
+    pub group_edit: Vec<String>,
+
+    pub owner: String,
+    pub main_group: String,
+
+    pub private_account: bool,
+    pub private_account_for_group: bool
+}
+
+/// Users and groups which can be granted permissions, i.e. options of permission inputs
+#[derive(PartialEq,Debug,Clone,Default)]
+pub struct SecurityEntities {
+    pub users: Vec<String>,
+    pub groups: Vec<String>,
+}
+
+impl AccountPermissions {
+    /// Nobody has access to account except owner and main group
+    pub fn is_empty(&self) -> bool {
+        self.user_view.is_empty() && self.user_edit.is_empty() &&
+        self.group_view.is_empty() && self.group_edit.is_empty()
+    }
+
+    /// Config which sets exactly these permissions, existing users and groups are replaced
+    pub fn to_config(&self) -> PermissionsConfig {
+        PermissionsConfig {
+            user: EntityPermissionsConfig {
+                view: self.user_view.clone(),
+                edit: self.user_edit.clone(),
+                mode: PermissionsMode::Replace,
+            },
+            group: EntityPermissionsConfig {
+                view: self.group_view.clone(),
+                edit: self.group_edit.clone(),
+                mode: PermissionsMode::Replace,
+            },
+            owner: self.owner.to_string(),
+            main_group: self.main_group.to_string(),
+            private_account: self.private_account,
+            private_account_for_group: self.private_account_for_group,
+        }
+    }
+
+    /// Expected permissions after `permissions` config is applied
+    pub fn with_config(&self, permissions: &PermissionsConfig) -> AccountPermissions {
+        let user_mode = permissions.user.mode;
+        let group_mode = permissions.group.mode;
+
+        AccountPermissions {
+            user_view: apply_entity_permissions(&self.user_view, &permissions.user.view, user_mode),
+            user_edit: apply_entity_permissions(&self.user_edit, &permissions.user.edit, user_mode),
+            group_view: apply_entity_permissions(&self.group_view, &permissions.group.view, group_mode),
+            group_edit: apply_entity_permissions(&self.group_edit, &permissions.group.edit, group_mode),
+            owner: get_value_or_current(&self.owner, &permissions.owner),
+            main_group: get_value_or_current(&self.main_group, &permissions.main_group),
+            private_account: permissions.private_account,
+            private_account_for_group: permissions.private_account_for_group,
+        }
+    }
+
+    /// Permissions after correction is applied
+    pub fn with_correction(&self, correction: &PermissionsCorrection) -> AccountPermissions {
+        AccountPermissions {
+            user_view: correction.user_view.apply(&self.user_view),
+            user_edit: correction.user_edit.apply(&self.user_edit),
+            group_view: correction.group_view.apply(&self.group_view),
+            group_edit: correction.group_edit.apply(&self.group_edit),
+            owner: get_value_or_current(&self.owner, &correction.owner),
+            main_group: get_value_or_current(&self.main_group, &correction.main_group),
+            private_account: correction.private_account,
+            private_account_for_group: correction.private_account_for_group,
+        }
+    }
+}
+
+/// Users or groups to remove from and to add to one permission input
+#[derive(PartialEq,Debug,Clone,Default)]
+pub struct EntityCorrection {
+    pub add: Vec<String>,
+    pub remove: Vec<String>,
+}
+
+impl EntityCorrection {
+    fn new(current: &[String], expected: &[String]) -> EntityCorrection {
+        EntityCorrection {
+            add: expected.iter().filter(|name| !current.contains(name)).cloned().collect(),
+            remove: current.iter().filter(|name| !expected.contains(name)).cloned().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.add.is_empty() && self.remove.is_empty()
+    }
+
+    fn apply(&self, current: &[String]) -> Vec<String> {
+        let mut result = apply_entity_permissions(current, &self.remove, PermissionsMode::Remove);
+        result = apply_entity_permissions(&result, &self.add, PermissionsMode::Add);
+        result
+    }
+}
+
+/// Minimal change which turns current permissions into expected ones, untouched inputs keep their tags.
+///
+/// Blank `owner` and `main_group` mean "don't change", switches are clicked only if their state differs.
+#[derive(PartialEq,Debug,Clone,Default)]
+pub struct PermissionsCorrection {
+    pub user_view: EntityCorrection,
+    pub user_edit: EntityCorrection,
+    pub group_view: EntityCorrection,
+    pub group_edit: EntityCorrection,
+
+    pub owner: String,
+    pub main_group: String,
+
+    pub private_account: bool,
+    pub private_account_for_group: bool,
+}
+
+impl PermissionsCorrection {
+    pub fn new(current: &AccountPermissions, expected: &AccountPermissions) -> PermissionsCorrection {
+        let get_changed_value = |current: &str, expected: &str| {
+            if current != expected { expected.to_string() } else { String::new() }
+        };
+
+        PermissionsCorrection {
+            user_view: EntityCorrection::new(&current.user_view, &expected.user_view),
+            user_edit: EntityCorrection::new(&current.user_edit, &expected.user_edit),
+            group_view: EntityCorrection::new(&current.group_view, &expected.group_view),
+            group_edit: EntityCorrection::new(&current.group_edit, &expected.group_edit),
+            owner: get_changed_value(&current.owner, &expected.owner),
+            main_group: get_changed_value(&current.main_group, &expected.main_group),
+            private_account: expected.private_account,
+            private_account_for_group: expected.private_account_for_group,
+        }
+    }
+
+    /// Owner, main group and switches as config for `set_secondary_properties`, entity lists are empty
+    pub fn to_secondary_config(&self) -> PermissionsConfig {
+        let get_entity_config = || EntityPermissionsConfig {
+            view: vec![],
+            edit: vec![],
+            mode: PermissionsMode::Add,
+        };
+
+        PermissionsConfig {
+            user: get_entity_config(),
+            group: get_entity_config(),
+            owner: self.owner.to_string(),
+            main_group: self.main_group.to_string(),
+            private_account: self.private_account,
+            private_account_for_group: self.private_account_for_group,
+        }
+    }
+}
+
+pub fn apply_entity_permissions(current: &[String], names: &[String], mode: PermissionsMode) -> Vec<String> {
+    match mode {
+        PermissionsMode::Add => {
+            let mut result = current.to_vec();
+
+            for name in names {
+                if !result.contains(name) {
+                    result.push(name.to_string());
+                }
+            }
+
+            result
+        }
+        PermissionsMode::Replace => names.to_vec(),
+        PermissionsMode::Remove => current.iter()
+            .filter(|name| !names.contains(name)).cloned().collect()
+    }
+}
+
+/// Blank value in config means "don't change"
+fn get_value_or_current(current: &str, value: &str) -> String {
+    if value.is_empty() {
+        current.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::PermissionsMode;
+    use crate::syspass::{AccountPermissions, apply_entity_permissions, PermissionsCorrection};
+
+    #[test]
+    fn add_mode_should_keep_existing_names() {
+        assert_eq!(
+            get_names(&["Admins", "Demo group 1"]),
+            apply_entity_permissions(&get_names(&["Admins"]), &get_names(&["Demo group 1", "Admins"]),
+                                     PermissionsMode::Add)
+        );
+    }
+
+    #[test]
+    fn replace_mode_should_drop_existing_names() {
+        assert_eq!(
+            get_names(&["Demo group 1"]),
+            apply_entity_permissions(&get_names(&["Admins"]), &get_names(&["Demo group 1"]),
+                                     PermissionsMode::Replace)
+        );
+
+        assert!(apply_entity_permissions(&get_names(&["Admins"]), &[], PermissionsMode::Replace).is_empty());
+    }
+
+    #[test]
+    fn remove_mode_should_drop_listed_names_only() {
+        assert_eq!(
+            get_names(&["Admins"]),
+            apply_entity_permissions(&get_names(&["Admins", "Beta Group"]), &get_names(&["Beta Group", "Unknown"]),
+                                     PermissionsMode::Remove)
+        );
+    }
+
+    #[test]
+    fn correction_should_contain_differences_only() {
+        let current = AccountPermissions {
+            group_view: get_names(&["Admins", "Old Team"]),
+            user_edit: get_names(&["j.doe"]),
+            owner: "j.doe".to_string(),
+            main_group: "Admins".to_string(),
+            ..AccountPermissions::default()
+        };
+
+        let expected = AccountPermissions {
+            group_view: get_names(&["Admins", "Beta Group"]),
+            ..current.clone()
+        };
+
+        let correction = PermissionsCorrection::new(&current, &expected);
+
+        assert_eq!(get_names(&["Beta Group"]), correction.group_view.add);
+        assert_eq!(get_names(&["Old Team"]), correction.group_view.remove);
+        assert!(correction.user_edit.is_empty());
+        assert_eq!("", correction.owner);
+        assert_eq!("", correction.main_group);
+
+        assert_eq!(expected, current.with_correction(&correction));
+    }
+
+    fn get_names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+}
@@ -1,110 +1,504 @@
-use std::thread;
-use std::time::Duration;
-
 use anyhow::anyhow;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use thirtyfour::{By, Key, WebDriver, WebElement};
 
-use crate::config::{AppConfig, PermissionsConfig};
-use crate::syspass::search::{get_search_item_category, get_search_item_client, get_search_item_login};
-use crate::syspass::UNSUPPORTED_UI_VERSION_ERROR;
+use crate::config::{AppConfig, PermissionsConfig, PermissionsMode, WaitConfig};
+use crate::syspass::{Account, ACCOUNT_NOT_FOUND_ERROR, AccountPermissions, EntityNotAppliedError, FIELD_NOT_FOUND_ERROR, PermissionsCorrection, SAVE_REJECTED_ERROR, SecurityEntities, UNSUPPORTED_UI_VERSION_ERROR};
+use crate::syspass::selectors::UiSelectors;
+use crate::syspass::search::{get_first_search_item, get_search_item_category, get_search_item_client, get_search_item_login, wait_for_search_results_update};
+use crate::syspass::wait::{wait_for, wait_for_element, wait_until};
 use crate::types::{EmptyResult, OperationResult};
 
-pub async fn set_permissions_for_account(config: &AppConfig,
-    driver: &WebDriver, account_login: &str,
-    account_client: &str, account_category: &str) -> EmptyResult {
-    info!("set permissions for syspass account '{}'", account_login);
-
+/// Search account on index page by login, client and category.
+///
+/// Returns search result element (`search.item` selector) if account was found.
+pub async fn find_account_search_item(config: &AppConfig, selectors: &UiSelectors, driver: &WebDriver,
+                                      account: &Account) -> OperationResult<Option<WebElement>> {
     let url = format!("{}/index.php?r=index", &config.syspass_url);
 
     driver.goto(&url).await?;
 
-    thread::sleep(Duration::from_millis(config.delays.after_redirect_to_index));
+    let search_input = wait_for_element(driver, &config.wait, By::Css(&selectors.search.search_input),
+                                        "search input on index page").await?;
+
+    let previous_first_item = get_first_search_item(driver, selectors).await?;
 
-    let search_input = driver.find(By::Id("search")).await?;
     search_input.clear().await?;
-    search_input.send_keys(account_login + Key::Enter).await?;
+    search_input.send_keys(account.login.to_string() + Key::Enter).await?;
 
-    thread::sleep(Duration::from_millis(config.delays.after_search));
+    wait_for_search_results_update(&config.wait, previous_first_item).await?;
 
-    let search_result_elements = driver.find_all(By::ClassName("account-label")).await?;
+    let search_result_elements = driver.find_all(By::Css(&selectors.search.item)).await?;
 
     for search_result_element in search_result_elements {
-        let item_client = get_search_item_client(&search_result_element).await?;
+        let item_client = get_search_item_client(&search_result_element, selectors).await?;
         debug!("client: '{}'", item_client);
 
-        let item_category = get_search_item_category(&search_result_element).await?;
+        let item_category = get_search_item_category(&search_result_element, selectors).await?;
         debug!("category: '{}'", item_category);
 
-        let item_login = get_search_item_login(&search_result_element).await?;
+        let item_login = get_search_item_login(&search_result_element, selectors).await?;
         debug!("username: '{}'", item_login);
 
-        if item_login == account_login &&
-            item_client == account_client &&
-            item_category == account_category {
+        if item_login == account.login &&
+            item_client == account.client &&
+            item_category == account.category {
+            return Ok(Some(search_result_element))
+        }
+    }
+
+    Ok(None)
+}
+
+pub async fn set_permissions_for_account(config: &AppConfig, selectors: &UiSelectors, driver: &WebDriver,
+                                         account: &Account, permissions: &PermissionsConfig) -> EmptyResult {
+    info!("set permissions for syspass account '{}'", account.login);
+
+    match find_account_search_item(config, selectors, driver, account).await? {
+        Some(search_result_element) => {
             debug!("going to account edit page");
-            open_account_actions_menu(&search_result_element).await?;
+            open_account_actions_menu(&search_result_element, selectors, &config.wait).await?;
+
+            go_to_account_edit_page(&search_result_element, selectors).await?;
+
+            set_permissions_on_edit_page(driver, selectors, &config.wait, permissions, config.strict).await?;
+
+            let url = format!("{}/index.php?r=index", &config.syspass_url);
+            driver.goto(&url).await?;
+            debug!("returned to index page");
+
+            Ok(())
+        }
+        None => Err(anyhow!("{} '{}'", ACCOUNT_NOT_FOUND_ERROR, account.login))
+    }
+}
+
+/// Open account edit page directly by account id (known from sysPass API) and set permissions
+pub async fn set_permissions_for_account_by_id(config: &AppConfig, selectors: &UiSelectors,
+                                               driver: &WebDriver, account_id: u32,
+                                               permissions: &PermissionsConfig) -> EmptyResult {
+    info!("set permissions for syspass account id {}", account_id);
+
+    let url = format!("{}/index.php?r=account/edit/{}", &config.syspass_url, account_id);
+
+    driver.goto(&url).await?;
+
+    set_permissions_on_edit_page(driver, selectors, &config.wait, permissions, config.strict).await
+}
+
+/// Set permissions on account edit page and save changes.
+///
+/// `strict` - don't save changes if some user or group wasn't applied, see `EntityNotAppliedError`.
+pub async fn set_permissions_on_edit_page(driver: &WebDriver, selectors: &UiSelectors, wait: &WaitConfig,
+                                          permissions: &PermissionsConfig, strict: bool) -> EmptyResult {
+    let click_for_close_element = wait_for_element(driver, wait, By::Css(&selectors.account.edit_form),
+                                                   "account edit form").await?;
+
+    open_permissions_tab(driver, selectors, wait).await?;
 
-            thread::sleep(Duration::from_millis(config.delays.menu_open));
+    let permission_panel = driver.find(By::Css(&selectors.account.permission_panel)).await?;
 
-            go_to_account_edit_page(&search_result_element).await?;
+    set_permissions_for_security_entities(&permission_panel, selectors, wait, permissions,
+                                          &click_for_close_element, strict).await?;
 
-            thread::sleep(Duration::from_millis(config.delays.after_redirect_to_edit));
+    set_secondary_properties(&permission_panel, selectors, wait, permissions,
+                             &click_for_close_element, strict).await?;
 
-            open_permissions_tab(&driver).await?;
+    save_account_form(driver, &permission_panel, selectors, wait).await
+}
+
+/// Form is saved via ajax, so sysPass notification is awaited before leaving the page.
+/// Error notification (i.e. validation error or lack of permissions) fails account.
+pub async fn save_account_form(driver: &WebDriver, permission_panel: &WebElement, selectors: &UiSelectors,
+                               wait: &WaitConfig) -> EmptyResult {
+    let save_button = permission_panel.find(By::Css(&selectors.account.save_button)).await?;
+    save_button.click().await?;
+    info!("permissions form has been submitted, wait for result");
 
-            let click_for_close_element = driver.find(By::Id("frmAccount")).await?;
+    let (saved, message) = wait_for(wait, "account save result", || async {
+        let results = [(&selectors.account.save_success, true), (&selectors.account.save_error, false)];
 
-            let perm_inputs = driver.find_all(By::ClassName("tag-list-box")).await?;
+        for (selector, saved) in results {
+            if let Some(element) = driver.find_all(By::Css(selector)).await?.into_iter().next() {
+                return Ok(Some((saved, element.text().await?.trim().to_string())))
+            }
+        }
 
-            set_permissions_for_security_entities(&perm_inputs, &config.permissions, &click_for_close_element).await?;
+        Ok(None)
+    }).await?;
 
-            let permission_panel = driver.find(By::Id("permission-panel")).await?;
+    if saved {
+        info!("permissions have been saved: '{}'", message);
+        Ok(())
 
-            set_secondary_properties(&permission_panel, &config.permissions, &click_for_close_element).await?;
+    } else {
+        error!("syspass has rejected changes: '{}'", message);
+        Err(anyhow!("{}: '{}'", SAVE_REJECTED_ERROR, message))
+    }
+}
 
-            let save_button = permission_panel.find(By::Id("1")).await?;
-            save_button.click().await?;
-            info!("settings have been saved");
+/// Apply minimal correction for account (see `PermissionsCorrection`), other tags and properties aren't touched
+pub async fn correct_permissions_for_account(config: &AppConfig, selectors: &UiSelectors, driver: &WebDriver,
+                                             account: &Account, correction: &PermissionsCorrection) -> EmptyResult {
+    info!("correct permissions for syspass account '{}'", account.login);
 
+    match find_account_search_item(config, selectors, driver, account).await? {
+        Some(search_result_element) => {
+            open_account_actions_menu(&search_result_element, selectors, &config.wait).await?;
+
+            go_to_account_edit_page(&search_result_element, selectors).await?;
+
+            correct_permissions_on_edit_page(driver, selectors, &config.wait, correction, config.strict).await?;
+
+            let url = format!("{}/index.php?r=index", &config.syspass_url);
             driver.goto(&url).await?;
             debug!("returned to index page");
 
-            break;
+            Ok(())
         }
+        None => Err(anyhow!("{} '{}'", ACCOUNT_NOT_FOUND_ERROR, account.login))
     }
+}
 
-    Ok(())
+/// Open account edit page directly by account id (known from search results or sysPass API) and apply correction
+pub async fn correct_permissions_for_account_by_id(config: &AppConfig, selectors: &UiSelectors,
+                                                   driver: &WebDriver, account_id: u32,
+                                                   correction: &PermissionsCorrection) -> EmptyResult {
+    info!("correct permissions for syspass account id {}", account_id);
+
+    let url = format!("{}/index.php?r=account/edit/{}", &config.syspass_url, account_id);
+
+    driver.goto(&url).await?;
+
+    correct_permissions_on_edit_page(driver, selectors, &config.wait, correction, config.strict).await
+}
+
+/// Remove and add listed users and groups only, then set changed secondary properties and save changes
+pub async fn correct_permissions_on_edit_page(driver: &WebDriver, selectors: &UiSelectors, wait: &WaitConfig,
+                                              correction: &PermissionsCorrection, strict: bool) -> EmptyResult {
+    let click_for_close_element = wait_for_element(driver, wait, By::Css(&selectors.account.edit_form),
+                                                   "account edit form").await?;
+
+    open_permissions_tab(driver, selectors, wait).await?;
+
+    let permission_panel = driver.find(By::Css(&selectors.account.permission_panel)).await?;
+
+    let account_selectors = &selectors.account;
+
+    let fields = [
+        ("user-view", &account_selectors.user_view_select, &correction.user_view),
+        ("user-edit", &account_selectors.user_edit_select, &correction.user_edit),
+        ("group-view", &account_selectors.group_view_select, &correction.group_view),
+        ("group-edit", &account_selectors.group_edit_select, &correction.group_edit),
+    ];
+
+    for (field, select_id, entity_correction) in fields {
+        if entity_correction.is_empty() {
+            continue;
+        }
+
+        info!("correct {} permissions", field);
+
+        set_permissions_for_security_entity(field, select_id, &permission_panel, wait,
+                                            &entity_correction.remove, PermissionsMode::Remove).await?;
+
+        check_entity_result(
+            set_permissions_for_security_entity(field, select_id, &permission_panel, wait,
+                                                &entity_correction.add, PermissionsMode::Add).await, strict
+        )?;
+
+        click_for_close_element.click().await?;
+    }
+
+    set_secondary_properties(&permission_panel, selectors, wait, &correction.to_secondary_config(),
+                             &click_for_close_element, strict).await?;
+
+    save_account_form(driver, &permission_panel, selectors, wait).await
+}
+
+/// Returns labels of all selectize options for given select ids, dropdowns aren't rendered
+/// until input is focused, so options are read from selectize instances.
+const SELECTIZE_OPTIONS_SCRIPT: &str = r#"
+    var labels = [];
+
+    arguments[0].forEach(function(id) {
+        var element = document.getElementById(id);
+
+        if (element && element.selectize) {
+            var selectize = element.selectize;
+
+            Object.keys(selectize.options).forEach(function(key) {
+                labels.push(String(selectize.options[key][selectize.settings.labelField]));
+            });
+        }
+    });
+
+    return labels;
+"#;
+
+/// Read available users and groups from account edit page
+pub async fn get_security_entities_for_account(config: &AppConfig, selectors: &UiSelectors, driver: &WebDriver,
+                                               account: &Account) -> OperationResult<SecurityEntities> {
+    info!("get available users and groups from edit page of syspass account '{}'", account.login);
+
+    match find_account_search_item(config, selectors, driver, account).await? {
+        Some(search_result_element) => {
+            open_account_actions_menu(&search_result_element, selectors, &config.wait).await?;
+
+            go_to_account_edit_page(&search_result_element, selectors).await?;
+
+            get_security_entities_on_edit_page(driver, selectors, &config.wait).await
+        }
+        None => Err(anyhow!("{} '{}'", ACCOUNT_NOT_FOUND_ERROR, account.login))
+    }
+}
+
+/// Open account edit page directly by account id (known from sysPass API) and read available users and groups
+pub async fn get_security_entities_for_account_by_id(config: &AppConfig, selectors: &UiSelectors,
+                                                     driver: &WebDriver, account_id: u32) -> OperationResult<SecurityEntities> {
+    info!("get available users and groups from edit page of syspass account id {}", account_id);
+
+    let url = format!("{}/index.php?r=account/edit/{}", &config.syspass_url, account_id);
+
+    driver.goto(&url).await?;
+
+    get_security_entities_on_edit_page(driver, selectors, &config.wait).await
+}
+
+/// Users are read from user view/edit and owner inputs, groups - from group view/edit and main group inputs
+pub async fn get_security_entities_on_edit_page(driver: &WebDriver, selectors: &UiSelectors,
+                                                wait: &WaitConfig) -> OperationResult<SecurityEntities> {
+    wait_for_element(driver, wait, By::Css(&selectors.account.edit_form), "account edit form").await?;
+
+    let account_selectors = &selectors.account;
+
+    let user_select_ids = [&account_selectors.user_view_select, &account_selectors.user_edit_select,
+                           &account_selectors.owner_select];
+
+    let users = get_selectize_options(driver, &user_select_ids).await?;
+    debug!("available users: {:?}", users);
+
+    let group_select_ids = [&account_selectors.group_view_select, &account_selectors.group_edit_select,
+                            &account_selectors.main_group_select];
+
+    let groups = get_selectize_options(driver, &group_select_ids).await?;
+    debug!("available groups: {:?}", groups);
+
+    if users.is_empty() && groups.is_empty() {
+        error!("couldn't read selectize options on account edit page");
+        return Err(anyhow!(UNSUPPORTED_UI_VERSION_ERROR))
+    }
+
+    Ok(SecurityEntities { users, groups })
 }
 
-pub async fn open_permissions_tab(driver: &WebDriver) -> EmptyResult {
-    let tabs = driver.find_all(By::ClassName("mdl-tabs__tab")).await?;
+async fn get_selectize_options(driver: &WebDriver, select_ids: &[&String]) -> OperationResult<Vec<String>> {
+    let result = driver.execute(SELECTIZE_OPTIONS_SCRIPT, vec![serde_json::json!(select_ids)]).await?;
 
-    match tabs.get(1) {
+    let mut labels: Vec<String> = result.convert()?;
+    labels.iter_mut().for_each(|label| *label = label.trim().to_string());
+    labels.sort();
+    labels.dedup();
+
+    Ok(labels)
+}
+
+/// Read permissions from account view page
+pub async fn get_permissions_for_account(config: &AppConfig, selectors: &UiSelectors, driver: &WebDriver,
+                                         account: &Account) -> OperationResult<AccountPermissions> {
+    info!("get permissions for syspass account '{}'", account.login);
+
+    match find_account_search_item(config, selectors, driver, account).await? {
+        Some(search_result_element) => {
+            search_result_element.scroll_into_view().await?;
+
+            go_to_account_view_page(&search_result_element, selectors).await?;
+
+            open_permissions_tab(driver, selectors, &config.wait).await?;
+
+            let permissions_panel = driver.find(By::Css(&selectors.account.permission_panel)).await?;
+
+            get_permissions_from_panel_in_view_mode(&permissions_panel, selectors).await
+        }
+        None => Err(anyhow!("{} '{}'", ACCOUNT_NOT_FOUND_ERROR, account.login))
+    }
+}
+
+/// Open account view page directly by account id (known from search results or sysPass API) and read permissions
+pub async fn get_permissions_for_account_by_id(config: &AppConfig, selectors: &UiSelectors, driver: &WebDriver,
+                                               account_id: u32) -> OperationResult<AccountPermissions> {
+    info!("get permissions for syspass account id {}", account_id);
+
+    let url = format!("{}/index.php?r=account/view/{}", &config.syspass_url, account_id);
+
+    driver.goto(&url).await?;
+
+    open_permissions_tab(driver, selectors, &config.wait).await?;
+
+    let permissions_panel = driver.find(By::Css(&selectors.account.permission_panel)).await?;
+
+    get_permissions_from_panel_in_view_mode(&permissions_panel, selectors).await
+}
+
+/// Read permissions from account view page -> permissions tab.
+///
+/// Expected element with `account.permission-panel` selector as function argument.
+///
+/// View page has no permission inputs, so users, groups, owner and main group rows are located by labels
+/// (`account.*-row-label` selectors), switch rows - by checkbox ids.
+pub async fn get_permissions_from_panel_in_view_mode(permissions_panel_element: &WebElement,
+                                                     selectors: &UiSelectors) -> OperationResult<AccountPermissions> {
+    let account_selectors = &selectors.account;
+
+    let users_row = get_row_by_label(permissions_panel_element, &account_selectors.users_row_label, "users").await?;
+    let (user_view, user_edit) = get_view_and_edit_tags_in_view_mode(&users_row, selectors, "users").await?;
+    debug!("users view tags: {:?}", user_view);
+    debug!("users edit tags: {:?}", user_edit);
+
+    let groups_row = get_row_by_label(permissions_panel_element, &account_selectors.groups_row_label, "groups").await?;
+    let (group_view, group_edit) = get_view_and_edit_tags_in_view_mode(&groups_row, selectors, "groups").await?;
+    debug!("group view tags: {:?}", group_view);
+    debug!("group edit tags: {:?}", group_edit);
+
+    let mut permissions = AccountPermissions {
+        user_view,
+        user_edit,
+        group_view,
+        group_edit,
+        ..AccountPermissions::default()
+    };
+
+    match find_row_by_label(permissions_panel_element, &account_selectors.owner_row_label).await? {
+        Some(owner_row) => {
+            permissions.owner = get_row_value_in_view_mode(&owner_row).await?;
+
+            let main_group_row = get_row_by_label(permissions_panel_element,
+                                                  &account_selectors.main_group_row_label, "main-group").await?;
+            permissions.main_group = get_row_value_in_view_mode(&main_group_row).await?;
+
+            let private_account_row = get_row_by_element_id(
+                permissions_panel_element, &account_selectors.private_account_checkbox, "private-account"
+            ).await?;
+            permissions.private_account = is_checkbox_enabled(&private_account_row, selectors).await?;
+
+            let private_account_for_group_row = get_row_by_element_id(
+                permissions_panel_element, &account_selectors.private_account_for_group_checkbox,
+                "private-account-for-group"
+            ).await?;
+            permissions.private_account_for_group = is_checkbox_enabled(&private_account_for_group_row,
+                                                                        selectors).await?;
+        }
+        None => info!("table with secondary properties is not available, possible lack of permissions. skip")
+    }
+
+    Ok(permissions)
+}
+
+/// Users or groups row contains two tag lists: view and edit
+async fn get_view_and_edit_tags_in_view_mode(row: &WebElement, selectors: &UiSelectors,
+                                             field: &str) -> OperationResult<(Vec<String>, Vec<String>)> {
+    let tag_lists = row.find_all(By::Css(&selectors.account.tag_list)).await?;
+
+    match tag_lists.as_slice() {
+        [view_tag_list, edit_tag_list] => Ok((
+            get_tags_from_list_box_in_view_mode(view_tag_list, selectors).await?,
+            get_tags_from_list_box_in_view_mode(edit_tag_list, selectors).await?
+        )),
+        _ => {
+            error!("expected view and edit tag lists in {} row, found {}", field, tag_lists.len());
+            Err(anyhow!("{} '{}'", FIELD_NOT_FOUND_ERROR, field))
+        }
+    }
+}
+
+/// Get value text from the last cell of table row
+async fn get_row_value_in_view_mode(row: &WebElement) -> OperationResult<String> {
+    let cells = row.find_all(By::Tag("td")).await?;
+
+    match cells.last() {
+        Some(cell) => Ok(cell.text().await?.trim().to_string()),
+        None => Ok(String::new())
+    }
+}
+
+pub async fn open_permissions_tab(driver: &WebDriver, selectors: &UiSelectors, wait: &WaitConfig) -> EmptyResult {
+    wait_for_element(driver, wait, By::Css(&selectors.account.tabs), "account page tabs").await?;
+
+    let tabs = driver.find_all(By::Css(&selectors.account.permissions_tab)).await?;
+
+    match tabs.first() {
         Some(perms_tab) => {
             perms_tab.click().await?;
+            wait_for_element(driver, wait, By::Css(&selectors.account.permission_panel),
+                             "active permissions tab").await?;
             Ok(())
         }
         None => {
             error!("couldn't find permissions tab in ui");
-            Err(anyhow!(UNSUPPORTED_UI_VERSION_ERROR))
+            Err(anyhow!("{} 'permissions tab'", FIELD_NOT_FOUND_ERROR))
         }
     }
 }
 
-pub async fn open_account_actions_menu(element: &WebElement) -> EmptyResult {
-    let actions_block = element.find(By::ClassName("account-actions")).await?;
+/// Find table row with form element (input, select) by element id
+async fn find_row_by_element_id(parent: &WebElement, element_id: &str) -> OperationResult<Option<WebElement>> {
+    let rows = parent.find_all(By::XPath(&format!(".//tr[.//*[@id='{}']]", element_id))).await?;
+    Ok(rows.into_iter().next())
+}
 
-    let more_actions = actions_block.find(By::Tag("button")).await?;
-    more_actions.click().await?;
+async fn get_row_by_element_id(parent: &WebElement, element_id: &str, field: &str) -> OperationResult<WebElement> {
+    match find_row_by_element_id(parent, element_id).await? {
+        Some(row) => Ok(row),
+        None => {
+            error!("couldn't find row with element id '{}'", element_id);
+            Err(anyhow!("{} '{}'", FIELD_NOT_FOUND_ERROR, field))
+        }
+    }
+}
 
-    Ok(())
+/// Row label is the text of the first cell
+async fn find_row_by_label(parent: &WebElement, label: &str) -> OperationResult<Option<WebElement>> {
+    let rows = parent.find_all(By::XPath(&format!(".//tr[td[1][normalize-space()='{}']]", label))).await?;
+    Ok(rows.into_iter().next())
+}
+
+async fn get_row_by_label(parent: &WebElement, label: &str, field: &str) -> OperationResult<WebElement> {
+    match find_row_by_label(parent, label).await? {
+        Some(row) => Ok(row),
+        None => {
+            error!("couldn't find row with label '{}'", label);
+            Err(anyhow!("{} '{}'", FIELD_NOT_FOUND_ERROR, field))
+        }
+    }
+}
+
+/// Selectize wrapper is inserted right after original select element
+async fn find_selectize_control(parent: &WebElement, select_id: &str, field: &str) -> OperationResult<WebElement> {
+    let controls = parent.find_all(By::Css(&format!("#{} + .selectize-control", select_id))).await?;
+
+    match controls.into_iter().next() {
+        Some(control) => Ok(control),
+        None => {
+            error!("couldn't find selectize input for select with id '{}'", select_id);
+            Err(anyhow!("{} '{}'", FIELD_NOT_FOUND_ERROR, field))
+        }
+    }
 }
 
-pub async fn go_to_account_view_page(element: &WebElement) -> EmptyResult {
-    let actions_block = element.find(By::ClassName("account-actions")).await?;
+pub async fn open_account_actions_menu(element: &WebElement, selectors: &UiSelectors, wait: &WaitConfig) -> EmptyResult {
+    let more_actions = element.find(By::Css(&selectors.search.actions_button)).await?;
+    more_actions.click().await?;
 
-    let view_button = actions_block.find_all(By::Tag("i")).await?;
+    let menu = element.find(By::Css(&selectors.search.actions_menu)).await?;
+
+    wait_until(wait, "account actions menu", || async {
+        let class_name = menu.class_name().await?.unwrap_or_default();
+        Ok(class_name.contains("is-visible"))
+    }).await
+}
+
+pub async fn go_to_account_view_page(element: &WebElement, selectors: &UiSelectors) -> EmptyResult {
+    let view_button = element.find_all(By::Css(&selectors.search.view_button)).await?;
 
     match view_button.first() {
         Some(button) => {
@@ -112,16 +506,14 @@ pub async fn go_to_account_view_page(element: &WebElement) -> EmptyResult {
             Ok(())
         }
         None => {
-            error!("couldn't find account view button inside 'account-actions'");
+            error!("couldn't find account view button '{}'", selectors.search.view_button);
             Err(anyhow!(UNSUPPORTED_UI_VERSION_ERROR))
         }
     }
 }
 
-pub async fn go_to_account_edit_page(element: &WebElement) -> EmptyResult {
-    let menu = element.find(By::ClassName("mdl-menu__container")).await?;
-
-    let menu_items = menu.find_all(By::ClassName("btn-action")).await?;
+pub async fn go_to_account_edit_page(element: &WebElement, selectors: &UiSelectors) -> EmptyResult {
+    let menu_items = element.find_all(By::Css(&selectors.search.edit_button)).await?;
 
     match menu_items.first() {
         Some(edit_item) => {
@@ -129,70 +521,85 @@ pub async fn go_to_account_edit_page(element: &WebElement) -> EmptyResult {
             Ok(())
         }
         None => {
-            error!("couldn't find 'btn-action' element inside 'mdl-menu__container'");
+            error!("couldn't find account edit button '{}'", selectors.search.edit_button);
             Err(anyhow!(UNSUPPORTED_UI_VERSION_ERROR))
         }
     }
 }
 
-pub async fn set_secondary_properties(permission_panel: &WebElement, permissions: &PermissionsConfig,
-                                      click_for_close_element: &WebElement) -> EmptyResult {
+pub async fn set_secondary_properties(permission_panel: &WebElement, selectors: &UiSelectors, wait: &WaitConfig,
+                                      permissions: &PermissionsConfig,
+                                      click_for_close_element: &WebElement, strict: bool) -> EmptyResult {
     info!("set secondary properties");
-    let form_rows = permission_panel.find_all(By::Tag("tr")).await?;
 
-    debug!("form rows: {}", form_rows.len());
-
-    if form_rows.len() >= 6 {
+    let account_selectors = &selectors.account;
+
+    let owner_row = find_row_by_element_id(permission_panel, &account_selectors.owner_select).await?;
+    let main_group_row = find_row_by_element_id(permission_panel, &account_selectors.main_group_select).await?;
+    let private_account_row = find_row_by_element_id(
+        permission_panel, &account_selectors.private_account_checkbox).await?;
+    let private_account_for_group_row = find_row_by_element_id(
+        permission_panel, &account_selectors.private_account_for_group_checkbox).await?;
+
+    match (owner_row, main_group_row, private_account_row, private_account_for_group_row) {
+        (Some(owner_row), Some(main_group_row), Some(private_account_row), Some(private_account_for_group_row)) => {
+            if !&permissions.owner.is_empty() {
+                info!("set owner");
+                check_entity_result(
+                    set_additional_property_value(&owner_row, wait, "owner", &permissions.owner).await, strict
+                )?;
+            }
 
-        if !&permissions.owner.is_empty() {
-            let owner_row = form_rows.get(2)
-                .expect("unexpected error");
-            info!("set owner");
-            set_additional_property_value(&owner_row, &permissions.owner).await?;
-        }
+            click_for_close_element.click().await?;
 
-        click_for_close_element.click().await?;
+            if !&permissions.main_group.is_empty() {
+                info!("set main group");
+                check_entity_result(
+                    set_additional_property_value(&main_group_row, wait, "main-group", &permissions.main_group).await, strict
+                )?;
+            }
 
-        if !&permissions.main_group.is_empty() {
-            let main_group_row = form_rows.get(3)
-                .expect("unexpected error");
-            info!("set main group");
-            set_additional_property_value(&main_group_row, &permissions.main_group).await?;
-        }
+            debug!("check if 'private account' option enabled");
+            set_switch_value(&private_account_row, selectors, permissions.private_account).await?;
 
-        let private_account_switch = form_rows.get(4)
-            .expect("unexpected error");
-        debug!("check if 'private account' option enabled");
-        let private_account_switch_status = is_checkbox_enabled(private_account_switch).await?;
+            debug!("check if 'private account for group' option enabled");
+            set_switch_value(&private_account_for_group_row, selectors, permissions.private_account_for_group).await?;
 
-        if permissions.private_account != private_account_switch_status {
-            let option_switch = private_account_switch.find(By::ClassName("mdl-switch")).await?;
-            option_switch.click().await?;
+            Ok(())
         }
-
-        let private_account_for_group_switch = form_rows.get(5)
-            .expect("unexpected error");
-        debug!("check if 'private account for group' option enabled");
-        let private_account_for_group_switch_status = is_checkbox_enabled(
-            private_account_for_group_switch).await?;
-
-        if permissions.private_account_for_group != private_account_for_group_switch_status {
-            let option_switch = private_account_for_group_switch.find(
-                By::ClassName("mdl-switch")
-            ).await?;
-            option_switch.click().await?;
+        (None, None, None, None) => {
+            info!("table with secondary properties is not available, possible lack of permissions. skip");
+            Ok(())
         }
+        (owner_row, main_group_row, private_account_row, _) => {
+            let field = if owner_row.is_none() {
+                "owner"
+            } else if main_group_row.is_none() {
+                "main-group"
+            } else if private_account_row.is_none() {
+                "private-account"
+            } else {
+                "private-account-for-group"
+            };
+
+            error!("couldn't find '{}' row on permissions tab", field);
+            Err(anyhow!("{} '{}'", FIELD_NOT_FOUND_ERROR, field))
+        }
+    }
+}
 
-        Ok(())
-
-    } else {
-        info!("table with secondary properties is not available, possible lack of permissions. skip");
-        Ok(())
+/// Click on switch inside row if its state differs from `enabled`
+async fn set_switch_value(row: &WebElement, selectors: &UiSelectors, enabled: bool) -> EmptyResult {
+    if is_checkbox_enabled(row, selectors).await? != enabled {
+        let option_switch = row.find(By::Css(&selectors.account.switch)).await?;
+        option_switch.click().await?;
     }
+
+    Ok(())
 }
 
-pub async fn is_checkbox_enabled(element: &WebElement) -> OperationResult<bool> {
-    let elements = element.find_all(By::ClassName("is-checked")).await?;
+pub async fn is_checkbox_enabled(element: &WebElement, selectors: &UiSelectors) -> OperationResult<bool> {
+    let elements = element.find_all(By::Css(&selectors.account.checked_switch)).await?;
 
     let status = !elements.is_empty();
     debug!("checkbox enabled: {}", status);
@@ -200,95 +607,198 @@ pub async fn is_checkbox_enabled(element: &WebElement) -> OperationResult<bool>
     Ok(status)
 }
 
-pub async fn set_permissions_for_security_entities(perm_inputs: &Vec<WebElement>,
+pub async fn set_permissions_for_security_entities(permission_panel: &WebElement,
+                                                   selectors: &UiSelectors,
+                                                   wait: &WaitConfig,
                                                    permissions: &PermissionsConfig,
-                                                   click_for_close_element: &WebElement) -> EmptyResult {
-    info!("add user view permissions");
-    set_permissions_for_security_entity("other_users_view-selectized", &perm_inputs, 0,
-                                        &permissions.user.view).await?;
+                                                   click_for_close_element: &WebElement,
+                                                   strict: bool) -> EmptyResult {
+    info!("set user view permissions");
+    check_entity_result(
+        set_permissions_for_security_entity("user-view", &selectors.account.user_view_select, permission_panel, wait,
+                                            &permissions.user.view, permissions.user.mode).await, strict
+    )?;
 
     click_for_close_element.click().await?;
 
-    info!("add user edit permissions");
-    set_permissions_for_security_entity("other_users_edit-selectized", &perm_inputs, 1,
-                                        &permissions.user.edit).await?;
+    info!("set user edit permissions");
+    check_entity_result(
+        set_permissions_for_security_entity("user-edit", &selectors.account.user_edit_select, permission_panel, wait,
+                                            &permissions.user.edit, permissions.user.mode).await, strict
+    )?;
 
     click_for_close_element.click().await?;
 
-    info!("add group view permissions");
-    set_permissions_for_security_entity("other_usergroups_view-selectized", &perm_inputs, 2,
-                                        &permissions.group.view).await?;
+    info!("set group view permissions");
+    check_entity_result(
+        set_permissions_for_security_entity("group-view", &selectors.account.group_view_select, permission_panel, wait,
+                                            &permissions.group.view, permissions.group.mode).await, strict
+    )?;
 
     click_for_close_element.click().await?;
 
-    info!("add group edit permissions");
-    set_permissions_for_security_entity("other_usergroups_edit-selectized", &perm_inputs, 3,
-                                        &permissions.group.edit).await?;
+    info!("set group edit permissions");
+    check_entity_result(
+        set_permissions_for_security_entity("group-edit", &selectors.account.group_edit_select, permission_panel, wait,
+                                            &permissions.group.edit, permissions.group.mode).await, strict
+    )?;
 
     click_for_close_element.click().await?;
 
     Ok(())
 }
 
-pub async fn set_permissions_for_security_entity(input_id: &str, perm_inputs: &Vec<WebElement>,
-                                                 perm_input_index: usize,
-                                                 permissions: &Vec<String>) -> EmptyResult {
-    debug!("set permissions for security entity: {:?}", permissions);
+/// `EntityNotAppliedError` fails account in strict mode only, other errors are returned as is
+fn check_entity_result(result: EmptyResult, strict: bool) -> EmptyResult {
+    match result {
+        Err(e) if !strict && e.is::<EntityNotAppliedError>() => {
+            warn!("{}", e);
+            Ok(())
+        }
+        result => result
+    }
+}
 
-    if !permissions.is_empty() {
+/// Returns `EntityNotAppliedError` if some names weren't added, i.e. no option matched name.
+pub async fn set_permissions_for_security_entity(field: &str, select_id: &str, permission_panel: &WebElement,
+                                                 wait: &WaitConfig, permissions: &[String],
+                                                 mode: PermissionsMode) -> EmptyResult {
+    debug!("set permissions for security entity: {:?}, mode: {:?}", permissions, mode);
 
-        match perm_inputs.get(perm_input_index) {
-            Some(perms_input) => {
+    if permissions.is_empty() && mode != PermissionsMode::Replace {
+        return Ok(())
+    }
 
-                let input = perms_input.find(By::Id(input_id)).await?;
+    let perms_input = find_selectize_control(permission_panel, select_id, field).await?;
 
-                for permission in permissions {
-                    info!("- add '{}'", permission);
+    let input = perms_input.find(By::Id(&format!("{}-selectized", select_id))).await?;
 
-                    input.send_keys(permission.to_string() + Key::Enter + Key::Escape).await?;
-                    thread::sleep(Duration::from_millis(500));
-                    input.send_keys("" + Key::Escape).await?;
-                }
+    match mode {
+        PermissionsMode::Add => {}
+        PermissionsMode::Replace => remove_selectize_items(&perms_input, &input, None).await?,
+        PermissionsMode::Remove => {
+            return remove_selectize_items(&perms_input, &input, Some(permissions)).await
+        }
+    }
 
-                Ok(())
-            }
-            None => {
-                error!("couldn't get permissions input by index {}", perm_input_index);
-                Err(anyhow!(UNSUPPORTED_UI_VERSION_ERROR))
-            }
+    let mut missing_names: Vec<String> = vec![];
+
+    for permission in permissions {
+        info!("- add '{}'", permission);
+
+        input.send_keys(permission).await?;
+
+        if let Err(e) = wait_for_selectize_options(&perms_input, wait).await {
+            warn!("{}", e);
         }
 
+        input.send_keys("" + Key::Enter + Key::Escape).await?;
 
-    } else {
+        if !has_selectize_item(&perms_input, permission).await? {
+            error!("- '{}' wasn't added", permission);
+            input.clear().await?;
+            missing_names.push(permission.to_string());
+        }
+    }
+
+    if missing_names.is_empty() {
         Ok(())
+
+    } else {
+        Err(EntityNotAppliedError { field: field.to_string(), names: missing_names }.into())
+    }
+}
+
+/// Remove items (tags) from selectize input. All items are removed if `names` is `None`.
+///
+/// Item is selected by click, then removed with backspace.
+async fn remove_selectize_items(perms_input: &WebElement, input: &WebElement,
+                                names: Option<&[String]>) -> EmptyResult {
+    let items = perms_input.find_all(By::Css(".selectize-input .item")).await?;
+
+    for item in items {
+        let name = get_selectize_item_name(&item).await?;
+
+        if names.is_none_or(|names| names.contains(&name)) {
+            info!("- remove '{}'", name);
+            item.click().await?;
+            input.send_keys("" + Key::Backspace).await?;
+        }
     }
 
+    input.send_keys("" + Key::Escape).await?;
+
+    Ok(())
 }
 
-pub async fn set_additional_property_value(element: &WebElement, value: &str) -> EmptyResult {
+/// Select option with `value` text in single-value selectize input (owner, main group).
+///
+/// Returns `EntityNotAppliedError` if option wasn't found or wasn't selected.
+pub async fn set_additional_property_value(element: &WebElement, wait: &WaitConfig,
+                                           field: &str, value: &str) -> EmptyResult {
     info!("set value '{}'", value);
     let input = element.find(By::ClassName("selectize-control")).await?;
     input.click().await?;
 
+    if let Err(e) = wait_for_selectize_options(element, wait).await {
+        warn!("{}", e);
+    }
+
     let options = element.find_all(By::ClassName("option")).await?;
 
     for option in options {
         let text = option.text().await?;
 
-        if &text == value {
-            info!("- set '{}' - success", value);
+        if text.trim() == value {
             option.click().await?;
+
+            if has_selectize_item(element, value).await? {
+                info!("- set '{}' - success", value);
+                return Ok(())
+            }
+
+            break;
         }
     }
 
-    Ok(())
+    error!("- '{}' wasn't selected", value);
+
+    Err(EntityNotAppliedError { field: field.to_string(), names: vec![value.to_string()] }.into())
+}
+
+/// Check if selectize input inside element has selected item (tag) with given name
+async fn has_selectize_item(element: &WebElement, name: &str) -> OperationResult<bool> {
+    let items = element.find_all(By::Css(".selectize-input .item")).await?;
+
+    for item in items {
+        if get_selectize_item_name(&item).await? == name {
+            return Ok(true)
+        }
+    }
+
+    Ok(false)
+}
+
+async fn get_selectize_item_name(item: &WebElement) -> OperationResult<String> {
+    let text = item.text().await?;
+
+    // Remove button plugin adds '×' to item text
+    Ok(text.trim_end_matches('×').trim().to_string())
+}
+
+/// Wait until selectize dropdown inside element has options
+async fn wait_for_selectize_options(element: &WebElement, wait: &WaitConfig) -> EmptyResult {
+    wait_until(wait, "selectize dropdown options", || async {
+        let options = element.find_all(By::Css(".selectize-dropdown .option")).await?;
+        Ok(!options.is_empty())
+    }).await
 }
 
 /// Get permission tags from account view page -> permissions tab.
 ///
-/// Expected element with class `.tag-list-box` as function argument.
-pub async fn get_tags_from_list_box_in_view_mode(element: &WebElement) -> OperationResult<Vec<String>> {
-    let tag_elements = element.find_all(By::ClassName("tag")).await?;
+/// Expected element with `account.tag-list` selector as function argument.
+pub async fn get_tags_from_list_box_in_view_mode(element: &WebElement, selectors: &UiSelectors) -> OperationResult<Vec<String>> {
+    let tag_elements = element.find_all(By::Css(&selectors.account.tag)).await?;
 
     let mut tags: Vec<String> = vec![];
 
@@ -299,3 +809,29 @@ pub async fn get_tags_from_list_box_in_view_mode(element: &WebElement) -> Operat
 
     Ok(tags)
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use crate::syspass::EntityNotAppliedError;
+    use crate::syspass::perms::check_entity_result;
+
+    #[test]
+    fn entity_error_should_fail_account_in_strict_mode_only() {
+        let get_error = || Err(EntityNotAppliedError {
+            field: "user-edit".to_string(),
+            names: vec!["Mr.Edtior".to_string()],
+        }.into());
+
+        assert!(check_entity_result(get_error(), false).is_ok());
+
+        let error = check_entity_result(get_error(), true).unwrap_err();
+        assert_eq!("'Mr.Edtior' wasn't applied for user-edit", error.to_string());
+    }
+
+    #[test]
+    fn other_errors_should_be_returned_in_any_mode() {
+        assert!(check_entity_result(Err(anyhow!("element not interactable")), false).is_err());
+    }
+}
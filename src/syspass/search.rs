@@ -1,40 +1,187 @@
+use anyhow::anyhow;
+use log::{debug, error, info};
 use thirtyfour::{By, WebDriver, WebElement};
 
+use crate::config::{AppConfig, WaitConfig};
+use crate::syspass::{Account, FIELD_NOT_FOUND_ERROR};
+use crate::syspass::selectors::UiSelectors;
+use crate::syspass::wait::{wait_for_element, wait_for_staleness};
 use crate::types::{EmptyResult, OperationResult};
 
-pub async fn get_search_item_category(element: &WebElement) -> OperationResult<String> {
-    let category_element = element.find(By::ClassName("field-category")).await?;
-    let category_text_element = category_element.find(By::ClassName("field-text")).await?;
-    let category_text = category_text_element.text().await?;
-    Ok(category_text.trim().to_string())
+pub async fn get_search_item_category(element: &WebElement, selectors: &UiSelectors) -> OperationResult<String> {
+    get_search_item_field_text(element, &selectors.search.item_category).await
 }
 
-pub async fn get_search_item_client(element: &WebElement) -> OperationResult<String> {
-    let client_element = element.find(By::ClassName("mdl-chip__text")).await?;
-    let client_text = client_element.text().await?;
-    Ok(client_text.trim().to_string())
+pub async fn get_search_item_client(element: &WebElement, selectors: &UiSelectors) -> OperationResult<String> {
+    get_search_item_field_text(element, &selectors.search.item_client).await
 }
 
-pub async fn get_search_item_login(element: &WebElement) -> OperationResult<String> {
-    let user_field = element.find(By::ClassName("field-user")).await?;
-    let username_field = user_field.find(By::ClassName("field-text")).await?;
-    let username = username_field.text().await?;
-    Ok(username.trim().to_string())
+pub async fn get_search_item_login(element: &WebElement, selectors: &UiSelectors) -> OperationResult<String> {
+    get_search_item_field_text(element, &selectors.search.item_login).await
 }
 
-pub async fn get_search_item_name(element: &WebElement) -> OperationResult<String> {
-    let name_element = element.find(By::ClassName("field-account")).await?;
-    let name_text_element = name_element.find(By::ClassName("field-text")).await?;
-    let name_text = name_text_element.text().await?;
-    Ok(name_text.trim().to_string())
+pub async fn get_search_item_name(element: &WebElement, selectors: &UiSelectors) -> OperationResult<String> {
+    get_search_item_field_text(element, &selectors.search.item_name).await
 }
 
-pub async fn clear_search_input(driver: &WebDriver) -> EmptyResult {
-    let input = driver.find(By::Id("btn-reset")).await?;
+async fn get_search_item_field_text(element: &WebElement, selector: &str) -> OperationResult<String> {
+    let field_element = element.find(By::Css(selector)).await?;
+    let field_text = field_element.text().await?;
+    Ok(field_text.trim().to_string())
+}
+
+pub async fn clear_search_input(driver: &WebDriver, selectors: &UiSelectors, wait: &WaitConfig) -> EmptyResult {
+    let previous_first_item = get_first_search_item(driver, selectors).await?;
+    let input = driver.find(By::Css(&selectors.search.reset_button)).await?;
     input.click().await?;
-    Ok(())
+    wait_for_search_results_update(wait, previous_first_item).await
+}
+
+pub async fn get_first_search_item(driver: &WebDriver, selectors: &UiSelectors) -> OperationResult<Option<WebElement>> {
+    let search_items = driver.find_all(By::Css(&selectors.search.item)).await?;
+    Ok(search_items.into_iter().next())
+}
+
+/// Search results are replaced via ajax after search, reset or page switch.
+/// Wait until previous results are removed from page.
+pub async fn wait_for_search_results_update(wait: &WaitConfig,
+                                            previous_first_item: Option<WebElement>) -> EmptyResult {
+    match previous_first_item {
+        Some(item) => wait_for_staleness(&item, wait, "search results update").await,
+        None => Ok(())
+    }
+}
+
+pub async fn next_page_available(driver: &WebDriver, selectors: &UiSelectors) -> bool {
+    driver.find(By::Css(&selectors.search.pager_last)).await.is_ok()
+}
+
+pub async fn get_search_item_account(element: &WebElement, selectors: &UiSelectors) -> OperationResult<Account> {
+    Ok(
+        Account {
+            name: get_search_item_name(element, selectors).await?,
+            login: get_search_item_login(element, selectors).await?,
+            category: get_search_item_category(element, selectors).await?,
+            client: get_search_item_client(element, selectors).await?,
+        }
+    )
+}
+
+/// sysPass account id from search result item, `None` if theme doesn't provide it
+pub async fn get_search_item_id(element: &WebElement, selectors: &UiSelectors) -> OperationResult<Option<u32>> {
+    let id_elements = element.find_all(By::Css(&selectors.search.item_id)).await?;
+
+    match id_elements.first() {
+        Some(id_element) => {
+            let id = id_element.attr("data-item-id").await?;
+            Ok(id.and_then(|id| id.trim().parse::<u32>().ok()))
+        }
+        None => Ok(None)
+    }
+}
+
+/// Set start position of search results in hidden input of search form and submit form,
+/// results are reloaded via ajax.
+const SEARCH_PAGE_SCRIPT: &str = r#"
+    var input = document.querySelector(arguments[0]);
+
+    if (!input || !input.form) {
+        return false;
+    }
+
+    input.value = arguments[1];
+    input.form.dispatchEvent(new Event('submit', { bubbles: true, cancelable: true }));
+
+    return true;
+"#;
+
+/// Open search results page (zero-based) directly, without clicking through previous pages.
+/// Page size is taken from the first page.
+///
+/// Returns accounts with sysPass ids (if available), `None` if page doesn't exist.
+pub async fn get_accounts_from_search_page(config: &AppConfig, selectors: &UiSelectors, driver: &WebDriver,
+                                           page: usize) -> OperationResult<Option<Vec<(Account, Option<u32>)>>> {
+    info!("collect accounts from search results page {}", page + 1);
+
+    let url = format!("{}/index.php?r=index", &config.syspass_url);
+    driver.goto(&url).await?;
+
+    wait_for_element(driver, &config.wait, By::Css(&selectors.search.search_input),
+                     "search input on index page").await?;
+
+    clear_search_input(driver, selectors, &config.wait).await?;
+
+    let mut search_items = driver.find_all(By::Css(&selectors.search.item)).await?;
+
+    if page > 0 {
+        if !next_page_available(driver, selectors).await {
+            return Ok(None)
+        }
+
+        let start = page * search_items.len();
+        let previous_first_item = search_items.into_iter().next();
+
+        let result = driver.execute(SEARCH_PAGE_SCRIPT, vec![
+            serde_json::json!(selectors.search.page_start_input), serde_json::json!(start)
+        ]).await?;
+
+        if !result.convert::<bool>()? {
+            error!("couldn't find search start input '{}'", selectors.search.page_start_input);
+            return Err(anyhow!("{} 'search page start'", FIELD_NOT_FOUND_ERROR))
+        }
+
+        wait_for_search_results_update(&config.wait, previous_first_item).await?;
+
+        search_items = driver.find_all(By::Css(&selectors.search.item)).await?;
+    }
+
+    debug!("search items: {}", search_items.len());
+
+    if search_items.is_empty() {
+        return Ok(None)
+    }
+
+    let mut accounts: Vec<(Account, Option<u32>)> = vec![];
+
+    for search_item in search_items {
+        let account = get_search_item_account(&search_item, selectors).await?;
+        let id = get_search_item_id(&search_item, selectors).await?;
+        accounts.push((account, id));
+    }
+
+    Ok(Some(accounts))
 }
 
-pub async fn next_page_available(driver: &WebDriver) -> bool {
-    driver.find(By::Id("btn-pager-last")).await.is_ok()
+/// Collect accounts from all search results pages
+pub async fn get_accounts_from_search_results(config: &AppConfig, selectors: &UiSelectors,
+                                              driver: &WebDriver) -> OperationResult<Vec<Account>> {
+    info!("collect accounts from search results");
+
+    clear_search_input(driver, selectors, &config.wait).await?;
+
+    let mut accounts: Vec<Account> = vec![];
+
+    loop {
+        let search_items = driver.find_all(By::Css(&selectors.search.item)).await?;
+        debug!("search items: {}", search_items.len());
+
+        for search_item in search_items {
+            accounts.push(get_search_item_account(&search_item, selectors).await?);
+        }
+
+        if !next_page_available(driver, selectors).await {
+            break;
+        }
+
+        info!("go to next search results page..");
+        let previous_first_item = get_first_search_item(driver, selectors).await?;
+        let next_page_button = driver.find(By::Css(&selectors.search.pager_next)).await?;
+        next_page_button.scroll_into_view().await?;
+        next_page_button.click().await?;
+        wait_for_search_results_update(&config.wait, previous_first_item).await?;
+    }
+
+    info!("accounts found: {}", accounts.len());
+
+    Ok(accounts)
 }
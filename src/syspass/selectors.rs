@@ -0,0 +1,187 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::anyhow;
+use log::info;
+use serde::Deserialize;
+
+use crate::config::UiConfig;
+use crate::types::OperationResult;
+
+pub const SYSPASS_31_VERSION: &str = "3.1";
+pub const SYSPASS_32_VERSION: &str = "3.2";
+
+/// Detect sysPass version after login and pick built-in profile for it
+pub const AUTO_VERSION: &str = "auto";
+
+/// Default profile, sysPass 3.1 and 3.2 share the same markup
+const SYSPASS_3_PROFILE: &str = include_str!("../../profiles/syspass-3.yml");
+
+/// Built-in profiles: (sysPass version, profile content)
+const BUILT_IN_PROFILES: [(&str, &str); 2] = [
+    (SYSPASS_31_VERSION, SYSPASS_3_PROFILE),
+    (SYSPASS_32_VERSION, SYSPASS_3_PROFILE),
+];
+
+/// Selectors for sysPass web ui elements, they differ between sysPass versions and themes.
+///
+/// Selectize internals (`.selectize-input .item`, `.selectize-dropdown .option`) belong to
+/// the library and aren't included.
+#[derive(Deserialize,PartialEq,Debug,Clone)]
+pub struct UiSelectors {
+    pub login: LoginSelectors,
+    pub search: SearchSelectors,
+    pub account: AccountSelectors,
+}
+
+#[derive(Deserialize,PartialEq,Debug,Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct LoginSelectors {
+    pub user_input: String,
+    pub password_input: String,
+    pub login_button: String,
+    pub login_form: String,
+
+    /// Element which is shown after successful login
+    pub logged_in: String,
+
+    /// Element with sysPass version, i.e. footer
+    pub version_info: String,
+}
+
+#[derive(Deserialize,PartialEq,Debug,Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct SearchSelectors {
+    pub search_input: String,
+    pub reset_button: String,
+
+    /// Search result item, item fields are searched inside it
+    pub item: String,
+    pub item_name: String,
+    pub item_login: String,
+    pub item_category: String,
+    pub item_client: String,
+
+    /// Element with `data-item-id` attribute (sysPass account id) inside search result item
+    pub item_id: String,
+
+    pub actions_button: String,
+    pub actions_menu: String,
+    pub view_button: String,
+    pub edit_button: String,
+
+    pub pager_last: String,
+    pub pager_next: String,
+
+    /// Hidden input of search form with start position of results page
+    pub page_start_input: String,
+}
+
+/// Account view and edit pages. `*_select` and `*_checkbox` values are element ids.
+#[derive(Deserialize,PartialEq,Debug,Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct AccountSelectors {
+    pub edit_form: String,
+    pub tabs: String,
+    pub permissions_tab: String,
+    pub permission_panel: String,
+    pub save_button: String,
+
+    /// Notifications shown after ajax save of account form
+    pub save_success: String,
+    pub save_error: String,
+
+    pub tag_list: String,
+    pub tag: String,
+
+    /// Texts of the first cell of permission rows on account view page
+    pub users_row_label: String,
+    pub groups_row_label: String,
+    pub owner_row_label: String,
+    pub main_group_row_label: String,
+
+    pub switch: String,
+    pub checked_switch: String,
+
+    pub user_view_select: String,
+    pub user_edit_select: String,
+    pub group_view_select: String,
+    pub group_edit_select: String,
+    pub owner_select: String,
+    pub main_group_select: String,
+    pub private_account_checkbox: String,
+    pub private_account_for_group_checkbox: String,
+}
+
+/// Custom profile from `ui.selectors-file` has priority over built-in profile for `ui.version`.
+///
+/// Profile of the latest supported version is used for login if version is `auto`,
+/// it's replaced after version detection (see `WebDriverBackend::login`).
+pub fn get_ui_selectors(ui_config: &UiConfig) -> OperationResult<UiSelectors> {
+    match &ui_config.selectors_file {
+        Some(selectors_file) => load_ui_selectors_from_file(Path::new(selectors_file)),
+        None if is_auto_version(ui_config) => get_built_in_ui_selectors(SYSPASS_32_VERSION),
+        None => get_built_in_ui_selectors(&ui_config.version)
+    }
+}
+
+/// Version should be detected if it isn't set explicitly and custom profile isn't used
+pub fn is_auto_version(ui_config: &UiConfig) -> bool {
+    ui_config.selectors_file.is_none() && ui_config.version == AUTO_VERSION
+}
+
+pub fn get_built_in_ui_selectors(version: &str) -> OperationResult<UiSelectors> {
+    info!("use built-in ui selectors for syspass {}", version);
+
+    match BUILT_IN_PROFILES.iter().find(|(profile_version, _)| *profile_version == version) {
+        Some((_, content)) => Ok(serde_yaml::from_str(content)?),
+        None => Err(anyhow!("unsupported syspass version '{}', supported: {}", version,
+                            get_built_in_versions().join(", ")))
+    }
+}
+
+pub fn get_built_in_versions() -> Vec<&'static str> {
+    BUILT_IN_PROFILES.iter().map(|(version, _)| *version).collect()
+}
+
+pub fn load_ui_selectors_from_file(file_path: &Path) -> OperationResult<UiSelectors> {
+    info!("load ui selectors from file '{}'", file_path.display());
+    let content = fs::read_to_string(file_path)?;
+    let selectors = serde_yaml::from_str(&content)?;
+    Ok(selectors)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::config::UiConfig;
+    use crate::syspass::selectors::{get_built_in_ui_selectors, get_built_in_versions, get_ui_selectors};
+
+    #[test]
+    fn built_in_profiles_should_be_valid() {
+        for version in get_built_in_versions() {
+            let selectors = get_built_in_ui_selectors(version).unwrap();
+            assert_eq!(".account-label", selectors.search.item);
+            assert_eq!("other_users_view", selectors.account.user_view_select);
+        }
+    }
+
+    #[test]
+    fn return_error_for_unknown_version() {
+        assert!(get_built_in_ui_selectors("2.1").is_err());
+    }
+
+    #[test]
+    fn custom_profile_should_have_priority() {
+        let ui_config = UiConfig {
+            version: "unknown".to_string(),
+            selectors_file: Some(Path::new("test-data").join("selectors.yml").display().to_string()),
+        };
+
+        let selectors = get_ui_selectors(&ui_config).unwrap();
+
+        assert_eq!(".patched-account-card", selectors.search.item);
+        assert_eq!("#btnLogin", selectors.login.login_button);
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::LazyLock;
+
+use anyhow::anyhow;
+use log::info;
+use regex::Regex;
+use thirtyfour::WebDriver;
+
+use crate::syspass::selectors::{get_built_in_versions, UiSelectors};
+use crate::types::OperationResult;
+
+/// Texts of version elements, `content` attribute is used for `meta` tags
+const VERSION_INFO_SCRIPT: &str = r#"
+    var texts = [];
+
+    document.querySelectorAll(arguments[0]).forEach(function(element) {
+        texts.push(element.getAttribute('content') || element.textContent || '');
+    });
+
+    return texts;
+"#;
+
+/// Read sysPass version from page (footer or meta tag), i.e. `3.1.2.20030701`
+pub async fn detect_syspass_version(driver: &WebDriver, selectors: &UiSelectors) -> OperationResult<String> {
+    let result = driver.execute(VERSION_INFO_SCRIPT,
+                                vec![serde_json::json!(selectors.login.version_info)]).await?;
+
+    let texts: Vec<String> = result.convert()?;
+
+    match texts.iter().find_map(|text| parse_syspass_version(text)) {
+        Some(version) => {
+            info!("syspass version: {}", version);
+            Ok(version)
+        }
+        None => Err(anyhow!("couldn't detect syspass version, version element '{}' wasn't found, \
+                             set `ui.version` in config", selectors.login.version_info))
+    }
+}
+
+static VERSION_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(\d+\.\d+(\.\d+)*)").expect("invalid version pattern")
+});
+
+/// Find version in text like `sysPass v3.1.2.20030701`
+pub fn parse_syspass_version(text: &str) -> Option<String> {
+    VERSION_PATTERN.captures(text).map(|captures| captures[1].to_string())
+}
+
+/// Returns supported version (`major.minor`) for detected one
+pub fn get_supported_version(version: &str) -> OperationResult<&'static str> {
+    let supported_versions = get_built_in_versions();
+
+    let mut parts = version.split('.');
+
+    let major_minor = match (parts.next(), parts.next()) {
+        (Some(major), Some(minor)) => format!("{}.{}", major, minor),
+        _ => version.to_string()
+    };
+
+    match supported_versions.iter().find(|supported_version| **supported_version == major_minor) {
+        Some(supported_version) => Ok(supported_version),
+        None => Err(anyhow!("unsupported syspass version '{}', supported: {}", version,
+                            supported_versions.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syspass::version::{get_supported_version, parse_syspass_version};
+
+    #[test]
+    fn version_should_be_parsed_from_footer_text() {
+        assert_eq!(Some("3.1.2.20030701".to_string()), parse_syspass_version("sysPass  v3.1.2.20030701  :: cygnux.org"));
+        assert_eq!(Some("3.2.11".to_string()), parse_syspass_version("3.2.11"));
+        assert_eq!(None, parse_syspass_version("sysPass :: 2012-2019"));
+    }
+
+    #[test]
+    fn detected_version_should_match_supported_major_minor() {
+        assert_eq!("3.1", get_supported_version("3.1.2.20030701").unwrap());
+        assert_eq!("3.2", get_supported_version("3.2.11").unwrap());
+    }
+
+    #[test]
+    fn return_error_for_unsupported_version() {
+        let error = get_supported_version("2.1.16").unwrap_err();
+        assert_eq!("unsupported syspass version '2.1.16', supported: 3.1, 3.2", error.to_string());
+
+        assert!(get_supported_version("3.10.0").is_err());
+    }
+}
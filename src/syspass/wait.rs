@@ -0,0 +1,129 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use log::{debug, trace};
+use thirtyfour::{By, WebDriver, WebElement};
+
+use crate::config::WaitConfig;
+use crate::types::{EmptyResult, OperationResult};
+
+/// Poll `condition` until it returns value or timeout expires.
+///
+/// Condition errors (i.e. element wasn't found yet) are treated as "not ready".
+pub async fn wait_for<T, F, Fut>(wait: &WaitConfig, description: &str,
+                                 mut condition: F) -> OperationResult<T>
+    where F: FnMut() -> Fut, Fut: Future<Output = OperationResult<Option<T>>> {
+    trace!("wait for {}", description);
+
+    let timeout = Duration::from_millis(wait.timeout);
+    let poll_interval = Duration::from_millis(wait.poll_interval);
+    let started = Instant::now();
+
+    loop {
+        match condition().await {
+            Ok(Some(value)) => {
+                trace!("'{}' - ready in {} ms", description, started.elapsed().as_millis());
+                return Ok(value)
+            }
+            Ok(None) => {}
+            Err(e) => trace!("'{}' - not ready: {}", description, e)
+        }
+
+        if started.elapsed() >= timeout {
+            debug!("timeout while waiting for {}", description);
+            return Err(anyhow!("timeout ({} ms) while waiting for {}", wait.timeout, description))
+        }
+
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+/// Poll `condition` until it returns `true` or timeout expires
+pub async fn wait_until<F, Fut>(wait: &WaitConfig, description: &str, mut condition: F) -> EmptyResult
+    where F: FnMut() -> Fut, Fut: Future<Output = OperationResult<bool>> {
+    wait_for(wait, description, || {
+        let ready = condition();
+        async move { Ok(ready.await?.then_some(())) }
+    }).await
+}
+
+/// Wait for displayed element
+pub async fn wait_for_element(driver: &WebDriver, wait: &WaitConfig, by: By,
+                              description: &str) -> OperationResult<WebElement> {
+    wait_for(wait, description, || {
+        let by = by.clone();
+        async move {
+            let elements = driver.find_all(by).await?;
+
+            for element in elements {
+                if element.is_displayed().await? {
+                    return Ok(Some(element))
+                }
+            }
+
+            Ok(None)
+        }
+    }).await
+}
+
+/// Wait until element is removed from page, i.e. after ajax content reload
+pub async fn wait_for_staleness(element: &WebElement, wait: &WaitConfig, description: &str) -> EmptyResult {
+    wait_until(wait, description, || async {
+        Ok(!element.is_present().await?)
+    }).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use anyhow::anyhow;
+
+    use crate::config::WaitConfig;
+    use crate::syspass::wait::{wait_for, wait_until};
+
+    #[tokio::test]
+    async fn return_value_when_condition_is_met() {
+        let attempts = Cell::new(0);
+
+        let value = wait_for(&get_wait_config(), "value", || {
+            attempts.set(attempts.get() + 1);
+            let attempt = attempts.get();
+            async move { Ok((attempt == 3).then_some(attempt)) }
+        }).await.unwrap();
+
+        assert_eq!(3, value);
+    }
+
+    #[tokio::test]
+    async fn condition_errors_should_be_treated_as_not_ready() {
+        let attempts = Cell::new(0);
+
+        wait_until(&get_wait_config(), "no errors", || {
+            attempts.set(attempts.get() + 1);
+            let attempt = attempts.get();
+            async move {
+                if attempt < 2 {
+                    Err(anyhow!("element wasn't found"))
+                } else {
+                    Ok(true)
+                }
+            }
+        }).await.unwrap();
+
+        assert_eq!(2, attempts.get());
+    }
+
+    #[tokio::test]
+    async fn return_error_on_timeout() {
+        assert!(wait_until(&get_wait_config(), "never", || async { Ok(false) }).await.is_err());
+    }
+
+    fn get_wait_config() -> WaitConfig {
+        WaitConfig {
+            timeout: 50,
+            poll_interval: 5,
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use log::{info, warn};
+use thirtyfour::{By, DesiredCapabilities, WebDriver};
+
+use crate::config::{AppConfig, PermissionsConfig};
+use crate::syspass::{Account, AccountPermissions, PermissionsCorrection, SecurityEntities};
+use crate::syspass::backend::SyspassBackend;
+use crate::syspass::login::{login_to_syspass, relogin_if_required};
+use crate::syspass::perms::{correct_permissions_for_account, correct_permissions_for_account_by_id, get_permissions_for_account, get_permissions_for_account_by_id, get_security_entities_for_account, set_permissions_for_account};
+use crate::syspass::search::{get_accounts_from_search_page, get_accounts_from_search_results};
+use crate::syspass::selectors::{get_built_in_ui_selectors, get_ui_selectors, is_auto_version, UiSelectors};
+use crate::syspass::version::{detect_syspass_version, get_supported_version};
+use crate::syspass::wait::wait_for_element;
+use crate::types::{EmptyResult, OperationResult};
+
+/// sysPass web ui automation with chrome webdriver
+pub struct WebDriverBackend<'a> {
+    config: &'a AppConfig,
+    pub driver: WebDriver,
+    pub selectors: UiSelectors,
+
+    /// Account ids from search results pages, accounts are opened by id without search
+    account_ids: HashMap<Account, u32>,
+}
+
+impl<'a> WebDriverBackend<'a> {
+    pub async fn new(config: &'a AppConfig) -> OperationResult<WebDriverBackend<'a>> {
+        let selectors = get_ui_selectors(&config.ui)?;
+        let driver = create_webdriver(config).await?;
+        Ok(WebDriverBackend { config, driver, selectors, account_ids: HashMap::new() })
+    }
+}
+
+#[async_trait]
+impl SyspassBackend for WebDriverBackend<'_> {
+    async fn login(&mut self) -> EmptyResult {
+        login_to_syspass(&self.driver, &self.selectors, &self.config.syspass_url,
+                         &self.config.auth.login, &self.config.auth.password).await?;
+
+        if is_auto_version(&self.config.ui) {
+            let version = detect_syspass_version(&self.driver, &self.selectors).await?;
+            self.selectors = get_built_in_ui_selectors(get_supported_version(&version)?)?;
+        }
+
+        wait_for_element(&self.driver, &self.config.wait, By::Css(&self.selectors.search.search_input),
+                         "index page after login").await?;
+
+        Ok(())
+    }
+
+    async fn search_accounts(&mut self) -> OperationResult<Vec<Account>> {
+        get_accounts_from_search_results(self.config, &self.selectors, &self.driver).await
+    }
+
+    async fn search_accounts_page(&mut self, page: usize) -> OperationResult<Option<Vec<Account>>> {
+        relogin_if_required(&self.driver, self.config, &self.selectors).await?;
+
+        let page_accounts = get_accounts_from_search_page(self.config, &self.selectors, &self.driver, page).await?;
+
+        Ok(page_accounts.map(|page_accounts| {
+            page_accounts.into_iter().map(|(account, id)| {
+                if let Some(id) = id {
+                    self.account_ids.insert(account.clone(), id);
+                }
+                account
+            }).collect()
+        }))
+    }
+
+    async fn read_permissions(&mut self, account: &Account) -> OperationResult<AccountPermissions> {
+        relogin_if_required(&self.driver, self.config, &self.selectors).await?;
+
+        match self.account_ids.get(account) {
+            Some(id) => get_permissions_for_account_by_id(self.config, &self.selectors, &self.driver, *id).await,
+            None => get_permissions_for_account(self.config, &self.selectors, &self.driver, account).await
+        }
+    }
+
+    async fn write_permissions(&mut self, account: &Account, permissions: &PermissionsConfig) -> EmptyResult {
+        relogin_if_required(&self.driver, self.config, &self.selectors).await?;
+        set_permissions_for_account(self.config, &self.selectors, &self.driver, account, permissions).await
+    }
+
+    async fn correct_permissions(&mut self, account: &Account, correction: &PermissionsCorrection) -> EmptyResult {
+        relogin_if_required(&self.driver, self.config, &self.selectors).await?;
+
+        match self.account_ids.get(account) {
+            Some(id) => correct_permissions_for_account_by_id(self.config, &self.selectors, &self.driver,
+                                                              *id, correction).await,
+            None => correct_permissions_for_account(self.config, &self.selectors, &self.driver,
+                                                    account, correction).await
+        }
+    }
+
+    async fn read_security_entities(&mut self, account: &Account) -> OperationResult<SecurityEntities> {
+        relogin_if_required(&self.driver, self.config, &self.selectors).await?;
+        get_security_entities_for_account(self.config, &self.selectors, &self.driver, account).await
+    }
+
+    async fn restart(&mut self) -> EmptyResult {
+        info!("restart webdriver session");
+
+        let driver = create_webdriver(self.config).await?;
+        let previous_driver = std::mem::replace(&mut self.driver, driver);
+
+        if let Err(e) = previous_driver.quit().await {
+            warn!("couldn't close previous webdriver session: {}", e);
+        }
+
+        self.login().await
+    }
+}
+
+/// Errors after which webdriver session can't be used anymore
+const FATAL_SESSION_ERRORS: [&str; 4] = [
+    "session deleted because of page crash",
+    "invalid session id",
+    "chrome not reachable",
+    "disconnected: not connected to devtools",
+];
+
+/// Check if webdriver session should be restarted after error
+pub fn is_fatal_session_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        let message = cause.to_string().to_lowercase();
+        FATAL_SESSION_ERRORS.iter().any(|fatal_error| message.contains(fatal_error))
+    })
+}
+
+pub async fn create_webdriver(config: &AppConfig) -> OperationResult<WebDriver> {
+    info!("connect to webdriver '{}'", config.webdriver.url);
+
+    let mut caps = DesiredCapabilities::chrome();
+
+    for arg in config.webdriver.args.iter() {
+        caps.add_chrome_arg(arg)?;
+    }
+
+    let driver = WebDriver::new(&config.webdriver.url, caps).await?;
+
+    Ok(driver)
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+
+use crate::config::PermissionsConfig;
+use crate::syspass::{Account, AccountPermissions, PermissionsCorrection, SecurityEntities};
+use crate::syspass::backend::SyspassBackend;
+use crate::types::{EmptyResult, OperationResult};
+
+/// In-memory sysPass instance
+#[derive(Default)]
+pub struct FakeSyspassBackend {
+    pub accounts: Vec<Account>,
+    pub permissions: HashMap<Account, AccountPermissions>,
+
+    /// Users and groups available for permissions
+    pub security_entities: SecurityEntities,
+
+    /// Operations for these accounts will fail
+    pub failing_logins: Vec<String>,
+
+    /// Writes for these accounts are "saved" without changes
+    pub ignored_writes: Vec<String>,
+
+    /// Next N operations will fail with page crash error
+    pub session_crashes: u16,
+    pub restarts: u16,
+
+    /// Accounts per search results page
+    pub page_size: usize,
+    pub searched_pages: Vec<usize>,
+
+    pub logged_in: bool,
+    pub read: Vec<Account>,
+    pub written: Vec<Account>,
+
+    /// Corrections applied with `correct_permissions`, accounts are added to `written` too
+    pub corrections: Vec<PermissionsCorrection>,
+}
+
+impl FakeSyspassBackend {
+    pub fn new(accounts: Vec<Account>) -> FakeSyspassBackend {
+        FakeSyspassBackend {
+            accounts,
+            security_entities: get_test_security_entities(),
+            page_size: 2,
+            ..FakeSyspassBackend::default()
+        }
+    }
+
+    async fn check_account(&mut self, account: &Account) -> EmptyResult {
+        // Simulate i/o so other workers could proceed
+        tokio::task::yield_now().await;
+
+        if !self.logged_in {
+            return Err(anyhow!("login required"))
+        }
+
+        if self.session_crashes > 0 {
+            self.session_crashes -= 1;
+            self.logged_in = false;
+            return Err(anyhow!("unknown error: session deleted because of page crash"))
+        }
+
+        if self.failing_logins.contains(&account.login) || !self.accounts.contains(account) {
+            return Err(anyhow!("account wasn't found '{}'", account.login))
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SyspassBackend for FakeSyspassBackend {
+    async fn login(&mut self) -> EmptyResult {
+        self.logged_in = true;
+        Ok(())
+    }
+
+    async fn search_accounts(&mut self) -> OperationResult<Vec<Account>> {
+        Ok(self.accounts.clone())
+    }
+
+    async fn search_accounts_page(&mut self, page: usize) -> OperationResult<Option<Vec<Account>>> {
+        tokio::task::yield_now().await;
+
+        if !self.logged_in {
+            return Err(anyhow!("login required"))
+        }
+
+        self.searched_pages.push(page);
+        Ok(self.accounts.chunks(self.page_size).nth(page).map(|page_accounts| page_accounts.to_vec()))
+    }
+
+    async fn read_permissions(&mut self, account: &Account) -> OperationResult<AccountPermissions> {
+        self.check_account(account).await?;
+        self.read.push(account.clone());
+        Ok(self.permissions.get(account).cloned().unwrap_or_default())
+    }
+
+    async fn write_permissions(&mut self, account: &Account, permissions: &PermissionsConfig) -> EmptyResult {
+        self.check_account(account).await?;
+
+        if self.ignored_writes.contains(&account.login) {
+            return Ok(())
+        }
+
+        let account_permissions = self.permissions.get(account).cloned()
+            .unwrap_or_default().with_config(permissions);
+
+        self.permissions.insert(account.clone(), account_permissions);
+        self.written.push(account.clone());
+
+        Ok(())
+    }
+
+    async fn correct_permissions(&mut self, account: &Account, correction: &PermissionsCorrection) -> EmptyResult {
+        self.check_account(account).await?;
+
+        if self.ignored_writes.contains(&account.login) {
+            return Ok(())
+        }
+
+        let account_permissions = self.permissions.get(account).cloned()
+            .unwrap_or_default().with_correction(correction);
+
+        self.permissions.insert(account.clone(), account_permissions);
+        self.written.push(account.clone());
+        self.corrections.push(correction.clone());
+
+        Ok(())
+    }
+
+    async fn read_security_entities(&mut self, account: &Account) -> OperationResult<SecurityEntities> {
+        self.check_account(account).await?;
+        Ok(self.security_entities.clone())
+    }
+
+    async fn restart(&mut self) -> EmptyResult {
+        self.restarts += 1;
+        self.login().await
+    }
+}
+
+/// Users and groups from `test-data/spt.yml` and tests
+pub fn get_test_security_entities() -> SecurityEntities {
+    let get_names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+
+    SecurityEntities {
+        users: get_names(&["sysPass Admin", "Mr.Editor", "j.doe", "m.smith"]),
+        groups: get_names(&["Admins", "Beta Group", "Demo group 1", "Old Team"]),
+    }
+}
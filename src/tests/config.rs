@@ -0,0 +1,13 @@
+use std::path::Path;
+
+use crate::config::{AppConfig, load_config_from_file};
+use crate::CONFIG_FILE;
+
+/// Config from `test-data/spt.yml` without sysPass API
+pub fn get_test_config() -> AppConfig {
+    let config_file = Path::new("test-data").join(CONFIG_FILE);
+    let mut config = load_config_from_file(config_file.as_path()).unwrap();
+    config.api_url = None;
+    config.api_token = None;
+    config
+}
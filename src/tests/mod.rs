@@ -2,6 +2,10 @@ use fake::{Fake, Faker};
 use log::LevelFilter;
 
 pub mod account;
+pub mod backend;
+pub mod config;
+pub mod rpc;
+pub mod xml;
 
 pub fn init_logging() {
     let _ = env_logger::builder().filter_level(LevelFilter::Debug)
@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Handler receives method name and params, returns result value or error (code, message)
+pub type RpcHandler = dyn Fn(&str, &Value) -> Result<Value, (i32, String)> + Send + Sync;
+
+/// Start local sysPass JSON-RPC mock server. Returns `api.php` url.
+pub async fn start_mock_rpc_server<F>(handler: F) -> String
+    where F: Fn(&str, &Value) -> Result<Value, (i32, String)> + Send + Sync + 'static {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let handler: Arc<RpcHandler> = Arc::new(handler);
+
+    tokio::spawn(async move {
+        loop {
+            if let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                tokio::spawn(async move { handle_connection(stream, handler).await });
+            }
+        }
+    });
+
+    format!("http://{}/api.php", address)
+}
+
+async fn handle_connection(mut stream: TcpStream, handler: Arc<RpcHandler>) {
+    let mut data: Vec<u8> = vec![];
+    let mut buf = [0; 4096];
+
+    let body = loop {
+        let read = stream.read(&mut buf).await.unwrap();
+
+        if read == 0 {
+            return;
+        }
+
+        data.extend_from_slice(&buf[..read]);
+
+        let content = String::from_utf8_lossy(&data).to_string();
+
+        if let Some(headers_end) = content.find("\r\n\r\n") {
+            let content_length = content[..headers_end].lines()
+                .find_map(|line| {
+                    let line = line.to_lowercase();
+                    line.strip_prefix("content-length:").map(|value| value.trim().parse::<usize>().unwrap())
+                })
+                .unwrap_or(0);
+
+            let body_start = headers_end + 4;
+
+            if data.len() >= body_start + content_length {
+                break data[body_start..body_start + content_length].to_vec();
+            }
+        }
+    };
+
+    let request: Value = serde_json::from_slice(&body).unwrap();
+    let method = request["method"].as_str().unwrap_or_default();
+
+    let response = match handler(method, &request["params"]) {
+        Ok(result) => json!({
+            "jsonrpc": "2.0",
+            "result": {
+                "itemId": 0,
+                "result": result,
+                "resultCode": 0,
+                "resultMessage": null,
+                "count": null
+            },
+            "id": request["id"]
+        }),
+        Err((code, message)) => json!({
+            "jsonrpc": "2.0",
+            "error": {
+                "message": message,
+                "code": code,
+                "data": null
+            },
+            "id": request["id"]
+        })
+    };
+
+    let response_body = response.to_string();
+
+    let http_response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response_body.len(), response_body
+    );
+
+    let _ = stream.write_all(http_response.as_bytes()).await;
+}
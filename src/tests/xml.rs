@@ -0,0 +1,70 @@
+use crate::syspass::Account;
+use crate::xml::{MetaProperty, XmlAccount, XmlConfig};
+
+/// Accounts from `get_test_xml_config()` with resolved client and category
+pub fn get_xml_accounts() -> Vec<Account> {
+    vec![
+        get_account("i.petrov", "Ivan Petrov", "CLI", "BirchStore"),
+        get_account("n.abramova", "Abramova Nina", "APP", "KalinkaStore"),
+        get_account("p.sidorov", "Petr Sidorov", "APP", "BirchStore"),
+    ]
+}
+
+fn get_account(login: &str, name: &str, category: &str, client: &str) -> Account {
+    Account {
+        name: name.to_string(),
+        login: login.to_string(),
+        category: category.to_string(),
+        client: client.to_string(),
+    }
+}
+
+/// Xml config with three accounts, `SSO` tag is set for the first and the last ones
+pub fn get_test_xml_config() -> XmlConfig {
+    XmlConfig {
+        categories: vec![
+            MetaProperty { id: 1, name: "APP".to_string() },
+            MetaProperty { id: 2, name: "CLI".to_string() },
+        ],
+        clients: vec![
+            MetaProperty { id: 1, name: "BirchStore".to_string() },
+            MetaProperty { id: 2, name: "KalinkaStore".to_string() },
+        ],
+        tags: vec![
+            MetaProperty { id: 1, name: "Shop".to_string() },
+            MetaProperty { id: 2, name: "SSO".to_string() },
+        ],
+        accounts: vec![
+            XmlAccount {
+                id: 1,
+                name: "Ivan Petrov".to_string(),
+                client_id: 1,
+                category_id: 2,
+                login: "i.petrov".to_string(),
+                url: "https://app.company.ru".to_string(),
+                notes: String::new(),
+                tags: vec!["SSO".to_string()],
+            },
+            XmlAccount {
+                id: 2,
+                name: "Abramova Nina".to_string(),
+                client_id: 2,
+                category_id: 1,
+                login: "n.abramova".to_string(),
+                url: "https://shop.company.ru".to_string(),
+                notes: String::new(),
+                tags: vec!["Shop".to_string()],
+            },
+            XmlAccount {
+                id: 3,
+                name: "Petr Sidorov".to_string(),
+                client_id: 1,
+                category_id: 1,
+                login: "p.sidorov".to_string(),
+                url: "".to_string(),
+                notes: String::new(),
+                tags: vec!["Shop".to_string(), "SSO".to_string()],
+            },
+        ],
+    }
+}
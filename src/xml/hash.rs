@@ -0,0 +1,295 @@
+use std::borrow::Cow;
+use std::io::Write;
+
+use anyhow::anyhow;
+use quick_xml::escape::unescape;
+use quick_xml::events::BytesStart;
+
+use crate::types::{EmptyResult, OperationResult};
+
+/// Open element, start tag is closed with `>` or `/>` when the first child or close tag is read
+struct OpenElement {
+    name: Vec<u8>,
+    has_children: bool,
+    first_child_is_text: bool,
+    last_child_is_text: bool,
+
+    /// Text starting with whitespace has been kept, libxml2 keeps any whitespace in element after it
+    keep_whitespace: bool,
+}
+
+/// Writes elements the same way as sysPass calculates export hash: `DOMDocument::saveXML($node)`
+/// for document loaded with `preserveWhiteSpace = false` (libxml2 serialization without formatting).
+///
+/// - whitespace-only text between elements is dropped (libxml2 "blanks" heuristic),
+///   whitespace-only content of element without children is kept
+/// - elements without children are written as `<name/>`
+/// - line endings and whitespace in attribute values are normalized, entities are re-escaped
+/// - CDATA sections, comments and processing instructions are written as is
+pub struct XmlNodeWriter<W: Write> {
+    output: W,
+    elements: Vec<OpenElement>,
+
+    /// Whitespace-only text of element without children, it's kept if element is closed right after it
+    pending_whitespace: Option<String>,
+}
+
+impl<W: Write> XmlNodeWriter<W> {
+    pub fn new(output: W) -> XmlNodeWriter<W> {
+        XmlNodeWriter { output, elements: vec![], pending_whitespace: None }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    pub fn write_start(&mut self, element: &BytesStart, is_empty: bool) -> EmptyResult {
+        self.open_child(false)?;
+
+        self.output.write_all(b"<")?;
+        self.output.write_all(element.name().as_ref())?;
+
+        let mut attributes = vec![];
+
+        for attribute in element.attributes() {
+            let attribute = attribute.map_err(|e| anyhow!("invalid attributes: {}", e))?;
+            attributes.push((attribute.key.as_ref().to_vec(), attribute.value.to_vec()));
+        }
+
+        // namespace declarations are written before attributes
+        attributes.sort_by_key(|(key, _)| !(key == b"xmlns" || key.starts_with(b"xmlns:")));
+
+        for (key, value) in attributes {
+            self.output.write_all(b" ")?;
+            self.output.write_all(&key)?;
+            self.output.write_all(b"=\"")?;
+            self.output.write_all(escape_attribute(&get_attribute_value(&value)?).as_bytes())?;
+            self.output.write_all(b"\"")?;
+        }
+
+        if is_empty {
+            self.output.write_all(b"/>")?;
+
+        } else {
+            self.elements.push(OpenElement {
+                name: element.name().as_ref().to_vec(),
+                has_children: false,
+                first_child_is_text: false,
+                last_child_is_text: false,
+                keep_whitespace: false,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn write_end(&mut self) -> EmptyResult {
+        if let Some(whitespace) = self.pending_whitespace.take() {
+            self.write_text_child(&whitespace)?;
+        }
+
+        let element = self.elements.pop().ok_or_else(|| anyhow!("unexpected close tag"))?;
+
+        if element.has_children {
+            self.output.write_all(b"</")?;
+            self.output.write_all(&element.name)?;
+            self.output.write_all(b">")?;
+
+        } else {
+            self.output.write_all(b"/>")?;
+        }
+
+        Ok(())
+    }
+
+    /// Raw (escaped) text
+    pub fn write_text(&mut self, raw: &[u8]) -> EmptyResult {
+        let text = normalize_line_endings(std::str::from_utf8(raw)?);
+
+        if text.is_empty() {
+            return Ok(())
+        }
+
+        if text.bytes().all(|byte| byte.is_ascii_whitespace()) {
+            return match self.elements.last() {
+                Some(element) if element.keep_whitespace || element.first_child_is_text
+                    || element.last_child_is_text => self.write_text_child(&text),
+                Some(element) if !element.has_children => {
+                    self.pending_whitespace = Some(text.into_owned());
+                    Ok(())
+                }
+                Some(_) => Ok(()),
+                None => self.write_text_child(&text)
+            }
+        }
+
+        let text = unescape(&text).map_err(|e| anyhow!("invalid text: {}", e))?;
+
+        self.write_text_child(&escape_text(&text))
+    }
+
+    pub fn write_cdata(&mut self, raw: &[u8]) -> EmptyResult {
+        self.write_node(b"<![CDATA[", raw, b"]]>")
+    }
+
+    pub fn write_comment(&mut self, raw: &[u8]) -> EmptyResult {
+        self.write_node(b"<!--", raw, b"-->")
+    }
+
+    /// Target and data are separated with single space
+    pub fn write_processing_instruction(&mut self, raw: &[u8]) -> EmptyResult {
+        let content = std::str::from_utf8(raw)?;
+
+        let content = match content.split_once(|char: char| char.is_ascii_whitespace()) {
+            Some((target, data)) if !data.trim_start().is_empty() => format!("{} {}", target, data.trim_start()),
+            Some((target, _)) => target.to_string(),
+            None => content.to_string()
+        };
+
+        self.write_node(b"<?", content.as_bytes(), b"?>")
+    }
+
+    fn write_node(&mut self, prefix: &[u8], raw: &[u8], suffix: &[u8]) -> EmptyResult {
+        self.open_child(false)?;
+
+        self.output.write_all(prefix)?;
+        self.output.write_all(normalize_line_endings(std::str::from_utf8(raw)?).as_bytes())?;
+        self.output.write_all(suffix)?;
+
+        Ok(())
+    }
+
+    fn write_text_child(&mut self, escaped_text: &str) -> EmptyResult {
+        self.open_child(true)?;
+        self.output.write_all(escaped_text.as_bytes())?;
+
+        if escaped_text.starts_with(|char: char| char.is_ascii_whitespace()) {
+            if let Some(element) = self.elements.last_mut() {
+                element.keep_whitespace = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pending whitespace is dropped if any node except close tag follows it
+    fn open_child(&mut self, is_text: bool) -> EmptyResult {
+        self.pending_whitespace = None;
+
+        if let Some(element) = self.elements.last_mut() {
+            if !element.has_children {
+                self.output.write_all(b">")?;
+                element.has_children = true;
+                element.first_child_is_text = is_text;
+            }
+
+            element.last_child_is_text = is_text;
+        }
+
+        Ok(())
+    }
+}
+
+fn normalize_line_endings(value: &str) -> Cow<'_, str> {
+    if value.contains('\r') {
+        Cow::Owned(value.replace("\r\n", "\n").replace('\r', "\n"))
+
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// Attribute value normalization: literal whitespace is replaced with spaces, then entities are unescaped
+fn get_attribute_value(raw: &[u8]) -> OperationResult<String> {
+    let value = normalize_line_endings(std::str::from_utf8(raw)?).replace(['\n', '\t'], " ");
+    Ok(unescape(&value).map_err(|e| anyhow!("invalid attribute value: {}", e))?.into_owned())
+}
+
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for char in value.chars() {
+        match char {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '\r' => escaped.push_str("&#13;"),
+            _ => escaped.push(char),
+        }
+    }
+
+    escaped
+}
+
+fn escape_attribute(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for char in value.chars() {
+        match char {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            '\t' => escaped.push_str("&#9;"),
+            _ => escaped.push(char),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::events::Event;
+    use quick_xml::reader::Reader;
+
+    use crate::xml::hash::XmlNodeWriter;
+
+    fn write_nodes(xml: &str) -> String {
+        let mut reader = Reader::from_str(xml);
+        let mut writer = XmlNodeWriter::new(vec![]);
+
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Eof => break,
+                Event::Start(e) => writer.write_start(&e, false).unwrap(),
+                Event::Empty(e) => writer.write_start(&e, true).unwrap(),
+                Event::End(_) => writer.write_end().unwrap(),
+                Event::Text(e) => writer.write_text(&e).unwrap(),
+                Event::CData(e) => writer.write_cdata(&e).unwrap(),
+                Event::Comment(e) => writer.write_comment(&e).unwrap(),
+                Event::PI(e) => writer.write_processing_instruction(&e).unwrap(),
+                _ => {}
+            }
+        }
+
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    /// Expected values are libxml2 output (`xmllint --noblanks --xpath`)
+    #[test]
+    fn nodes_should_be_written_as_libxml_does() {
+        let xml = "<A id=\"1\" t='a&amp;b&#10;c\tx'>\r\n    <n>Ёж &amp; \"q\" &gt; &#13;</n>\n    <e></e>\n    \
+                   <w>  </w>\n    <c><![CDATA[<x>]]></c>\n    <!-- c -->\n  </A>";
+
+        assert_eq!("<A id=\"1\" t=\"a&amp;b&#10;c x\"><n>Ёж &amp; \"q\" &gt; &#13;</n><e/><w>  </w>\
+                    <c><![CDATA[<x>]]></c><!-- c --></A>", write_nodes(xml));
+    }
+
+    #[test]
+    fn whitespace_should_be_kept_in_mixed_content() {
+        assert_eq!("<a>x<b/>  <c/></a>", write_nodes("<a>x<b/>  <c/></a>"));
+        assert_eq!("<a><b/><c/></a>", write_nodes("<a>  <b/>  <c/>  </a>"));
+        assert_eq!("<a><!--c--></a>", write_nodes("<a>  <!--c--></a>"));
+        assert_eq!("<a><b/>x<c/></a>", write_nodes("<a><b/>x<c/> </a>"));
+        assert_eq!("<a><b/> x<c/><d/> </a>", write_nodes("<a><b/> x<c/><d/> </a>"));
+        assert_eq!("<a><b/>   <c/></a>", write_nodes("<a><b/> &#32; <c/></a>"));
+    }
+
+    #[test]
+    fn processing_instruction_target_should_be_separated_with_single_space() {
+        assert_eq!("<a><?p?><?q?><?r d ?></a>", write_nodes("<a><?p?><?q   ?><?r  d ?></a>"));
+    }
+}
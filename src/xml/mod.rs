@@ -0,0 +1,634 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Seek};
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Error};
+use hmac::{Hmac, Mac};
+use log::{debug, info};
+use quick_xml::events::Event;
+use quick_xml::name::QName;
+use quick_xml::reader::Reader;
+use regex::{NoExpand, Regex};
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+
+use crate::crypt::decrypt_with_secured_key;
+use crate::types::{EmptyResult, OperationResult};
+use crate::xml::hash::XmlNodeWriter;
+use crate::xml::parser::{read_xml_index, XmlAccountReader};
+
+pub mod hash;
+pub mod parser;
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct XmlConfig {
+    pub categories: Vec<MetaProperty>,
+    pub clients: Vec<MetaProperty>,
+    pub tags: Vec<MetaProperty>,
+    pub accounts: Vec<XmlAccount>
+}
+
+impl XmlConfig {
+    /// Index for account streaming functions, accounts are counted only
+    pub fn get_index(&self) -> XmlIndex {
+        let mut tag_accounts_count: HashMap<u32, usize> = HashMap::new();
+
+        for account in &self.accounts {
+            for tag in self.tags.iter().filter(|tag| account.has_tag(&tag.name)) {
+                *tag_accounts_count.entry(tag.id).or_insert(0) += 1;
+            }
+        }
+
+        XmlIndex {
+            categories: self.categories.clone(),
+            clients: self.clients.clone(),
+            tags: self.tags.clone(),
+            accounts_count: self.accounts.len(),
+            tag_accounts_count,
+            encrypted: false,
+        }
+    }
+}
+
+/// Result of the first (lightweight) pass over xml export: everything except accounts
+#[derive(Debug,PartialEq,Clone,Default)]
+pub struct XmlIndex {
+    pub categories: Vec<MetaProperty>,
+    pub clients: Vec<MetaProperty>,
+    pub tags: Vec<MetaProperty>,
+
+    pub accounts_count: usize,
+
+    /// Accounts count by tag id
+    pub tag_accounts_count: HashMap<u32, usize>,
+
+    /// Export has `<Encrypted>` element
+    pub encrypted: bool,
+}
+
+impl XmlIndex {
+    pub fn get_accounts_count_with_tag(&self, tag: &str) -> usize {
+        self.tags.iter()
+            .filter(|item| item.name == tag)
+            .filter_map(|item| self.tag_accounts_count.get(&item.id))
+            .sum()
+    }
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct MetaProperty {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct XmlAccount {
+    pub id: u32,
+    pub name: String,
+    pub client_id: u32,
+    pub category_id: u32,
+    pub login: String,
+    pub url: String,
+    pub notes: String,
+
+    /// Tag names
+    pub tags: Vec<String>,
+}
+
+impl XmlAccount {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|account_tag| account_tag == tag)
+    }
+}
+
+impl Display for XmlAccount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<Account>id {}, name '{}', login '{}', client-id {}, category-id {}, url '{}', tags {:?}</Account>",
+               self.id, self.name, self.login, self.client_id, self.category_id, self.url, self.tags)
+    }
+}
+
+/// Source for xml parser: plain file or decrypted export in memory
+pub trait XmlSource: BufRead + Seek {}
+
+impl<T: BufRead + Seek> XmlSource for T {}
+
+pub type XmlAccounts = XmlAccountReader<Box<dyn XmlSource>>;
+
+pub const XML_PASSWORD_ENV_VAR: &str = "SPT_XML_PASSWORD";
+
+/// Options for xml export loading, see `open_xml_accounts`
+#[derive(Default)]
+pub struct XmlLoadOptions<'a> {
+    /// Password for encrypted export
+    pub password: Option<&'a str>,
+
+    /// Key for hash signature verification
+    pub sign_key: Option<&'a str>,
+
+    /// Fail on any structural problem instead of skipping invalid entities
+    pub strict: bool,
+}
+
+/// Extract from given xml file properties for entities:
+/// - category
+/// - client
+/// - tag
+/// - account (without password)
+///
+/// All accounts are loaded in memory, use `open_xml_accounts` for large files.
+pub fn get_xml_config_from_file(file_path: &Path, options: &XmlLoadOptions) -> OperationResult<XmlConfig> {
+    let (index, accounts) = open_xml_accounts(file_path, options)?;
+
+    let accounts = accounts.collect::<OperationResult<Vec<XmlAccount>>>()?;
+
+    Ok(XmlConfig {
+        categories: index.categories,
+        clients: index.clients,
+        tags: index.tags,
+        accounts,
+    })
+}
+
+/// Open xml file for account streaming. File is read twice: categories, clients and tags are
+/// collected by the first pass (see `XmlIndex`), accounts are parsed one by one while iterating.
+///
+/// Extracted data is not complete, just few fields for data filtering and reports.
+///
+/// Encrypted exports are decrypted in memory with `password`, `SPT_XML_PASSWORD` env variable
+/// or password from prompt.
+///
+/// Export hash and signature are verified before parsing if `sign_key` is provided, see `verify_xml_hash`.
+///
+/// Errors contain line and column of invalid element, see `parser::XmlAccountReader` for strict mode.
+pub fn open_xml_accounts(file_path: &Path, options: &XmlLoadOptions) -> OperationResult<(XmlIndex, XmlAccounts)> {
+    info!("load xml configuration from file '{}'", file_path.display());
+
+    let open_file = || -> OperationResult<BufReader<File>> {
+        Ok(BufReader::new(File::open(file_path).context("couldn't read xml file")?))
+    };
+
+    if let Some(sign_key) = options.sign_key {
+        verify_xml_hash(open_file()?, sign_key)?;
+        info!("xml hash and signature are valid");
+    }
+
+    let index = read_xml_index(open_file()?, options.strict)?;
+
+    if index.encrypted {
+        info!("xml file is encrypted");
+        let xml = fs::read_to_string(file_path).context("couldn't read xml file")?;
+        let password = get_xml_password(options.password)?;
+        let decrypted_xml = decrypt_xml(&xml, &password)?.into_bytes();
+
+        let index = read_xml_index(Cursor::new(decrypted_xml.as_slice()), options.strict)?;
+        let source: Box<dyn XmlSource> = Box::new(Cursor::new(decrypted_xml));
+
+        let accounts = XmlAccountReader::new(source, &index, options.strict);
+        Ok((index, accounts))
+
+    } else {
+        let source: Box<dyn XmlSource> = Box::new(open_file()?);
+
+        let accounts = XmlAccountReader::new(source, &index, options.strict);
+        Ok((index, accounts))
+    }
+}
+
+/// sysPass export hash is sha1 of top-level elements (except `<Meta>`, for encrypted exports it's `<Encrypted>`)
+/// serialized by `DOMDocument::saveXML`, see `XmlNodeWriter`. So the hash doesn't depend on formatting of file.
+/// Signature is HMAC-SHA256 of hash with sysPass key (export password for encrypted exports).
+///
+/// Hash is calculated for file content before decryption, elements are hashed while file is being read.
+pub fn verify_xml_hash<R: BufRead>(source: R, sign_key: &str) -> EmptyResult {
+    let mut reader = Reader::from_reader(source);
+
+    let mut buf = Vec::new();
+
+    let mut depth = 0;
+    let mut in_meta = false;
+    let mut in_hash = false;
+
+    let mut writer = XmlNodeWriter::new(Sha1::new());
+
+    let mut hash: Option<String> = None;
+    let mut sign: Option<String> = None;
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Err(e) => return Err(anyhow!("xml parse error: {}", e)),
+            Ok(Event::Eof) => break,
+
+            Ok(Event::Start(e)) => {
+                depth += 1;
+
+                if depth == 2 {
+                    in_meta = e.name().as_ref() == b"Meta";
+                }
+
+                if depth >= 2 && !in_meta {
+                    writer.write_start(&e, false)?;
+
+                } else if in_meta && e.name().as_ref() == b"Hash" {
+                    sign = match e.try_get_attribute("sign")? {
+                        Some(attribute) => Some(attribute.unescape_value()?.to_string()),
+                        None => None
+                    };
+                    in_hash = true;
+                }
+            }
+            Ok(Event::Empty(e)) => {
+                if depth == 1 {
+                    in_meta = e.name().as_ref() == b"Meta";
+                }
+
+                if depth >= 1 && !in_meta {
+                    writer.write_start(&e, true)?;
+                }
+            }
+            Ok(Event::End(_)) => {
+                if depth >= 2 && !in_meta {
+                    writer.write_end()?;
+                }
+                in_hash = false;
+                depth -= 1;
+            }
+            Ok(Event::Text(e)) => {
+                if in_hash {
+                    hash = Some(e.unescape()?.trim().to_string());
+
+                } else if depth >= 2 && !in_meta {
+                    writer.write_text(&e)?;
+                }
+            }
+            Ok(Event::CData(e)) if depth >= 2 && !in_meta => writer.write_cdata(&e)?,
+            Ok(Event::Comment(e)) if depth >= 2 && !in_meta => writer.write_comment(&e)?,
+            Ok(Event::PI(e)) if depth >= 2 && !in_meta => writer.write_processing_instruction(&e)?,
+            _ => (),
+        }
+        buf.clear();
+    }
+
+    if depth != 0 {
+        return Err(anyhow!("xml file is truncated"))
+    }
+
+    let (hash, sign) = match (hash, sign) {
+        (Some(hash), Some(sign)) => (hash, sign),
+        _ => return Err(anyhow!("xml file doesn't have hash or signature in <Meta> element"))
+    };
+
+    let actual_hash = hex::encode(writer.into_inner().finalize());
+
+    if actual_hash != hash {
+        return Err(anyhow!("xml hash mismatch, file has been changed or truncated"))
+    }
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(sign_key.as_bytes())?;
+    mac.update(hash.as_bytes());
+
+    let sign = hex::decode(&sign).context("xml signature isn't hex-encoded")?;
+
+    mac.verify_slice(&sign).map_err(|_| anyhow!("xml signature mismatch, file has been changed or sign key is wrong"))
+}
+
+fn get_xml_password(xml_password: Option<&str>) -> OperationResult<String> {
+    if let Some(password) = xml_password {
+        return Ok(password.to_string())
+    }
+
+    if let Ok(password) = env::var(XML_PASSWORD_ENV_VAR) {
+        return Ok(password)
+    }
+
+    rpassword::prompt_password("xml export password: ").context("couldn't read xml export password")
+}
+
+/// sysPass replaces `<Categories>`, `<Clients>`, `<Tags>` and `<Accounts>` with `<Data>` elements
+/// inside `<Encrypted>` element, each one has own password protected key.
+///
+/// Returns xml with decrypted elements instead of `<Encrypted>`.
+fn decrypt_xml(xml: &str, password: &str) -> OperationResult<String> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+
+    let mut buf = Vec::new();
+
+    let mut fragments: Vec<String> = vec![];
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Err(e) => return Err(anyhow!("xml parse error: {}", e)),
+            Ok(Event::Eof) => break,
+
+            Ok(Event::Start(e)) if e.name().as_ref() == b"Data" => {
+                let key = match e.try_get_attribute("key")? {
+                    Some(attribute) => attribute.unescape_value()?.to_string(),
+                    None => return Err(anyhow!("encrypted data doesn't have 'key' attribute"))
+                };
+
+                let data = get_element_text(&mut reader, e.name())?;
+
+                let fragment = decrypt_with_secured_key(&data, &key, password)
+                                                .context("couldn't decrypt xml data")?;
+
+                debug!("decrypted xml fragment, length {}", fragment.len());
+
+                fragments.push(strip_xml_declaration(&fragment).to_string());
+            }
+            _ => (),
+        }
+        buf.clear();
+    }
+
+    if fragments.is_empty() {
+        return Err(anyhow!("encrypted xml doesn't have data"))
+    }
+
+    let encrypted_element = Regex::new(r"(?s)<Encrypted\b.*?</Encrypted>")?;
+
+    let decrypted = fragments.join("\n");
+
+    Ok(encrypted_element.replace(xml, NoExpand(&decrypted)).to_string())
+}
+
+fn get_element_text(reader: &mut Reader<&[u8]>, element_name: QName) -> Result<String, Error> {
+    let txt = reader
+        .read_text(element_name)?;
+    Ok(txt.to_string())
+}
+
+fn strip_xml_declaration(fragment: &str) -> &str {
+    let fragment = fragment.trim();
+
+    match fragment.strip_prefix("<?xml") {
+        Some(declaration) => match declaration.find("?>") {
+            Some(end) => declaration[end + 2..].trim_start(),
+            None => fragment
+        },
+        None => fragment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Cursor;
+    use std::path::Path;
+
+    use fake::{Fake, Faker};
+
+    use crate::crypt::WRONG_PASSWORD_ERROR;
+    use crate::tests::init_logging;
+    use crate::tests::xml::get_test_xml_config;
+    use crate::xml::{get_xml_config_from_file, MetaProperty, open_xml_accounts, verify_xml_hash, XmlAccount, XmlConfig, XmlLoadOptions};
+
+    const XML_PASSWORD: &str = "export-pass-123";
+    const SIGN_KEY: &str = "sign-key-123";
+
+    /// sysPass signs not encrypted exports with sha1 of password salt
+    const SYSPASS_SIGN_KEY: &str = "3119a81460acd919e34ed6b97ad1443c6ffa5eba";
+
+    #[test]
+    fn config_should_be_loaded() {
+        init_logging();
+
+        let xml_file_path = Path::new("test-data").join("import.xml");
+
+        match get_xml_config_from_file(xml_file_path.as_path(), &XmlLoadOptions::default()) {
+            Ok(xml_config) => {
+                assert_eq!(xml_config, get_expected_xml_config());
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                eprintln!("{}", e.root_cause());
+                panic!("result expected")
+            }
+        }
+    }
+
+    #[test]
+    fn encrypted_config_should_be_loaded() {
+        let xml_file_path = Path::new("test-data").join("import-encrypted.xml");
+
+        let xml_config = get_xml_config_from_file(
+            xml_file_path.as_path(), &XmlLoadOptions { password: Some(XML_PASSWORD), ..XmlLoadOptions::default() }).unwrap();
+
+        assert_eq!(xml_config, get_expected_xml_config());
+    }
+
+    #[test]
+    fn decrypted_data_with_dollar_signs_should_be_kept_as_is() {
+        let xml_file_path = Path::new("test-data").join("import-encrypted-dollar.xml");
+
+        let xml_config = get_xml_config_from_file(
+            xml_file_path.as_path(), &XmlLoadOptions { password: Some(XML_PASSWORD), ..XmlLoadOptions::default() }).unwrap();
+
+        assert_eq!("i.petrov@company.ru, limit $1 ${name} $$0", xml_config.accounts[0].notes);
+    }
+
+    #[test]
+    fn return_error_for_wrong_xml_password() {
+        let xml_file_path = Path::new("test-data").join("import-encrypted.xml");
+
+        let error = get_xml_config_from_file(
+            xml_file_path.as_path(), &XmlLoadOptions { password: Some("wrong-password"), ..XmlLoadOptions::default() }).unwrap_err();
+
+        assert_eq!(WRONG_PASSWORD_ERROR, error.root_cause().to_string());
+    }
+
+    #[test]
+    fn signed_config_should_be_loaded() {
+        let xml_file_path = Path::new("test-data").join("import-signed.xml");
+
+        let xml_config = get_xml_config_from_file(
+            xml_file_path.as_path(), &XmlLoadOptions { sign_key: Some(SIGN_KEY), strict: true, ..XmlLoadOptions::default() }).unwrap();
+
+        assert_eq!(xml_config, get_expected_xml_config());
+    }
+
+    #[test]
+    fn signature_of_encrypted_xml_should_be_verified_before_decryption() {
+        let xml = fs::read_to_string(Path::new("test-data").join("import-encrypted.xml")).unwrap();
+        assert!(verify_xml_hash(Cursor::new(xml.as_bytes()), XML_PASSWORD).is_ok());
+    }
+
+    #[test]
+    fn syspass_export_hash_should_not_depend_on_formatting() {
+        let xml = fs::read_to_string(Path::new("test-data").join("export-syspass-3.xml")).unwrap();
+        assert!(verify_xml_hash(Cursor::new(xml.as_bytes()), SYSPASS_SIGN_KEY).is_ok());
+
+        let reformatted_xml = xml.replace("\n  <", "\n    <").replace('\n', "\r\n").replace("<url/>", "<url></url>");
+        assert!(verify_xml_hash(Cursor::new(reformatted_xml.as_bytes()), SYSPASS_SIGN_KEY).is_ok());
+    }
+
+    #[test]
+    fn return_error_for_tampered_syspass_export() {
+        let xml = fs::read_to_string(Path::new("test-data").join("export-syspass-3-tampered.xml")).unwrap();
+
+        let error = verify_xml_hash(Cursor::new(xml.as_bytes()), SYSPASS_SIGN_KEY).unwrap_err();
+
+        assert!(error.to_string().contains("hash mismatch"));
+    }
+
+    #[test]
+    fn return_error_for_changed_xml() {
+        let xml = fs::read_to_string(Path::new("test-data").join("import-signed.xml")).unwrap();
+        let changed_xml = xml.replace("i.petrov", "i.petrov2");
+
+        let error = verify_xml_hash(Cursor::new(changed_xml.as_bytes()), SIGN_KEY).unwrap_err();
+
+        assert!(error.to_string().contains("hash mismatch"));
+    }
+
+    #[test]
+    fn return_error_for_truncated_xml() {
+        let xml = fs::read_to_string(Path::new("test-data").join("import-signed.xml")).unwrap();
+        let truncated_xml = &xml[..xml.find("<Account id=\"2\">").unwrap()];
+
+        assert!(verify_xml_hash(Cursor::new(truncated_xml.as_bytes()), SIGN_KEY).is_err());
+    }
+
+    #[test]
+    fn return_error_for_wrong_sign_key() {
+        let xml = fs::read_to_string(Path::new("test-data").join("import-signed.xml")).unwrap();
+
+        let error = verify_xml_hash(Cursor::new(xml.as_bytes()), "another-key").unwrap_err();
+
+        assert!(error.to_string().contains("signature mismatch"));
+    }
+
+    #[test]
+    fn return_error_for_unsigned_xml_if_sign_key_provided() {
+        let xml = fs::read_to_string(Path::new("test-data").join("import-signed.xml")).unwrap();
+        let unsigned_xml = regex::Regex::new(r"(?s)<Hash.*</Hash>").unwrap().replace(&xml, "");
+
+        assert!(verify_xml_hash(Cursor::new(unsigned_xml.as_bytes()), SIGN_KEY).is_err());
+    }
+
+    fn get_expected_xml_config() -> XmlConfig {
+        XmlConfig {
+            categories: vec![
+                MetaProperty {
+                    id: 1,
+                    name: "APP".to_string(),
+                },
+                MetaProperty {
+                    id: 2,
+                    name: "CLI".to_string(),
+                }
+            ],
+            clients: vec![
+                MetaProperty {
+                    id: 1,
+                    name: "BirchStore".to_string(),
+                },
+                MetaProperty {
+                    id: 2,
+                    name: "KalinkaStore".to_string(),
+                }
+            ],
+            tags: vec![
+                MetaProperty {
+                    id: 1,
+                    name: "Shop".to_string(),
+                },
+                MetaProperty {
+                    id: 2,
+                    name: "SSO".to_string(),
+                }
+            ],
+            accounts: vec![
+                XmlAccount {
+                    id: 1,
+                    name: "Ivan Petrov".to_string(),
+                    client_id: 1,
+                    category_id: 2,
+                    login: "i.petrov".to_string(),
+                    url: "https://app.internal.company.ru:5555/app/".to_string(),
+                    notes: "i.petrov@company.ru".to_string(),
+                    tags: vec!["Shop".to_string(), "SSO".to_string()],
+                },
+                XmlAccount {
+                    id: 2,
+                    name: "Abramova Nina".to_string(),
+                    client_id: 2,
+                    category_id: 1,
+                    login: "n.abramova".to_string(),
+                    url: "https://stage.internal.company.ru".to_string(),
+                    notes: "n.abramova@somecompany.ru".to_string(),
+                    tags: vec!["Shop".to_string(), "SSO".to_string()],
+                }
+            ],
+        }
+    }
+
+    #[test]
+    fn accounts_should_be_filtered_by_tag() {
+        let xml_config = get_test_xml_config();
+
+        let logins: Vec<&str> = xml_config.accounts.iter()
+            .filter(|account| account.has_tag("SSO"))
+            .map(|account| account.login.as_str()).collect();
+        assert_eq!(vec!["i.petrov", "p.sidorov"], logins);
+
+        assert!(!xml_config.accounts.iter().any(|account| account.has_tag("sso")));
+    }
+
+    #[test]
+    fn accounts_should_be_counted_by_index() {
+        let index = get_test_xml_config().get_index();
+
+        assert_eq!(3, index.accounts_count);
+        assert_eq!(2, index.get_accounts_count_with_tag("SSO"));
+        assert_eq!(0, index.get_accounts_count_with_tag("sso"));
+    }
+
+    #[test]
+    fn accounts_should_be_streamed_after_index_pass() {
+        let xml_file_path = Path::new("test-data").join("import.xml");
+
+        let (index, mut accounts) = open_xml_accounts(xml_file_path.as_path(), &XmlLoadOptions::default()).unwrap();
+
+        let expected_config = get_expected_xml_config();
+
+        assert_eq!(expected_config.categories, index.categories);
+        assert_eq!(expected_config.clients, index.clients);
+        assert_eq!(expected_config.tags, index.tags);
+        assert_eq!(2, index.accounts_count);
+        assert_eq!(2, index.get_accounts_count_with_tag("Shop"));
+        assert!(!index.encrypted);
+
+        assert_eq!(expected_config.accounts[0], accounts.next().unwrap().unwrap());
+        assert_eq!(expected_config.accounts[1], accounts.next().unwrap().unwrap());
+        assert!(accounts.next().is_none());
+    }
+
+    #[test]
+    fn encrypted_xml_should_be_detected_by_index_pass() {
+        let xml_file_path = Path::new("test-data").join("import-encrypted.xml");
+
+        let (index, accounts) = open_xml_accounts(
+            xml_file_path.as_path(), &XmlLoadOptions { password: Some(XML_PASSWORD), ..XmlLoadOptions::default() }).unwrap();
+
+        assert_eq!(2, index.accounts_count);
+        assert_eq!(2, accounts.count());
+    }
+
+    #[test]
+    fn return_error_for_missing_file() {
+        let filename = Faker.fake::<String>();
+        let path = Path::new(&filename);
+        assert!(get_xml_config_from_file(path, &XmlLoadOptions::default()).is_err());
+    }
+
+    #[test]
+    fn return_error_for_invalid_xml_file() {
+        let path = Path::new("test-data").join("invalid.xml");
+        assert!(get_xml_config_from_file(path.as_path(), &XmlLoadOptions::default()).is_err());
+    }
+}
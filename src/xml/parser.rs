@@ -0,0 +1,1015 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, Seek, SeekFrom};
+
+use anyhow::Error;
+use log::{debug, warn};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::name::QName;
+use quick_xml::reader::Reader;
+
+use crate::types::{EmptyResult, OperationResult};
+use crate::xml::{MetaProperty, XmlAccount, XmlIndex};
+
+/// Invalid xml structure or data, position points to the offending element
+#[derive(PartialEq,Debug,Clone)]
+pub struct XmlParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for XmlParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "xml error at line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for XmlParseError {}
+
+const ROOT_ELEMENT: &[u8] = b"Root";
+const ENCRYPTED_ELEMENT: &[u8] = b"Encrypted";
+
+#[derive(PartialEq,Debug,Clone,Copy)]
+enum XmlEntityKind {
+    Category,
+    Client,
+    Tag,
+    Account,
+}
+
+impl XmlEntityKind {
+    fn from_element(name: &[u8]) -> Option<XmlEntityKind> {
+        match name {
+            b"Category" => Some(XmlEntityKind::Category),
+            b"Client" => Some(XmlEntityKind::Client),
+            b"Tag" => Some(XmlEntityKind::Tag),
+            b"Account" => Some(XmlEntityKind::Account),
+            _ => None
+        }
+    }
+
+    fn from_container(name: &[u8]) -> Option<XmlEntityKind> {
+        match name {
+            b"Categories" => Some(XmlEntityKind::Category),
+            b"Clients" => Some(XmlEntityKind::Client),
+            b"Tags" => Some(XmlEntityKind::Tag),
+            b"Accounts" => Some(XmlEntityKind::Account),
+            _ => None
+        }
+    }
+
+    fn get_title(&self) -> &'static str {
+        match self {
+            XmlEntityKind::Category => "category",
+            XmlEntityKind::Client => "client",
+            XmlEntityKind::Tag => "tag",
+            XmlEntityKind::Account => "account",
+        }
+    }
+}
+
+#[derive(PartialEq,Debug,Clone,Copy)]
+enum XmlField {
+    Name,
+    Login,
+    ClientId,
+    CategoryId,
+    Url,
+    Notes,
+}
+
+impl XmlField {
+    fn from_element(name: &[u8], kind: XmlEntityKind) -> Option<XmlField> {
+        match (name, kind) {
+            (b"name", _) => Some(XmlField::Name),
+            (b"login", XmlEntityKind::Account) => Some(XmlField::Login),
+            (b"clientId", XmlEntityKind::Account) => Some(XmlField::ClientId),
+            (b"categoryId", XmlEntityKind::Account) => Some(XmlField::CategoryId),
+            (b"url", XmlEntityKind::Account) => Some(XmlField::Url),
+            (b"notes", XmlEntityKind::Account) => Some(XmlField::Notes),
+            _ => None
+        }
+    }
+}
+
+/// Entity which is being parsed, required fields are checked on close tag
+struct XmlEntity {
+    kind: XmlEntityKind,
+    position: usize,
+    id: Option<u32>,
+    name: Option<String>,
+    login: Option<String>,
+    client_id: Option<u32>,
+    category_id: Option<u32>,
+    url: String,
+    notes: String,
+    tag_ids: Vec<(u32, usize)>,
+}
+
+impl XmlEntity {
+    fn new(kind: XmlEntityKind, position: usize, id: Option<u32>) -> XmlEntity {
+        XmlEntity {
+            kind, position, id,
+            name: None,
+            login: None,
+            client_id: None,
+            category_id: None,
+            url: String::new(),
+            notes: String::new(),
+            tag_ids: vec![],
+        }
+    }
+}
+
+/// Field element which is being read
+struct XmlFieldValue {
+    field: XmlField,
+    position: usize,
+
+    /// Open elements count including field element
+    depth: usize,
+
+    value: String,
+}
+
+/// Index pass collects categories, clients and tags, accounts are counted only
+/// (in strict mode they are validated too, so invalid file is rejected before any account is processed).
+/// Accounts pass reads accounts, other entities are skipped.
+#[derive(PartialEq,Debug,Clone,Copy)]
+enum XmlPass {
+    Index,
+    Accounts,
+}
+
+/// First (lightweight) pass over xml export, see `XmlAccountReader`.
+///
+/// Strict mode: accounts are checked the same way as by `XmlAccountReader`, but aren't kept in memory.
+pub fn read_xml_index<R: BufRead + Seek>(source: R, strict: bool) -> OperationResult<XmlIndex> {
+    let mut parser = XmlParser::new(source, strict, XmlPass::Index, XmlIndex::default());
+
+    while parser.read_next_account()?.is_some() {}
+
+    parser.check_pending_references()?;
+
+    debug!("xml index: {} categories, {} clients, {} tags, {} accounts", parser.index.categories.len(),
+           parser.index.clients.len(), parser.index.tags.len(), parser.index.accounts_count);
+
+    Ok(parser.index)
+}
+
+/// Streaming reader of xml export accounts, memory usage doesn't depend on file size.
+///
+/// Clients, categories and tags are resolved with index from the first pass (`read_xml_index`).
+///
+/// Strict mode: any structural problem (missing id or required field, duplicate id, unknown client,
+/// category or tag reference, unexpected element nesting) is an error.
+/// Otherwise problems are logged and invalid entities are skipped.
+///
+/// Syntax errors and unclosed elements are errors in both modes, reader stops after the first error.
+pub struct XmlAccountReader<R: BufRead + Seek> {
+    parser: XmlParser<R>,
+    finished: bool,
+}
+
+impl<R: BufRead + Seek> XmlAccountReader<R> {
+    pub fn new(source: R, index: &XmlIndex, strict: bool) -> XmlAccountReader<R> {
+        let index = XmlIndex {
+            categories: index.categories.clone(),
+            clients: index.clients.clone(),
+            tags: index.tags.clone(),
+            ..XmlIndex::default()
+        };
+
+        XmlAccountReader {
+            parser: XmlParser::new(source, strict, XmlPass::Accounts, index),
+            finished: false,
+        }
+    }
+}
+
+impl<R: BufRead + Seek> Iterator for XmlAccountReader<R> {
+    type Item = OperationResult<XmlAccount>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None
+        }
+
+        match self.parser.read_next_account() {
+            Ok(Some(account)) => Some(Ok(account)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Account reference to client, category or tag which wasn't read yet by index pass
+struct XmlReference {
+    kind: XmlEntityKind,
+    id: u32,
+    position: usize,
+    message: String,
+}
+
+struct XmlParser<R: BufRead + Seek> {
+    reader: Reader<R>,
+    buf: Vec<u8>,
+    strict: bool,
+    pass: XmlPass,
+
+    /// Names of open elements
+    path: Vec<String>,
+
+    entity: Option<XmlEntity>,
+    field: Option<XmlFieldValue>,
+
+    index: XmlIndex,
+    account_ids: HashSet<u32>,
+
+    /// Index pass only, references are checked after the whole file is read
+    pending_references: Vec<XmlReference>,
+
+    positions: PositionTracker,
+}
+
+impl<R: BufRead + Seek> XmlParser<R> {
+    fn new(source: R, strict: bool, pass: XmlPass, index: XmlIndex) -> XmlParser<R> {
+        let mut reader = Reader::from_reader(source);
+        reader.trim_text(true);
+
+        XmlParser {
+            reader,
+            buf: Vec::new(),
+            strict, pass,
+            path: vec![],
+            entity: None,
+            field: None,
+            index,
+            account_ids: HashSet::new(),
+            pending_references: vec![],
+            positions: PositionTracker::new(),
+        }
+    }
+
+    /// Returns `None` at the end of file
+    fn read_next_account(&mut self) -> OperationResult<Option<XmlAccount>> {
+        let mut buf = std::mem::take(&mut self.buf);
+        let result = self.read_events(&mut buf);
+        buf.clear();
+        self.buf = buf;
+        result
+    }
+
+    fn read_events(&mut self, buf: &mut Vec<u8>) -> OperationResult<Option<XmlAccount>> {
+        loop {
+            buf.clear();
+
+            let position = self.reader.buffer_position();
+
+            let account = match self.reader.read_event_into(buf) {
+                Err(e) => {
+                    let error_position = self.reader.buffer_position();
+                    return Err(self.error(error_position, &format!("syntax error: {}", e)))
+                }
+                Ok(Event::Eof) => break,
+
+                Ok(Event::Start(e)) => {
+                    self.open_element(&e, position, false)?;
+                    None
+                }
+                Ok(Event::Empty(e)) => self.open_element(&e, position, true)?,
+                Ok(Event::End(e)) => self.close_element(e.name(), position)?,
+                Ok(Event::Text(e)) => {
+                    if let Some(field) = self.get_field_value() {
+                        field.value.push_str(&e.unescape()?);
+                    }
+                    None
+                }
+                Ok(Event::CData(e)) => {
+                    if let Some(field) = self.get_field_value() {
+                        field.value.push_str(std::str::from_utf8(&e.into_inner())?);
+                    }
+                    None
+                }
+                _ => None,
+            };
+
+            if account.is_some() {
+                return Ok(account)
+            }
+        }
+
+        if let Some(element_name) = self.path.last() {
+            let message = format!("element '{}' isn't closed, file is truncated", element_name);
+            let position = self.reader.buffer_position();
+            return Err(self.error(position, &message))
+        }
+
+        Ok(None)
+    }
+
+    /// Field which is direct parent of current text
+    fn get_field_value(&mut self) -> Option<&mut XmlFieldValue> {
+        let depth = self.path.len();
+        self.field.as_mut().filter(|field| field.depth == depth)
+    }
+
+    fn open_element(&mut self, element: &BytesStart, position: usize,
+                    is_empty: bool) -> OperationResult<Option<XmlAccount>> {
+        let name = element.name();
+        let element_name = String::from_utf8(name.as_ref().to_vec())?;
+
+        let parent = self.path.last().map(|parent| parent.as_bytes().to_vec());
+
+        match (parent, XmlEntityKind::from_element(name.as_ref())) {
+            (None, _) => {
+                if name.as_ref() != ROOT_ELEMENT && self.pass == XmlPass::Index {
+                    self.report(position, &format!("unexpected root element '{}', 'Root' expected", element_name))?;
+                }
+            }
+            (Some(parent), Some(kind)) => {
+                let is_misplaced = XmlEntityKind::from_container(&parent) != Some(kind) || self.entity.is_some();
+
+                if is_misplaced {
+                    // Structure is checked by index pass, accounts pass skips misplaced elements
+                    if self.pass == XmlPass::Index {
+                        self.report(position, &format!("unexpected element '{}' inside '{}'", element_name,
+                                                       String::from_utf8_lossy(&parent)))?;
+                    }
+
+                } else {
+                    if kind == XmlEntityKind::Account && self.pass == XmlPass::Index {
+                        self.index.accounts_count += 1;
+                    }
+
+                    if self.is_in_pass(kind) {
+                        self.positions.mark(position);
+                        let id = self.get_id_attribute(element, position)?;
+                        self.entity = Some(XmlEntity::new(kind, position, id));
+                    }
+                }
+            }
+            (Some(parent), _) => {
+                if parent == ROOT_ELEMENT && name.as_ref() == ENCRYPTED_ELEMENT {
+                    self.index.encrypted = true;
+                }
+
+                if XmlEntityKind::from_container(name.as_ref()).is_some() && parent != ROOT_ELEMENT
+                    && self.pass == XmlPass::Index {
+                    self.report(position, &format!("unexpected element '{}' inside '{}'", element_name,
+                                                   String::from_utf8_lossy(&parent)))?;
+                }
+
+                self.open_entity_child(element, &parent, position, is_empty)?;
+            }
+        }
+
+        if is_empty {
+            self.finish_element(name, position)
+
+        } else {
+            self.path.push(element_name);
+
+            if let Some(field) = &mut self.field {
+                if field.depth == 0 {
+                    field.depth = self.path.len();
+                }
+            }
+
+            Ok(None)
+        }
+    }
+
+    fn is_in_pass(&self, kind: XmlEntityKind) -> bool {
+        match self.pass {
+            XmlPass::Index => kind != XmlEntityKind::Account || self.strict,
+            XmlPass::Accounts => kind == XmlEntityKind::Account
+        }
+    }
+
+    /// Account tags and entity fields, field value is read from text events until close tag
+    fn open_entity_child(&mut self, element: &BytesStart, parent: &[u8], position: usize,
+                         is_empty: bool) -> EmptyResult {
+        let name = element.name();
+
+        let is_account_tag = name.as_ref() == b"tag" && parent == b"tags" && self.path.len() >= 2
+            && self.path[self.path.len() - 2] == "Account";
+
+        if is_account_tag {
+            if let Some(id) = self.get_id_attribute(element, position)? {
+                if self.pass == XmlPass::Index {
+                    *self.index.tag_accounts_count.entry(id).or_insert(0) += 1;
+                }
+
+                if let Some(entity) = &mut self.entity {
+                    if entity.kind == XmlEntityKind::Account {
+                        entity.tag_ids.push((id, position));
+                    }
+                }
+            }
+            return Ok(())
+        }
+
+        let entity_kind = match &self.entity {
+            Some(entity) if XmlEntityKind::from_element(parent) == Some(entity.kind) => entity.kind,
+            _ => return Ok(())
+        };
+
+        if let Some(field) = XmlField::from_element(name.as_ref(), entity_kind) {
+            let field_value = XmlFieldValue { field, position, depth: 0, value: String::new() };
+
+            if is_empty {
+                self.set_field_value(field_value)?;
+
+            } else {
+                self.field = Some(field_value);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn set_field_value(&mut self, field_value: XmlFieldValue) -> EmptyResult {
+        let value = field_value.value;
+
+        let id = match field_value.field {
+            XmlField::ClientId | XmlField::CategoryId => Some(self.parse_id(&value, field_value.position)?),
+            _ => None
+        };
+
+        if let Some(entity) = &mut self.entity {
+            match field_value.field {
+                XmlField::Name => entity.name = Some(value),
+                XmlField::Login => entity.login = Some(value),
+                XmlField::ClientId => entity.client_id = id,
+                XmlField::CategoryId => entity.category_id = id,
+                XmlField::Url => entity.url = value,
+                XmlField::Notes => entity.notes = value.trim().to_string(),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn close_element(&mut self, name: QName, position: usize) -> OperationResult<Option<XmlAccount>> {
+        let depth = self.path.len();
+
+        if let Some(field_value) = self.field.take() {
+            if field_value.depth == depth {
+                self.set_field_value(field_value)?;
+
+            } else {
+                self.field = Some(field_value);
+            }
+        }
+
+        self.path.pop();
+
+        self.finish_element(name, position)
+    }
+
+    /// Entity is added (or returned for accounts) on its close tag
+    fn finish_element(&mut self, name: QName, position: usize) -> OperationResult<Option<XmlAccount>> {
+        let is_entity_end = matches!(
+            (&self.entity, XmlEntityKind::from_element(name.as_ref())),
+            (Some(entity), Some(kind)) if entity.kind == kind && !self.is_inside_entity()
+        );
+
+        if is_entity_end {
+            if let Some(entity) = self.entity.take() {
+                return self.add_entity(entity)
+            }
+
+        } else if self.path.is_empty() && name.as_ref() != ROOT_ELEMENT && self.pass == XmlPass::Index {
+            self.report(position, "unexpected close tag")?;
+        }
+
+        Ok(None)
+    }
+
+    /// Open element with the same name as current entity, i.e. unexpected nested `<Account>`
+    fn is_inside_entity(&self) -> bool {
+        match &self.entity {
+            Some(entity) => self.path.iter()
+                .any(|element_name| XmlEntityKind::from_element(element_name.as_bytes()) == Some(entity.kind)),
+            None => false
+        }
+    }
+
+    fn add_entity(&mut self, entity: XmlEntity) -> OperationResult<Option<XmlAccount>> {
+        let title = entity.kind.get_title();
+
+        let id = match entity.id {
+            Some(id) => id,
+            None => {
+                self.report(entity.position, &format!("{} doesn't have 'id' attribute", title))?;
+                return Ok(None)
+            }
+        };
+
+        let duplicate = match entity.kind {
+            XmlEntityKind::Category => self.index.categories.iter().any(|item| item.id == id),
+            XmlEntityKind::Client => self.index.clients.iter().any(|item| item.id == id),
+            XmlEntityKind::Tag => self.index.tags.iter().any(|item| item.id == id),
+            XmlEntityKind::Account => !self.account_ids.insert(id),
+        };
+
+        if duplicate {
+            self.report(entity.position, &format!("duplicate {} id {}", title, id))?;
+            return Ok(None)
+        }
+
+        let name = match entity.name {
+            Some(name) => name,
+            None => {
+                self.report(entity.position, &format!("{} {} doesn't have name", title, id))?;
+                String::new()
+            }
+        };
+
+        if entity.kind != XmlEntityKind::Account {
+            let property = MetaProperty { id, name };
+
+            match entity.kind {
+                XmlEntityKind::Category => self.index.categories.push(property),
+                XmlEntityKind::Client => self.index.clients.push(property),
+                _ => self.index.tags.push(property),
+            }
+
+            return Ok(None)
+        }
+
+        let (client_id, category_id) = match (entity.client_id, entity.category_id) {
+            (Some(client_id), Some(category_id)) => (client_id, category_id),
+            _ => {
+                self.report(entity.position, &format!("account {} doesn't have 'clientId' or 'categoryId'", id))?;
+                return Ok(None)
+            }
+        };
+
+        // Unknown clients and categories are reported later for each account in non-strict mode
+        if self.strict {
+            self.check_reference(XmlEntityKind::Client, client_id, entity.position,
+                                 &format!("account {} refers to unknown client id {}", id, client_id))?;
+
+            self.check_reference(XmlEntityKind::Category, category_id, entity.position,
+                                 &format!("account {} refers to unknown category id {}", id, category_id))?;
+        }
+
+        let login = match entity.login {
+            Some(login) => login,
+            None => {
+                self.report(entity.position, &format!("account {} doesn't have login", id))?;
+                String::new()
+            }
+        };
+
+        let mut tags: Vec<String> = vec![];
+
+        for (tag_id, position) in &entity.tag_ids {
+            match self.index.tags.iter().find(|tag| tag.id == *tag_id) {
+                Some(tag) => tags.push(tag.name.to_string()),
+                None => self.check_reference(XmlEntityKind::Tag, *tag_id, *position,
+                                             &format!("unknown tag id {}", tag_id))?
+            }
+        }
+
+        Ok(Some(XmlAccount {
+            id,
+            name,
+            client_id,
+            category_id,
+            login,
+            url: entity.url,
+            notes: entity.notes,
+            tags,
+        }))
+    }
+
+    fn is_known_reference(&self, kind: XmlEntityKind, id: u32) -> bool {
+        let entities = match kind {
+            XmlEntityKind::Category => &self.index.categories,
+            XmlEntityKind::Client => &self.index.clients,
+            _ => &self.index.tags,
+        };
+
+        entities.iter().any(|entity| entity.id == id)
+    }
+
+    /// Unknown reference is reported by accounts pass. Index pass postpones the check,
+    /// referenced entity could be placed after accounts.
+    fn check_reference(&mut self, kind: XmlEntityKind, id: u32, position: usize, message: &str) -> EmptyResult {
+        if self.is_known_reference(kind, id) {
+            Ok(())
+
+        } else if self.pass == XmlPass::Index {
+            self.pending_references.push(XmlReference { kind, id, position, message: message.to_string() });
+            Ok(())
+
+        } else {
+            self.report(position, message)
+        }
+    }
+
+    fn check_pending_references(&mut self) -> EmptyResult {
+        let mut pending_references = std::mem::take(&mut self.pending_references);
+        pending_references.sort_by_key(|reference| reference.position);
+
+        for reference in pending_references {
+            if !self.is_known_reference(reference.kind, reference.id) {
+                self.report(reference.position, &reference.message)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_id_attribute(&mut self, element: &BytesStart, position: usize) -> OperationResult<Option<u32>> {
+        match element.try_get_attribute("id") {
+            Ok(Some(attribute)) => {
+                let value = attribute.unescape_value()?;
+                Ok(Some(self.parse_id(&value, position)?))
+            }
+            Ok(None) => Ok(None),
+            Err(e) => Err(self.error(position, &format!("invalid attributes: {}", e)))
+        }
+    }
+
+    fn parse_id(&mut self, value: &str, position: usize) -> OperationResult<u32> {
+        match value.trim().parse::<u32>() {
+            Ok(id) => Ok(id),
+            Err(_) => Err(self.error(position, &format!("invalid id '{}'", value)))
+        }
+    }
+
+    /// Error in strict mode, warning otherwise
+    fn report(&mut self, position: usize, message: &str) -> EmptyResult {
+        if self.strict {
+            Err(self.error(position, message))
+
+        } else {
+            warn!("{}", self.error(position, message));
+            Ok(())
+        }
+    }
+
+    fn error(&mut self, position: usize, message: &str) -> Error {
+        match self.positions.get_line_and_column(self.reader.get_mut(), position) {
+            Ok((line, column)) => Error::new(XmlParseError { line, column, message: message.to_string() }),
+            Err(e) => Error::msg(format!("xml error: {} (position unknown: {})", message, e))
+        }
+    }
+}
+
+/// Line and column (in chars) of element which starts at given byte position.
+/// Position points to whitespace before element because text is trimmed by reader.
+///
+/// Source is read from the beginning (it's done on errors and warnings only), then its position is restored.
+pub fn get_line_and_column<R: BufRead + Seek>(source: &mut R, position: usize) -> OperationResult<(usize, usize)> {
+    PositionTracker::new().get_line_and_column(source, position)
+}
+
+/// Source scanned up to `offset` byte
+#[derive(Clone,Copy,Debug)]
+struct SourcePosition {
+    offset: usize,
+    line: usize,
+
+    /// Chars in current line before `offset`
+    chars: usize,
+}
+
+impl SourcePosition {
+    const START: SourcePosition = SourcePosition { offset: 0, line: 1, chars: 0 };
+
+    fn push(&mut self, byte: u8) {
+        self.offset += 1;
+
+        if byte == b'\n' {
+            self.line += 1;
+            self.chars = 0;
+
+        } else if byte & 0xC0 != 0x80 {
+            // not a continuation byte of multibyte utf-8 char
+            self.chars += 1;
+        }
+    }
+}
+
+/// Resolves byte positions into line and column for error and warning messages.
+///
+/// Source is scanned from the nearest known position (the last resolved one or the start of current entity)
+/// instead of the beginning of file, so many warnings in non-strict mode don't rescan large file again and again.
+struct PositionTracker {
+    last: SourcePosition,
+
+    /// Problems of current entity are reported after its close tag, their positions are after entity start
+    mark: SourcePosition,
+    mark_offset: usize,
+}
+
+impl PositionTracker {
+    fn new() -> PositionTracker {
+        PositionTracker {
+            last: SourcePosition::START,
+            mark: SourcePosition::START,
+            mark_offset: 0,
+        }
+    }
+
+    /// Start of entity, source isn't read until some problem is reported
+    fn mark(&mut self, offset: usize) {
+        self.mark_offset = offset;
+    }
+
+    fn get_line_and_column<R: BufRead + Seek>(&mut self, source: &mut R,
+                                              position: usize) -> OperationResult<(usize, usize)> {
+        let current_position = source.stream_position()?;
+
+        if self.mark.offset != self.mark_offset && self.mark_offset <= position {
+            let start = self.get_nearest(self.mark_offset);
+            self.mark = scan_source(source, start, self.mark_offset, false)?;
+        }
+
+        let start = self.get_nearest(position);
+        self.last = scan_source(source, start, position, false)?;
+
+        let element = scan_source(source, self.last, position, true)?;
+
+        source.seek(SeekFrom::Start(current_position))?;
+
+        Ok((element.line, element.chars + 1))
+    }
+
+    /// The closest known position before given offset
+    fn get_nearest(&self, offset: usize) -> SourcePosition {
+        [self.last, self.mark].into_iter()
+            .filter(|known| known.offset <= offset)
+            .max_by_key(|known| known.offset)
+            .unwrap_or(SourcePosition::START)
+    }
+}
+
+/// Scan source from `start` to `offset` byte. With `skip_whitespace` scanning stops at the first non-whitespace byte
+/// at or after `offset`.
+fn scan_source<R: BufRead + Seek>(source: &mut R, start: SourcePosition, offset: usize,
+                                  skip_whitespace: bool) -> OperationResult<SourcePosition> {
+    source.seek(SeekFrom::Start(start.offset as u64))?;
+
+    let mut position = start;
+
+    loop {
+        let chunk = source.fill_buf()?;
+
+        if chunk.is_empty() {
+            break
+        }
+
+        let chunk_offset = position.offset;
+
+        let remaining = chunk.iter().enumerate()
+            .position(|(index, byte)| chunk_offset + index >= offset &&
+                                     !(skip_whitespace && byte.is_ascii_whitespace()));
+
+        let consumed = remaining.unwrap_or(chunk.len());
+
+        for byte in &chunk[..consumed] {
+            position.push(*byte);
+        }
+
+        source.consume(consumed);
+
+        if remaining.is_some() {
+            break
+        }
+    }
+
+    Ok(position)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::tests::init_logging;
+    use crate::types::OperationResult;
+    use crate::xml::parser::{get_line_and_column, PositionTracker, read_xml_index, XmlAccountReader, XmlParseError};
+    use crate::xml::XmlConfig;
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Root>
+    <Categories>
+        <Category id="12345">
+            <name>APP</name>
+        </Category>
+        <Category id="70000">
+            <name>CLI</name>
+        </Category>
+    </Categories>
+    <Clients>
+        <Client id="1">
+            <name>BirchStore</name>
+        </Client>
+    </Clients>
+    <Tags>
+        <Tag id="1">
+            <name>SSO</name>
+        </Tag>
+    </Tags>
+    <Accounts>
+        <Account id="100000">
+            <name>Ivan Petrov</name>
+            <clientId>1</clientId>
+            <categoryId>70000</categoryId>
+            <login>i.petrov</login>
+            <notes/>
+            <pass>secret</pass>
+            <tags>
+                <tag id="1"/>
+            </tags>
+        </Account>
+    </Accounts>
+</Root>"#;
+
+    fn get_xml_config_from_str(xml: &str, strict: bool) -> OperationResult<XmlConfig> {
+        let index = read_xml_index(Cursor::new(xml.as_bytes()), strict)?;
+
+        let accounts = XmlAccountReader::new(Cursor::new(xml.as_bytes()), &index, strict)
+                                                    .collect::<OperationResult<Vec<_>>>()?;
+
+        Ok(XmlConfig {
+            categories: index.categories,
+            clients: index.clients,
+            tags: index.tags,
+            accounts,
+        })
+    }
+
+    fn get_parse_error(xml: &str) -> XmlParseError {
+        let error = get_xml_config_from_str(xml, true).unwrap_err();
+        error.downcast::<XmlParseError>().unwrap()
+    }
+
+    #[test]
+    fn large_and_former_sentinel_ids_should_be_parsed() {
+        let config = get_xml_config_from_str(XML, true).unwrap();
+
+        assert_eq!(12345, config.categories[0].id);
+        assert_eq!(70000, config.categories[1].id);
+        assert_eq!(100000, config.accounts[0].id);
+        assert_eq!(70000, config.accounts[0].category_id);
+        assert_eq!(vec!["SSO".to_string()], config.accounts[0].tags);
+    }
+
+    #[test]
+    fn return_error_with_position_for_missing_id_in_strict_mode() {
+        let xml = XML.replace(r#"<Client id="1">"#, "<Client>");
+
+        let error = get_parse_error(&xml);
+
+        assert_eq!(12, error.line);
+        assert_eq!(9, error.column);
+        assert_eq!("xml error at line 12, column 9: client doesn't have 'id' attribute", error.to_string());
+    }
+
+    #[test]
+    fn entity_without_id_should_be_skipped_in_non_strict_mode() {
+        init_logging();
+
+        let xml = XML.replace(r#"<Category id="12345">"#, "<Category>");
+
+        let config = get_xml_config_from_str(&xml, false).unwrap();
+
+        assert_eq!(1, config.categories.len());
+        assert_eq!("CLI", config.categories[0].name);
+    }
+
+    #[test]
+    fn name_should_not_leak_to_next_entity() {
+        let xml = XML.replace("<name>CLI</name>", "");
+
+        let config = get_xml_config_from_str(&xml, false).unwrap();
+        assert_eq!("", config.categories[1].name);
+
+        let error = get_parse_error(&xml);
+        assert_eq!((7, 9), (error.line, error.column));
+    }
+
+    #[test]
+    fn return_error_for_unknown_references_in_strict_mode() {
+        let xml = XML.replace("<clientId>1</clientId>", "<clientId>2</clientId>");
+        assert_eq!(22, get_parse_error(&xml).line);
+
+        let xml = XML.replace(r#"<tag id="1"/>"#, r#"<tag id="3"/>"#);
+        let error = get_parse_error(&xml);
+        assert_eq!((30, 17), (error.line, error.column));
+
+        let config = get_xml_config_from_str(&xml, false).unwrap();
+        assert!(config.accounts[0].tags.is_empty());
+    }
+
+    #[test]
+    fn accounts_should_be_validated_by_index_pass_in_strict_mode() {
+        let xml = XML.replace("<categoryId>70000</categoryId>", "");
+
+        let error = read_xml_index(Cursor::new(xml.as_bytes()), true).unwrap_err();
+        assert!(error.to_string().ends_with("account 100000 doesn't have 'clientId' or 'categoryId'"));
+
+        let index = read_xml_index(Cursor::new(xml.as_bytes()), false).unwrap();
+        assert_eq!(1, index.accounts_count);
+    }
+
+    #[test]
+    fn references_to_entities_after_accounts_should_be_resolved_by_index_pass() {
+        let tags = "<Tags>\n        <Tag id=\"1\">\n            <name>SSO</name>\n        </Tag>\n    </Tags>";
+        let xml = XML.replace(tags, "").replace("</Accounts>", &format!("</Accounts>\n    {}", tags));
+
+        let index = read_xml_index(Cursor::new(xml.as_bytes()), true).unwrap();
+
+        assert_eq!(1, index.accounts_count);
+        assert_eq!(Some(&1), index.tag_accounts_count.get(&1));
+
+        let xml = xml.replace(r#"<Tag id="1">"#, r#"<Tag id="2">"#);
+        let error = read_xml_index(Cursor::new(xml.as_bytes()), true).unwrap_err();
+        assert!(error.to_string().ends_with("unknown tag id 1"));
+    }
+
+    #[test]
+    fn return_error_for_duplicate_id_in_strict_mode() {
+        let xml = XML.replace(r#"<Category id="70000">"#, r#"<Category id="12345">"#);
+        assert_eq!(7, get_parse_error(&xml).line);
+    }
+
+    #[test]
+    fn return_error_for_unexpected_nesting_in_strict_mode() {
+        let xml = XML.replace("<Client id=\"1\">\n            <name>BirchStore</name>\n        </Client>",
+                              "<Account id=\"1\"><name>BirchStore</name></Account>");
+
+        let error = get_parse_error(&xml);
+
+        assert_eq!("xml error at line 12, column 9: unexpected element 'Account' inside 'Clients'", error.to_string());
+    }
+
+    #[test]
+    fn return_error_for_invalid_id() {
+        let xml = XML.replace("<clientId>1</clientId>", "<clientId>one</clientId>");
+
+        let error = get_xml_config_from_str(&xml, false).unwrap_err();
+
+        assert!(error.to_string().starts_with("xml error at line 24, column 13: invalid id 'one'"));
+    }
+
+    #[test]
+    fn return_error_for_truncated_xml() {
+        let xml = &XML[..XML.find("<tags>").unwrap()];
+
+        let error = get_xml_config_from_str(xml, false).unwrap_err();
+
+        assert!(error.to_string().contains("element 'Account' isn't closed, file is truncated"));
+    }
+
+    #[test]
+    fn column_should_be_counted_in_chars() {
+        assert_eq!((1, 1), get_line_and_column(&mut Cursor::new("abc".as_bytes()), 0).unwrap());
+        assert_eq!((2, 3), get_line_and_column(&mut Cursor::new("abc\nяz".as_bytes()), 3 + 1 + 3).unwrap());
+    }
+
+    #[test]
+    fn positions_should_be_resolved_in_any_order() {
+        let xml = XML.replace("BirchStore", "Берёзка");
+
+        let mut source = Cursor::new(xml.as_bytes());
+        source.set_position(42);
+
+        let mut tracker = PositionTracker::new();
+        tracker.mark(300);
+
+        for position in [600, 310, 100, 101, 350, 0, 900, 350, xml.len()] {
+            let expected = get_expected_line_and_column(&xml, position);
+            assert_eq!(expected, tracker.get_line_and_column(&mut source, position).unwrap(), "position {}", position);
+        }
+
+        assert_eq!(42, source.position());
+    }
+
+    fn get_expected_line_and_column(xml: &str, position: usize) -> (usize, usize) {
+        let element_position = xml.bytes().enumerate()
+            .position(|(index, byte)| index >= position && !byte.is_ascii_whitespace())
+            .unwrap_or(xml.len());
+
+        let before = &xml.as_bytes()[..element_position];
+        let line_start = before.iter().rposition(|byte| *byte == b'\n').map(|index| index + 1).unwrap_or(0);
+
+        let line = before.iter().filter(|byte| **byte == b'\n').count() + 1;
+        let column = String::from_utf8_lossy(&before[line_start..]).chars().count() + 1;
+
+        (line, column)
+    }
+}